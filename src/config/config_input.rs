@@ -0,0 +1,339 @@
+use std::collections::BTreeMap;
+
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use serde::{Deserialize, Serialize};
+
+use crate::config::data_locale::UiText;
+
+/// Something the player can do with a key or a mouse button. Systems ask whether an action was
+/// triggered, rather than which keys were pressed, so that the controls can be rebound.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    /// Back out of the game to the main menu, or close the game from the main menu.
+    Back,
+    ToggleFullscreen,
+    OpenSettings,
+    /// Let the dungeon sim continue after it stopped.
+    Continue,
+    /// Click buttons and pick up items.
+    Select,
+    /// Use, equip or consume the item under the cursor.
+    Use,
+    /// Put the item under the cursor in the trash.
+    Delete,
+    /// Held while picking up a stack, to split it in half.
+    Split,
+    /// Turn the item that is being dragged a quarter turn.
+    Rotate,
+    /// Open the menu of what can be done with the item under the cursor.
+    ContextMenu,
+    RecipeBook,
+    /// Move the grid cursor, which plays the part of the mouse on the bag and crafting grids.
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    /// Pick up the item under the cursor, or drop the item that was picked up. Unlike Select, it
+    /// doesn't need to be held down while dragging.
+    Grab,
+    /// Delete the item under the cursor. It has to be triggered twice on the same item.
+    DeleteConfirmed,
+    /// Combine the items in the crafting area.
+    Combine,
+    /// Take the last discarded item back out of the trash.
+    Undo,
+    /// Open the window with every message of the run, which can be filtered and searched.
+    FeedHistory,
+    /// Debug: put a buff on the hero.
+    DebugBuffHero,
+    /// Debug: put a debuff on the enemy.
+    DebugDebuffEnemy,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 22] = [
+        InputAction::Back,
+        InputAction::ToggleFullscreen,
+        InputAction::OpenSettings,
+        InputAction::Continue,
+        InputAction::Select,
+        InputAction::Use,
+        InputAction::Delete,
+        InputAction::Split,
+        InputAction::Rotate,
+        InputAction::ContextMenu,
+        InputAction::RecipeBook,
+        InputAction::CursorUp,
+        InputAction::CursorDown,
+        InputAction::CursorLeft,
+        InputAction::CursorRight,
+        InputAction::Grab,
+        InputAction::DeleteConfirmed,
+        InputAction::Combine,
+        InputAction::Undo,
+        InputAction::FeedHistory,
+        InputAction::DebugBuffHero,
+        InputAction::DebugDebuffEnemy,
+    ];
+
+    /// The name of the action on the settings page.
+    pub fn ui_text(&self) -> UiText {
+        match self {
+            InputAction::Back => UiText::ActionBack,
+            InputAction::ToggleFullscreen => UiText::ActionToggleFullscreen,
+            InputAction::OpenSettings => UiText::ActionOpenSettings,
+            InputAction::Continue => UiText::ActionContinue,
+            InputAction::Select => UiText::ActionSelect,
+            InputAction::Use => UiText::ActionUse,
+            InputAction::Delete => UiText::ActionDelete,
+            InputAction::Split => UiText::ActionSplit,
+            InputAction::Rotate => UiText::ActionRotate,
+            InputAction::ContextMenu => UiText::ActionContextMenu,
+            InputAction::RecipeBook => UiText::ActionRecipeBook,
+            InputAction::CursorUp => UiText::ActionCursorUp,
+            InputAction::CursorDown => UiText::ActionCursorDown,
+            InputAction::CursorLeft => UiText::ActionCursorLeft,
+            InputAction::CursorRight => UiText::ActionCursorRight,
+            InputAction::Grab => UiText::ActionGrab,
+            InputAction::DeleteConfirmed => UiText::ActionDeleteConfirmed,
+            InputAction::Combine => UiText::ActionCombine,
+            InputAction::Undo => UiText::ActionUndo,
+            InputAction::FeedHistory => UiText::ActionFeedHistory,
+            InputAction::DebugBuffHero => UiText::ActionDebugBuffHero,
+            InputAction::DebugDebuffEnemy => UiText::ActionDebugDebuffEnemy,
+        }
+    }
+
+    /// The bindings that are used if the config doesn't bind the action.
+    pub fn default_bindings(&self) -> Vec<Binding> {
+        use InputButton::*;
+        match self {
+            InputAction::Back => vec![Binding::new(Key(KeyCode::Escape))],
+            InputAction::ToggleFullscreen => vec![Binding::new(Key(KeyCode::F11))],
+            InputAction::OpenSettings => vec![Binding::new(Key(KeyCode::F1))],
+            InputAction::Continue => vec![Binding::new(Key(KeyCode::Space))],
+            InputAction::Select => vec![Binding::new(Mouse(MouseButton::Left))],
+            InputAction::Use => vec![
+                Binding::held(vec![KeyCode::LShift], Mouse(MouseButton::Left)),
+                Binding::new(Key(KeyCode::E)),
+            ],
+            InputAction::Delete => vec![Binding::held(
+                vec![KeyCode::LControl, KeyCode::LAlt],
+                Mouse(MouseButton::Left),
+            )],
+            InputAction::Split => vec![Binding::new(Key(KeyCode::LControl))],
+            InputAction::Rotate => vec![
+                Binding::new(Key(KeyCode::R)),
+                Binding::new(Mouse(MouseButton::Right)),
+            ],
+            InputAction::ContextMenu => vec![Binding::new(Mouse(MouseButton::Right))],
+            InputAction::RecipeBook => vec![Binding::new(Key(KeyCode::B))],
+            InputAction::CursorUp => vec![
+                Binding::new(Key(KeyCode::Up)),
+                Binding::new(Key(KeyCode::W)),
+            ],
+            InputAction::CursorDown => vec![
+                Binding::new(Key(KeyCode::Down)),
+                Binding::new(Key(KeyCode::S)),
+            ],
+            InputAction::CursorLeft => vec![
+                Binding::new(Key(KeyCode::Left)),
+                Binding::new(Key(KeyCode::A)),
+            ],
+            InputAction::CursorRight => vec![
+                Binding::new(Key(KeyCode::Right)),
+                Binding::new(Key(KeyCode::D)),
+            ],
+            InputAction::Grab => vec![Binding::new(Key(KeyCode::Return))],
+            InputAction::DeleteConfirmed => vec![
+                Binding::new(Key(KeyCode::Delete)),
+                Binding::new(Key(KeyCode::X)),
+            ],
+            InputAction::Combine => vec![Binding::new(Key(KeyCode::C))],
+            InputAction::Undo => vec![Binding::held(vec![KeyCode::LControl], Key(KeyCode::Z))],
+            InputAction::FeedHistory => vec![Binding::new(Key(KeyCode::H))],
+            InputAction::DebugBuffHero => vec![Binding::new(Key(KeyCode::M))],
+            InputAction::DebugDebuffEnemy => vec![Binding::new(Key(KeyCode::N))],
+        }
+    }
+}
+
+/// A key or a mouse button.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputButton {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl InputButton {
+    pub fn name(&self) -> String {
+        match self {
+            InputButton::Key(key) => format!("{:?}", key),
+            InputButton::Mouse(button) => format!("{:?} Mouse", button),
+        }
+    }
+}
+
+/// A button, along with the keys that must be held down while pressing it, e.g. LShift + Click.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    #[serde(default)]
+    pub held: Vec<KeyCode>,
+    pub button: InputButton,
+}
+
+impl Binding {
+    pub fn new(button: InputButton) -> Self {
+        Binding {
+            held: vec![],
+            button,
+        }
+    }
+
+    pub fn held(held: Vec<KeyCode>, button: InputButton) -> Self {
+        Binding { held, button }
+    }
+
+    pub fn name(&self) -> String {
+        self.held
+            .iter()
+            .map(|key| format!("{:?} + ", key))
+            .collect::<String>()
+            + &self.button.name()
+    }
+
+    fn held_down(&self, keys: &Input<KeyCode>) -> bool {
+        self.held.iter().all(|key| keys.pressed(*key))
+    }
+
+    pub fn just_pressed(&self, keys: &Input<KeyCode>, buttons: &Input<MouseButton>) -> bool {
+        self.held_down(keys)
+            && match self.button {
+                InputButton::Key(key) => keys.just_pressed(key),
+                InputButton::Mouse(button) => buttons.just_pressed(button),
+            }
+    }
+
+    pub fn pressed(&self, keys: &Input<KeyCode>, buttons: &Input<MouseButton>) -> bool {
+        self.held_down(keys)
+            && match self.button {
+                InputButton::Key(key) => keys.pressed(key),
+                InputButton::Mouse(button) => buttons.pressed(button),
+            }
+    }
+
+    /// The held keys don't matter when letting go, so that they can be let go of first.
+    pub fn just_released(&self, keys: &Input<KeyCode>, buttons: &Input<MouseButton>) -> bool {
+        match self.button {
+            InputButton::Key(key) => keys.just_released(key),
+            InputButton::Mouse(button) => buttons.just_released(button),
+        }
+    }
+}
+
+/// Maps actions to the keys and mouse buttons that trigger them. Actions that aren't in the map
+/// keep their default bindings. Use the `Actions` system param to check for actions.
+#[derive(Debug, Deserialize, Serialize, Default, Clone, TypeUuid)]
+#[serde(deny_unknown_fields)]
+#[uuid = "8c2f0b84-4f3e-4a2e-9a27-5d0c6a1e7b31"]
+pub struct InputConfig {
+    #[serde(default)]
+    pub bindings: BTreeMap<InputAction, Vec<Binding>>,
+}
+
+impl InputConfig {
+    pub fn bindings(&self, action: InputAction) -> Vec<Binding> {
+        self.bindings
+            .get(&action)
+            .cloned()
+            .unwrap_or_else(|| action.default_bindings())
+    }
+
+    pub fn just_pressed(
+        &self,
+        action: InputAction,
+        keys: &Input<KeyCode>,
+        buttons: &Input<MouseButton>,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.just_pressed(keys, buttons))
+    }
+
+    pub fn pressed(
+        &self,
+        action: InputAction,
+        keys: &Input<KeyCode>,
+        buttons: &Input<MouseButton>,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.pressed(keys, buttons))
+    }
+
+    pub fn just_released(
+        &self,
+        action: InputAction,
+        keys: &Input<KeyCode>,
+        buttons: &Input<MouseButton>,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.just_released(keys, buttons))
+    }
+}
+
+#[derive(Default)]
+pub struct InputConfigLoader;
+
+impl AssetLoader for InputConfigLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let custom_asset = ron::de::from_bytes::<InputConfig>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(custom_asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["input.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn held_keys_must_be_down() {
+        let config = InputConfig::default();
+        let mut keys = Input::<KeyCode>::default();
+        let mut buttons = Input::<MouseButton>::default();
+        buttons.press(MouseButton::Left);
+        assert!(config.just_pressed(InputAction::Select, &keys, &buttons));
+        assert!(!config.just_pressed(InputAction::Use, &keys, &buttons));
+        keys.press(KeyCode::LShift);
+        assert!(config.just_pressed(InputAction::Use, &keys, &buttons));
+    }
+
+    #[test]
+    fn rebound_actions_lose_their_defaults() {
+        let mut config = InputConfig::default();
+        config.bindings.insert(
+            InputAction::Rotate,
+            vec![Binding::new(InputButton::Key(KeyCode::Q))],
+        );
+        let mut keys = Input::<KeyCode>::default();
+        let buttons = Input::<MouseButton>::default();
+        keys.press(KeyCode::R);
+        assert!(!config.just_pressed(InputAction::Rotate, &keys, &buttons));
+        keys.press(KeyCode::Q);
+        assert!(config.just_pressed(InputAction::Rotate, &keys, &buttons));
+    }
+}
@@ -0,0 +1,65 @@
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+
+use bevy::reflect::TypeUuid;
+use serde::{Deserialize, Serialize};
+
+/// A language that the game can be played in. The strings for each language are in their own
+/// directory in assets/locale, named by the language code.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    Portuguese,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::English, Language::German, Language::Portuguese];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::Portuguese => "pt",
+        }
+    }
+
+    /// The name of the language in that language, so that its speakers can find it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::German => "Deutsch",
+            Language::Portuguese => "Português",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, TypeUuid)]
+#[serde(deny_unknown_fields)]
+#[uuid = "3f0c8e54-2b1d-4c8e-9a57-6d1f0b7e4a21"]
+pub struct LocaleConfig {
+    /// The language that the game is shown in. Strings that are missing in it are shown in
+    /// English instead.
+    pub language: Language,
+}
+
+#[derive(Default)]
+pub struct LocaleConfigLoader;
+
+impl AssetLoader for LocaleConfigLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let custom_asset = ron::de::from_bytes::<LocaleConfig>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(custom_asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["locale.ron"]
+    }
+}
@@ -1,35 +1,38 @@
-use std::collections::HashMap;
-
-use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
-
 use serde::{Deserialize, Serialize};
 
-use crate::game::sim::dungeon_components::TextType;
+use crate::game::affixes::{Affix, RarityTier};
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
 
 use bevy::reflect::TypeUuid;
 #[derive(Debug, Deserialize, Serialize, Default, Clone, TypeUuid)]
 #[serde(deny_unknown_fields)]
-#[uuid = "9141eb1b-2cde-453a-8886-9757a7b17f9b"]
-pub struct TextsData {
-    pub map: HashMap<TextType, Vec<String>>,
+#[uuid = "3f0f9a52-7d4b-4a8e-9c41-b2d6e1a0c5f7"]
+pub struct AffixesData {
+    /// The rarities that loot can roll, each with the chance that it is picked.
+    pub rarities: Vec<RarityTier>,
+    /// Affixes that go in front of the item name, such as "Keen".
+    pub prefixes: Vec<Affix>,
+    /// Affixes that go after the item name, such as "of Vitality".
+    pub suffixes: Vec<Affix>,
 }
+
 #[derive(Default)]
-pub struct TextsDataLoader;
+pub struct AffixesDataLoader;
 
-impl AssetLoader for TextsDataLoader {
+impl AssetLoader for AffixesDataLoader {
     fn load<'a>(
         &'a self,
         bytes: &'a [u8],
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
         Box::pin(async move {
-            let custom_asset = ron::de::from_bytes::<TextsData>(bytes)?;
+            let custom_asset = ron::de::from_bytes::<AffixesData>(bytes)?;
             load_context.set_default_asset(LoadedAsset::new(custom_asset));
             Ok(())
         })
     }
 
     fn extensions(&self) -> &[&str] {
-        &["texts.ron"]
+        &["affixes.ron"]
     }
 }
@@ -1,8 +1,9 @@
+use bevy::prelude::warn;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 use crate::game::items::{Item, ItemId};
-use crate::positioning::Dimens;
+use crate::positioning::{Coords, Dimens};
 
 use bevy::reflect::TypeUuid;
 #[derive(Debug, Deserialize, Serialize, Default, Clone, TypeUuid)]
@@ -26,6 +27,32 @@ impl ItemsData {
             .find(|(_, item)| item.id == item_id)
             .cloned()
     }
+
+    /// Drops the shapes that don't fit in a Coords mask, so those items fill their whole
+    /// rectangle instead, and warns about them and about cells outside of the dimens.
+    pub fn check_shapes(&mut self) {
+        for (dimens, item) in self.items.iter_mut() {
+            let cells = match &item.shape {
+                Some(cells) => cells,
+                None => continue,
+            };
+            if !Coords::fits_mask(*dimens) {
+                warn!(
+                    "{:?} is {} by {}, too big for a shape, so it fills its whole rectangle.",
+                    item.id, dimens.x, dimens.y
+                );
+                item.shape = None;
+            } else if cells
+                .iter()
+                .any(|cell| cell.x < 0 || cell.x >= dimens.x || cell.y < 0 || cell.y >= dimens.y)
+            {
+                warn!(
+                    "{:?} has shape cells outside of its {} by {} dimens, they are ignored.",
+                    item.id, dimens.x, dimens.y
+                );
+            }
+        }
+    }
 }
 #[derive(Default)]
 pub struct ItemsDataLoader;
@@ -37,7 +64,8 @@ impl AssetLoader for ItemsDataLoader {
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
         Box::pin(async move {
-            let custom_asset = ron::de::from_bytes::<ItemsData>(bytes)?;
+            let mut custom_asset = ron::de::from_bytes::<ItemsData>(bytes)?;
+            custom_asset.check_shapes();
             load_context.set_default_asset(LoadedAsset::new(custom_asset));
             Ok(())
         })
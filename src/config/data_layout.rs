@@ -63,6 +63,23 @@ pub struct ColumnMiddle {
     pub width: f32,
     pub toasts: Container,
     pub inventory: Container,
+    /// The panel that shows the enemy during a fight, along the top of the column. Layouts that
+    /// leave it out get a panel of the default height.
+    #[serde(default)]
+    pub encounter: Container,
+}
+
+const DEFAULT_ENCOUNTER_HEIGHT: f32 = 2.;
+
+impl ColumnMiddle {
+    /// Calculate the y-position of the encounter widget, while it is shown.
+    pub fn encounter_y(&self, layout: &LayoutData) -> f32 {
+        layout.screen_dimens.y - self.encounter.margin_top.unwrap_or(0.) - self.encounter_height()
+    }
+    /// Calculate the height of the encounter widget.
+    pub fn encounter_height(&self) -> f32 {
+        self.encounter.height.unwrap_or(DEFAULT_ENCOUNTER_HEIGHT)
+    }
 }
 
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
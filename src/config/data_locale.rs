@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::affixes::AffixId;
+use crate::game::combat::EnemyId;
+use crate::game::sim::dungeon_components::TextType;
+use crate::game::ItemId;
+
+use bevy::reflect::TypeUuid;
+
+/// The strings of one language, keyed by stable ids. The strings of a language are split over
+/// several files in its directory in assets/locale, which are merged after loading.
+#[derive(Debug, Deserialize, Serialize, Default, Clone, TypeUuid)]
+#[serde(deny_unknown_fields, default)]
+#[uuid = "a51c2d7e-96f4-4b0a-8e3c-1d7b5f2c9e64"]
+pub struct LocaleData {
+    /// The messages in the feed. One of the lines is picked at random.
+    pub texts: HashMap<TextType, Vec<String>>,
+    pub items: HashMap<ItemId, ItemText>,
+    /// The names of the affixes, which go into the names of the items that rolled them.
+    pub affixes: HashMap<AffixId, String>,
+    pub enemies: HashMap<EnemyId, String>,
+    pub ui: HashMap<UiText, String>,
+}
+
+impl LocaleData {
+    /// Adds the strings of another file of the same language.
+    pub fn merge(&mut self, other: LocaleData) {
+        self.texts.extend(other.texts);
+        self.items.extend(other.items);
+        self.affixes.extend(other.affixes);
+        self.enemies.extend(other.enemies);
+        self.ui.extend(other.ui);
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ItemText {
+    pub name: String,
+    pub description: String,
+}
+
+/// The labels and messages of the user interface. Some of them have placeholders in braces,
+/// such as "{value}", which are filled in when they are shown.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UiText {
+    ContinuePrompt,
+    StatMaxHealth,
+    StatProficiency,
+    StatDamageRes,
+    StatDamageBonus,
+    EquippedArmour,
+    EquippedShield,
+    EquippedWeapon,
+    NothingEquipped,
+    SlotArmour,
+    SlotShield,
+    SlotWeapon,
+    TooltipStats,
+    TooltipComparedTo,
+    TooltipNothingEquipped,
+    TooltipNoChange,
+    TooltipAfterUse,
+    TooltipWhileItLasts,
+    TooltipIngredientIn,
+    MenuUse,
+    MenuEquip,
+    MenuSplit,
+    MenuMoveToCrafting,
+    MenuMoveToBag,
+    MenuDiscard,
+    MenuInspect,
+    Trash,
+    GameWon,
+    GameLost,
+    BackToMenu,
+    Settings,
+    SettingsLanguage,
+    SettingsControls,
+    SettingsPressAKey,
+    SettingsRebind,
+    SettingsAdd,
+    SettingsReset,
+    SettingsCancel,
+    SettingsSave,
+    SettingsResetAll,
+    SettingsClose,
+    HeroName,
+    FallbackEnemy,
+    FallbackDamage,
+    FallbackItem,
+    FallbackDepth,
+    FeedHistory,
+    FeedSearch,
+    FeedCombat,
+    FeedLoot,
+    FeedExploration,
+    FeedInventory,
+    FeedNoMatches,
+    StatNameMaxHealth,
+    StatNameProficiency,
+    StatNameDamageRes,
+    StatNameDamageBonus,
+    StatChange,
+    BreakdownBase,
+    BreakdownTotal,
+    TemporaryEffect,
+    EffectLasts,
+    DurationStep,
+    DurationSteps,
+    DurationRound,
+    DurationRounds,
+    DurationRoom,
+    DurationRooms,
+    RarityCommon,
+    RarityUncommon,
+    RarityRare,
+    RaritySlot,
+    AffixPrefix,
+    AffixSuffix,
+    Durability,
+    PreviewHealth,
+    PreviewStat,
+    InspectThrowDamage,
+    InspectStack,
+    NoRoomToSplit,
+    NoRoomToMove,
+    NoRoomToUnequip,
+    GearBreaks,
+    TrashConfirm,
+    TrashThrown,
+    TrashNoRoom,
+    TrashSalvaged,
+    EncounterStats,
+    ThrowAtEnemy,
+    ThrowDamage,
+    BagGrown,
+    BagNoRoom,
+    BagInTheWay,
+    RecipeLearned,
+    RecipeBookTitle,
+    RecipeBookTitleEmpty,
+    CraftingNeedsMore,
+    SettingsSaved,
+    SettingsSaveFailed,
+    SettingsCantSave,
+    ActionBack,
+    ActionToggleFullscreen,
+    ActionOpenSettings,
+    ActionContinue,
+    ActionSelect,
+    ActionUse,
+    ActionDelete,
+    ActionSplit,
+    ActionRotate,
+    ActionContextMenu,
+    ActionRecipeBook,
+    ActionCursorUp,
+    ActionCursorDown,
+    ActionCursorLeft,
+    ActionCursorRight,
+    ActionGrab,
+    ActionDeleteConfirmed,
+    ActionCombine,
+    ActionUndo,
+    ActionFeedHistory,
+    ActionDebugBuffHero,
+    ActionDebugDebuffEnemy,
+}
+
+impl UiText {
+    pub const ALL: [UiText; 122] = [
+        UiText::ContinuePrompt,
+        UiText::StatMaxHealth,
+        UiText::StatProficiency,
+        UiText::StatDamageRes,
+        UiText::StatDamageBonus,
+        UiText::EquippedArmour,
+        UiText::EquippedShield,
+        UiText::EquippedWeapon,
+        UiText::NothingEquipped,
+        UiText::SlotArmour,
+        UiText::SlotShield,
+        UiText::SlotWeapon,
+        UiText::TooltipStats,
+        UiText::TooltipComparedTo,
+        UiText::TooltipNothingEquipped,
+        UiText::TooltipNoChange,
+        UiText::TooltipAfterUse,
+        UiText::TooltipWhileItLasts,
+        UiText::TooltipIngredientIn,
+        UiText::MenuUse,
+        UiText::MenuEquip,
+        UiText::MenuSplit,
+        UiText::MenuMoveToCrafting,
+        UiText::MenuMoveToBag,
+        UiText::MenuDiscard,
+        UiText::MenuInspect,
+        UiText::Trash,
+        UiText::GameWon,
+        UiText::GameLost,
+        UiText::BackToMenu,
+        UiText::Settings,
+        UiText::SettingsLanguage,
+        UiText::SettingsControls,
+        UiText::SettingsPressAKey,
+        UiText::SettingsRebind,
+        UiText::SettingsAdd,
+        UiText::SettingsReset,
+        UiText::SettingsCancel,
+        UiText::SettingsSave,
+        UiText::SettingsResetAll,
+        UiText::SettingsClose,
+        UiText::HeroName,
+        UiText::FallbackEnemy,
+        UiText::FallbackDamage,
+        UiText::FallbackItem,
+        UiText::FallbackDepth,
+        UiText::FeedHistory,
+        UiText::FeedSearch,
+        UiText::FeedCombat,
+        UiText::FeedLoot,
+        UiText::FeedExploration,
+        UiText::FeedInventory,
+        UiText::FeedNoMatches,
+        UiText::StatNameMaxHealth,
+        UiText::StatNameProficiency,
+        UiText::StatNameDamageRes,
+        UiText::StatNameDamageBonus,
+        UiText::StatChange,
+        UiText::BreakdownBase,
+        UiText::BreakdownTotal,
+        UiText::TemporaryEffect,
+        UiText::EffectLasts,
+        UiText::DurationStep,
+        UiText::DurationSteps,
+        UiText::DurationRound,
+        UiText::DurationRounds,
+        UiText::DurationRoom,
+        UiText::DurationRooms,
+        UiText::RarityCommon,
+        UiText::RarityUncommon,
+        UiText::RarityRare,
+        UiText::RaritySlot,
+        UiText::AffixPrefix,
+        UiText::AffixSuffix,
+        UiText::Durability,
+        UiText::PreviewHealth,
+        UiText::PreviewStat,
+        UiText::InspectThrowDamage,
+        UiText::InspectStack,
+        UiText::NoRoomToSplit,
+        UiText::NoRoomToMove,
+        UiText::NoRoomToUnequip,
+        UiText::GearBreaks,
+        UiText::TrashConfirm,
+        UiText::TrashThrown,
+        UiText::TrashNoRoom,
+        UiText::TrashSalvaged,
+        UiText::EncounterStats,
+        UiText::ThrowAtEnemy,
+        UiText::ThrowDamage,
+        UiText::BagGrown,
+        UiText::BagNoRoom,
+        UiText::BagInTheWay,
+        UiText::RecipeLearned,
+        UiText::RecipeBookTitle,
+        UiText::RecipeBookTitleEmpty,
+        UiText::CraftingNeedsMore,
+        UiText::SettingsSaved,
+        UiText::SettingsSaveFailed,
+        UiText::SettingsCantSave,
+        UiText::ActionBack,
+        UiText::ActionToggleFullscreen,
+        UiText::ActionOpenSettings,
+        UiText::ActionContinue,
+        UiText::ActionSelect,
+        UiText::ActionUse,
+        UiText::ActionDelete,
+        UiText::ActionSplit,
+        UiText::ActionRotate,
+        UiText::ActionContextMenu,
+        UiText::ActionRecipeBook,
+        UiText::ActionCursorUp,
+        UiText::ActionCursorDown,
+        UiText::ActionCursorLeft,
+        UiText::ActionCursorRight,
+        UiText::ActionGrab,
+        UiText::ActionDeleteConfirmed,
+        UiText::ActionCombine,
+        UiText::ActionUndo,
+        UiText::ActionFeedHistory,
+        UiText::ActionDebugBuffHero,
+        UiText::ActionDebugDebuffEnemy,
+    ];
+}
+
+#[derive(Default)]
+pub struct LocaleDataLoader;
+
+impl AssetLoader for LocaleDataLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let custom_asset = ron::de::from_bytes::<LocaleData>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(custom_asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["strings.ron"]
+    }
+}
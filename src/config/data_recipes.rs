@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use crate::game::items::ItemId;
 use crate::game::recipes::Recipe;
 use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
 
@@ -11,6 +12,17 @@ pub struct RecipesData {
     pub recipes: Vec<Recipe>,
 }
 
+impl RecipesData {
+    /// Returns all recipes that are taught by consuming the given item.
+    pub fn taught_by(&self, item_id: &ItemId) -> Vec<Recipe> {
+        self.recipes
+            .iter()
+            .filter(|recipe| recipe.taught_by.contains(item_id))
+            .cloned()
+            .collect()
+    }
+}
+
 #[derive(Default)]
 pub struct RecipesDataLoader;
 
@@ -1,9 +1,12 @@
 pub mod config_audio;
 pub mod config_debug;
+pub mod config_input;
+pub mod config_locale;
 pub mod config_sim;
+pub mod data_affixes;
 pub mod data_blueprint;
 pub mod data_enemies;
 pub mod data_items;
 pub mod data_layout;
+pub mod data_locale;
 pub mod data_recipes;
-pub mod data_texts;
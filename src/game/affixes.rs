@@ -0,0 +1,240 @@
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::data_affixes::AffixesData;
+use crate::config::data_locale::UiText;
+use crate::game::{EquipmentSlot, Item, StatBonus};
+use crate::locale::Locale;
+
+/// How special a piece of loot is. Rarer items have more affixes.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+}
+
+impl Rarity {
+    pub fn ui_text(&self) -> UiText {
+        match self {
+            Rarity::Common => UiText::RarityCommon,
+            Rarity::Uncommon => UiText::RarityUncommon,
+            Rarity::Rare => UiText::RarityRare,
+        }
+    }
+
+    /// The kind of gear along with its rarity, e.g. "Rare Weapon". Common gear is only named by
+    /// its slot.
+    pub fn slot_name(&self, slot: EquipmentSlot, locale: &Locale) -> String {
+        let slot = locale.ui(slot.ui_text());
+        match self {
+            Rarity::Common => slot,
+            rarity => locale.ui_with(
+                UiText::RaritySlot,
+                &[("rarity", locale.ui(rarity.ui_text())), ("slot", slot)],
+            ),
+        }
+    }
+
+    /// The colour of the border around the item, and of its name in the tooltip.
+    /// Common items don't stand out.
+    pub fn colour(&self) -> Option<Color> {
+        match self {
+            Rarity::Common => None,
+            Rarity::Uncommon => Some(Color::rgb(0.35, 0.85, 0.35)),
+            Rarity::Rare => Some(Color::rgb(0.35, 0.55, 1.)),
+        }
+    }
+}
+
+/// One of the rarities that loot can roll.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RarityTier {
+    pub rarity: Rarity,
+    /// The chance of rolling this tier, relative to the weights of the other tiers.
+    pub weight: u32,
+    /// How many affixes the item gets: at most one prefix and one suffix.
+    pub affixes: usize,
+}
+
+/// The affixes that loot can roll. Their names are in the strings of each language, see
+/// `Locale::item_name`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AffixId {
+    Keen,
+    Brutal,
+    Sturdy,
+    Nimble,
+    Vitality,
+    Might,
+    Warding,
+}
+
+/// The affixes that an item rolled, which are named along with it.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Affixes {
+    pub prefix: Option<AffixId>,
+    pub suffix: Option<AffixId>,
+}
+
+/// A random bonus that is added to a piece of loot, along with a change to its name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Affix {
+    pub id: AffixId,
+    pub stat_bonuses: StatBonus,
+    /// The kinds of gear that can roll this affix. Any gear can if this is empty.
+    #[serde(default)]
+    pub slots: Vec<EquipmentSlot>,
+}
+
+impl Affix {
+    fn fits(&self, slot: EquipmentSlot) -> bool {
+        self.slots.is_empty() || self.slots.contains(&slot)
+    }
+}
+
+/// Rolls the rarity and affixes of a freshly found item. Only gear is rolled; everything else is
+/// always common, so that it keeps stacking with its copies.
+pub fn roll_item(item: Item, data: &AffixesData, rng: &mut impl Rng) -> Item {
+    let slot = match item.wearable {
+        Some(slot) => slot,
+        None => return item,
+    };
+    let tier = match data.rarities.choose_weighted(rng, |tier| tier.weight).ok() {
+        Some(tier) => tier,
+        None => return item,
+    };
+    let mut lists = [(true, &data.prefixes), (false, &data.suffixes)];
+    lists.shuffle(rng);
+    let mut rolled = Affixes::default();
+    let mut stat_bonuses = item.stat_bonuses.unwrap_or_default();
+    for (is_prefix, affixes) in lists.into_iter().take(tier.affixes) {
+        let fitting: Vec<&Affix> = affixes.iter().filter(|affix| affix.fits(slot)).collect();
+        if let Some(affix) = fitting.choose(rng) {
+            if is_prefix {
+                rolled.prefix = Some(affix.id);
+            } else {
+                rolled.suffix = Some(affix.id);
+            }
+            stat_bonuses += affix.stat_bonuses;
+        }
+    }
+    Item {
+        affixes: rolled,
+        stat_bonuses: Some(stat_bonuses),
+        rarity: tier.rarity,
+        ..item
+    }
+}
+
+/// Draws a frame in the colour of the item's rarity along the edges of its sprite.
+pub fn spawn_rarity_border(parent: &mut ChildBuilder, size: Vec2, colour: Color) {
+    let thickness = 0.06;
+    let edges = [
+        (
+            Vec2::new(0., (size.y - thickness) * 0.5),
+            Vec2::new(size.x, thickness),
+        ),
+        (
+            Vec2::new(0., (thickness - size.y) * 0.5),
+            Vec2::new(size.x, thickness),
+        ),
+        (
+            Vec2::new((thickness - size.x) * 0.5, 0.),
+            Vec2::new(thickness, size.y),
+        ),
+        (
+            Vec2::new((size.x - thickness) * 0.5, 0.),
+            Vec2::new(thickness, size.y),
+        ),
+    ];
+    for (offset, edge_size) in edges {
+        parent
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: colour,
+                    custom_size: Some(edge_size),
+                    ..default()
+                },
+                transform: Transform::from_translation(offset.extend(0.5)),
+                ..default()
+            })
+            .insert(Name::new("RarityBorder"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::ItemId;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn affix(id: AffixId, proficiency: i32, slots: Vec<EquipmentSlot>) -> Affix {
+        Affix {
+            id,
+            stat_bonuses: StatBonus {
+                proficiency,
+                ..default()
+            },
+            slots,
+        }
+    }
+
+    fn only_rare() -> AffixesData {
+        AffixesData {
+            rarities: vec![RarityTier {
+                rarity: Rarity::Rare,
+                weight: 1,
+                affixes: 2,
+            }],
+            prefixes: vec![
+                affix(AffixId::Keen, 1, vec![EquipmentSlot::Weapon]),
+                affix(AffixId::Sturdy, 5, vec![EquipmentSlot::Armour]),
+            ],
+            suffixes: vec![affix(AffixId::Might, 2, vec![])],
+        }
+    }
+
+    #[test]
+    fn rare_gear_gets_a_prefix_and_a_suffix() {
+        let sword = Item {
+            id: ItemId::Sword,
+            name: "Sword".to_string(),
+            wearable: Some(EquipmentSlot::Weapon),
+            stat_bonuses: Some(StatBonus {
+                proficiency: 2,
+                ..default()
+            }),
+            ..default()
+        };
+        let rolled = roll_item(sword, &only_rare(), &mut StdRng::seed_from_u64(7));
+        assert_eq!(
+            rolled.affixes,
+            Affixes {
+                prefix: Some(AffixId::Keen),
+                suffix: Some(AffixId::Might),
+            }
+        );
+        assert_eq!(
+            rolled.display_name(&Locale::english()),
+            "Keen Sword of Might"
+        );
+        assert_eq!(rolled.stat_bonuses.unwrap().proficiency, 5);
+        assert_eq!(rolled.rarity, Rarity::Rare);
+    }
+
+    #[test]
+    fn only_gear_is_rolled() {
+        let herb = Item {
+            id: ItemId::HerbRed,
+            ..default()
+        };
+        let rolled = roll_item(herb, &only_rare(), &mut StdRng::seed_from_u64(7));
+        assert_eq!(rolled.affixes, Affixes::default());
+        assert_eq!(rolled.rarity, Rarity::Common);
+    }
+}
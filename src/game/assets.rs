@@ -6,13 +6,15 @@ use bevy_kira_audio::AudioSource;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
+use crate::config::config_locale::{Language, LocaleConfig};
+use crate::config::data_affixes::AffixesData;
 use crate::config::data_blueprint::BlueprintData;
 use crate::config::data_enemies::EnemiesData;
 use crate::config::data_items::ItemsData;
 use crate::config::data_layout::LayoutData;
+use crate::config::data_locale::LocaleData;
 use crate::config::data_recipes::RecipesData;
-use crate::config::data_texts::TextsData;
-use crate::{AudioConfig, DebugConfig, SimConfig};
+use crate::{AudioConfig, DebugConfig, InputConfig, SimConfig};
 
 #[derive(Default, Debug)]
 pub struct AssetStorage {
@@ -23,13 +25,17 @@ pub struct AssetStorage {
     fonts: HashMap<FontId, Handle<Font>>,
     pub audio: Handle<AudioConfig>,
     pub debug: Handle<DebugConfig>,
+    pub input: Handle<InputConfig>,
     pub sim: Handle<SimConfig>,
+    pub affixes: Handle<AffixesData>,
     pub blueprint: Handle<BlueprintData>,
     pub enemies: Handle<EnemiesData>,
     pub items: Handle<ItemsData>,
     pub layout: Handle<LayoutData>,
     pub recipes: Handle<RecipesData>,
-    pub texts: Handle<TextsData>,
+    pub locale: Handle<LocaleConfig>,
+    /// The files with the strings of each language.
+    pub strings: Vec<(Language, Handle<LocaleData>)>,
 }
 
 impl AssetStorage {
@@ -150,13 +156,16 @@ impl AssetStorage {
         );
         vec.push(self.audio.clone().id);
         vec.push(self.debug.clone().id);
+        vec.push(self.input.clone().id);
         vec.push(self.sim.clone().id);
+        vec.push(self.affixes.clone().id);
         vec.push(self.blueprint.clone().id);
         vec.push(self.enemies.clone().id);
         vec.push(self.items.clone().id);
         vec.push(self.layout.clone().id);
         vec.push(self.recipes.clone().id);
-        vec.push(self.texts.clone().id);
+        vec.push(self.locale.clone().id);
+        vec.append(&mut self.strings.iter().map(|(_, handle)| handle.id).collect());
         vec
     }
 }
@@ -0,0 +1,251 @@
+use std::cmp::Reverse;
+
+use bevy::prelude::*;
+
+use crate::audio::sound_event::SoundEvent;
+use crate::game::items::{item_rotation, CraftItem, FallingItem, Item, ItemKind, Rotated};
+use crate::game::occupancy::Occupancy;
+use crate::game::{AssetStorage, BeingDragged, CleanupOnGameplayEnd, Silhouette, SoundId};
+use crate::mouse::MouseInteractive;
+use crate::positioning::{Coords, Depth, GridData, Pos};
+
+/// Marker component for the button that sorts the inventory.
+#[derive(Component)]
+pub struct SortButton;
+
+/// An item that is about to be repacked.
+pub struct SortEntry {
+    pub entity: Entity,
+    /// The cells the item takes up at the origin, in its original orientation.
+    pub footprint: Coords,
+    pub kind: ItemKind,
+    pub name: String,
+}
+
+/// Packs the items into the bag from scratch, using a first-fit decreasing heuristic: items are
+/// grouped by kind, and within a group the biggest ones go first. Each item goes in the first
+/// spot where it fits, turning it if needed. Pockets that are meant for the item are tried before
+/// the inventory. Spots are tried column by column, so that every group ends up next to the
+/// previous one.
+///
+/// Returns where each item goes and whether it is turned, or None if not every item fits.
+pub fn pack_items(grid: &GridData, entries: &[SortEntry]) -> Option<Vec<(Entity, Coords, bool)>> {
+    let mut order: Vec<&SortEntry> = entries.iter().collect();
+    order.sort_by_key(|entry| {
+        (
+            entry.kind,
+            Reverse(entry.footprint.cells().count()),
+            Reverse(entry.footprint.dimens.x.max(entry.footprint.dimens.y)),
+            entry.name.clone(),
+        )
+    });
+    let mut packed = Occupancy::default();
+    let mut placements = vec![];
+    for entry in order {
+        let turned = entry.footprint.turned(true);
+        let mut orientations = vec![(entry.footprint, false)];
+        if turned != entry.footprint {
+            orientations.push((turned, true));
+        }
+        let spot = grid.storage_for(entry.kind).into_iter().find_map(|area| {
+            (area.pos.x..area.pos.x + area.dimens.x)
+                .flat_map(|x| (area.pos.y..area.pos.y + area.dimens.y).map(move |y| Pos::new(x, y)))
+                .flat_map(|pos| {
+                    orientations
+                        .iter()
+                        .map(move |(footprint, rotated)| (Coords { pos, ..*footprint }, *rotated))
+                })
+                .find(|(coords, _)| area.encloses(coords) && packed.is_free(coords, &[]))
+        })?;
+        packed.insert(entry.entity, spot.0);
+        placements.push((entry.entity, spot.0, spot.1));
+    }
+    Some(placements)
+}
+
+/// Repacks the bag when the sort button is clicked. Items fly to their new spots.
+/// Nothing happens while items are moving, and nothing moves if the items can't all be packed.
+pub fn auto_sort_inventory(
+    mut commands: Commands,
+    mut audio: EventWriter<SoundEvent>,
+    assets: Res<AssetStorage>,
+    grid: Res<GridData>,
+    query_button: Query<&MouseInteractive, With<SortButton>>,
+    mut query_items: Query<
+        (
+            Entity,
+            &Item,
+            &mut Coords,
+            &mut Transform,
+            &mut MouseInteractive,
+            Option<&Rotated>,
+        ),
+        (Without<CraftItem>, Without<SortButton>),
+    >,
+    query_busy: Query<(), Or<(With<FallingItem>, With<BeingDragged>)>>,
+) {
+    if !query_button.iter().any(|button| button.clicked) || !query_busy.is_empty() {
+        return;
+    }
+    let entries: Vec<SortEntry> = query_items
+        .iter()
+        .map(|(entity, item, coords, _, _, rotated)| {
+            let dimens = if rotated.is_some() {
+                coords.dimens.swapped()
+            } else {
+                coords.dimens
+            };
+            SortEntry {
+                entity,
+                footprint: item.footprint(dimens),
+                kind: item.kind(),
+                name: item.name.clone(),
+            }
+        })
+        .collect();
+    let placements = match pack_items(&grid, &entries) {
+        Some(placements) => placements,
+        None => {
+            audio.send(SoundEvent::Sfx(SoundId::CombineCant));
+            return;
+        }
+    };
+    for (entity, new_coords, rotated) in placements {
+        if let Ok((_, item, mut coords, mut transform, mut interactive, was_rotated)) =
+            query_items.get_mut(entity)
+        {
+            if *coords == new_coords && was_rotated.is_some() == rotated {
+                continue;
+            }
+            let source = coords.pos.as_vec2() + grid.offset;
+            let sprite_size = if rotated {
+                new_coords.dimens.swapped()
+            } else {
+                new_coords.dimens
+            };
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(sprite_size.as_vec2()),
+                        ..default()
+                    },
+                    texture: assets.texture(&item.texture_id),
+                    transform: Transform::from_xyz(
+                        grid.calc_x(&coords),
+                        grid.calc_y(&coords),
+                        Depth::FloatingItem.z(),
+                    )
+                    .with_rotation(item_rotation(rotated)),
+                    ..default()
+                })
+                .insert(Name::new("FallingItem"))
+                .insert(FallingItem::new(
+                    new_coords,
+                    source,
+                    new_coords.pos.as_vec2() + grid.offset,
+                    0.5,
+                ))
+                .insert(CleanupOnGameplayEnd);
+            *coords = new_coords;
+            transform.translation.x = grid.calc_x(&coords);
+            transform.translation.y = grid.calc_y(&coords);
+            transform.rotation = item_rotation(rotated);
+            interactive.size = coords.dimens.as_vec2();
+            let mut entity_commands = commands.entity(entity);
+            entity_commands.insert(Silhouette);
+            if rotated {
+                entity_commands.insert(Rotated);
+            } else {
+                entity_commands.remove::<Rotated>();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::positioning::{Dimens, Pocket};
+
+    fn bag(dimens: Dimens) -> GridData {
+        GridData {
+            offset: Vec2::ZERO,
+            inventory: Coords::new(Pos::new(0, 0), dimens),
+            crafting: Coords::new(Pos::new(10, 0), Dimens::new(3, 3)),
+            pockets: vec![],
+        }
+    }
+
+    fn entry(id: u32, dimens: Dimens, kind: ItemKind) -> SortEntry {
+        SortEntry {
+            entity: Entity::from_raw(id),
+            footprint: Coords::new(Pos::default(), dimens),
+            kind,
+            name: format!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    fn packs_groups_next_to_each_other() {
+        let grid = bag(Dimens::new(4, 2));
+        let entries = vec![
+            entry(0, Dimens::unit(), ItemKind::Ingredient),
+            entry(1, Dimens::new(1, 2), ItemKind::Gear),
+            entry(2, Dimens::unit(), ItemKind::Ingredient),
+        ];
+        let placements = pack_items(&grid, &entries).unwrap();
+        let pos_of = |id: u32| {
+            placements
+                .iter()
+                .find(|(entity, _, _)| *entity == Entity::from_raw(id))
+                .map(|(_, coords, _)| coords.pos)
+                .unwrap()
+        };
+        // Gear comes first, then the ingredients fill the next column.
+        assert_eq!(pos_of(1), Pos::new(0, 0));
+        assert_eq!(pos_of(0).x, 1);
+        assert_eq!(pos_of(2).x, 1);
+    }
+
+    #[test]
+    fn turns_items_that_only_fit_sideways() {
+        let grid = bag(Dimens::new(3, 1));
+        let entries = vec![entry(0, Dimens::new(1, 3), ItemKind::Gear)];
+        let placements = pack_items(&grid, &entries).unwrap();
+        assert_eq!(placements[0].1.dimens, Dimens::new(3, 1));
+        assert!(placements[0].2);
+    }
+
+    #[test]
+    fn gives_up_if_not_everything_fits() {
+        let grid = bag(Dimens::new(2, 1));
+        let entries = vec![
+            entry(0, Dimens::new(2, 1), ItemKind::Gear),
+            entry(1, Dimens::unit(), ItemKind::Potion),
+        ];
+        assert!(pack_items(&grid, &entries).is_none());
+    }
+
+    #[test]
+    fn fills_dedicated_pockets_first() {
+        let mut grid = bag(Dimens::new(2, 1));
+        grid.pockets.push(Pocket {
+            coords: Coords::new(Pos::new(3, 0), Dimens::new(1, 2)),
+            only: Some(ItemKind::Potion),
+        });
+        let entries = vec![
+            entry(0, Dimens::unit(), ItemKind::Potion),
+            entry(1, Dimens::unit(), ItemKind::Potion),
+            entry(2, Dimens::unit(), ItemKind::Potion),
+            entry(3, Dimens::new(2, 1), ItemKind::Gear),
+        ];
+        // The gear takes up the whole inventory, so the third potion has nowhere to go.
+        assert!(pack_items(&grid, &entries).is_none());
+        let placements = pack_items(&grid, &entries[..3]).unwrap();
+        let in_pocket = placements
+            .iter()
+            .filter(|(_, coords, _)| grid.pockets[0].coords.encloses(coords))
+            .count();
+        assert_eq!(in_pocket, 2);
+    }
+}
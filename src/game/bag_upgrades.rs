@@ -0,0 +1,201 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::audio::sound_event::SoundEvent;
+use crate::config::data_layout::LayoutData;
+use crate::config::data_locale::UiText;
+use crate::game::create_widget_grids::{create_grid, overseer_y};
+use crate::game::feed::{AddFeedItemEvent, FeedCategory, MessageColour};
+use crate::game::item_info_system::TooltipBg;
+use crate::game::{AssetStorage, Eyes, FontId, Item, Overseer, SoundId, Stack};
+use crate::locale::Locale;
+use crate::mouse::MouseInteractive;
+use crate::positioning::{Coords, Dimens, GridData, Pocket, Pos};
+
+/// Makes the bag bigger when the item is used. Upgrades last until the end of the run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BagUpgrade {
+    /// Adds columns to the right of the inventory, and rows on top of it.
+    Grow { columns: i32, rows: i32 },
+    /// Attaches a separate pocket to the bag.
+    Pocket(Pocket),
+}
+
+/// Marker component for the grids that make up the bag: the inventory and its pockets.
+/// These are redrawn whenever the bag changes shape.
+#[derive(Component)]
+pub struct BagGrid;
+
+/// Returns the grid as it would be after the upgrade, or the reason why the upgrade doesn't fit.
+/// The inventory and pockets must stay within the bounds, and may not overlap each other or the
+/// crafting area.
+pub fn upgrade_grid(
+    grid: &GridData,
+    upgrade: &BagUpgrade,
+    bounds: &Coords,
+) -> Result<GridData, UiText> {
+    let mut upgraded = grid.clone();
+    match upgrade {
+        BagUpgrade::Grow { columns, rows } => {
+            upgraded.inventory.dimens = upgraded.inventory.dimens.plus_xy(*columns, *rows);
+        }
+        BagUpgrade::Pocket(pocket) => upgraded.pockets.push(*pocket),
+    }
+    let areas: Vec<Coords> = std::iter::once(upgraded.inventory)
+        .chain(upgraded.pockets.iter().map(|pocket| pocket.coords))
+        .collect();
+    if areas.iter().any(|area| !bounds.encloses(area)) {
+        return Err(UiText::BagNoRoom);
+    }
+    for (i, area) in areas.iter().enumerate() {
+        if area.overlaps(&upgraded.crafting) || areas[i + 1..].iter().any(|b| area.overlaps(b)) {
+            return Err(UiText::BagInTheWay);
+        }
+    }
+    Ok(upgraded)
+}
+
+/// The area that the bag may grow into: everything to the left of the crafting area, up to the
+/// point where the overseer would no longer fit on the screen above the inventory.
+pub fn bag_bounds(grid: &GridData, layout: &LayoutData) -> Coords {
+    let overseer_height = layout.middle_width() * 0.3;
+    let max_height =
+        layout.screen_dimens.y - grid.offset.y - overseer_height * (1. - layout.overseer_baseline);
+    Coords::new(
+        Pos::new(0, 0),
+        Dimens::new(grid.crafting.pos.x, max_height.floor() as i32),
+    )
+}
+
+/// Applies the bag upgrade when such an item is SHIFT-clicked. The item is only used up if the
+/// upgrade fits.
+pub fn use_bag_upgrades(
+    mut commands: Commands,
+    mut grid: ResMut<GridData>,
+    layout: Res<LayoutData>,
+    mut feed: EventWriter<AddFeedItemEvent>,
+    mut audio: EventWriter<SoundEvent>,
+    locale: Res<Locale>,
+    mut items: Query<(Entity, &Item, &MouseInteractive, &mut Stack)>,
+    tooltips: Query<Entity, With<TooltipBg>>,
+) {
+    for (entity, item, interactive, mut stack) in items.iter_mut() {
+        let upgrade = match &item.bag_upgrade {
+            Some(upgrade) if interactive.shift_clicked => upgrade,
+            _ => continue,
+        };
+        match upgrade_grid(&grid, upgrade, &bag_bounds(&grid, &layout)) {
+            Ok(upgraded) => {
+                *grid = upgraded;
+                feed.send(AddFeedItemEvent {
+                    message: locale
+                        .ui_with(UiText::BagGrown, &[("item", item.display_name(&locale))]),
+                    font: FontId::FiraSansMedium,
+                    colour: MessageColour::MinorPositive,
+                    category: FeedCategory::Inventory,
+                });
+                stack.0 -= 1;
+                if stack.0 <= 0 {
+                    commands.entity(entity).despawn_recursive();
+                    for tooltip in tooltips.iter() {
+                        commands.entity(tooltip).despawn_recursive();
+                    }
+                }
+            }
+            Err(reason) => {
+                audio.send(SoundEvent::Sfx(SoundId::CombineCant));
+                feed.send(AddFeedItemEvent {
+                    message: locale.ui(reason),
+                    font: FontId::FiraSansItalic,
+                    colour: MessageColour::Neutral,
+                    category: FeedCategory::Inventory,
+                });
+            }
+        }
+    }
+}
+
+/// Redraws the tiles of the inventory and its pockets whenever the bag changes shape, and keeps
+/// the overseer perched on top of the inventory.
+pub fn draw_bag_grids(
+    mut commands: Commands,
+    grid: Res<GridData>,
+    layout: Res<LayoutData>,
+    assets: Res<AssetStorage>,
+    query_grids: Query<Entity, With<BagGrid>>,
+    mut query_overseer: Query<(&mut Transform, Option<&Eyes>), Or<(With<Overseer>, With<Eyes>)>>,
+) {
+    if !grid.is_changed() {
+        return;
+    }
+    for entity in query_grids.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let areas = std::iter::once(grid.inventory).chain(grid.pockets.iter().map(|p| p.coords));
+    for area in areas {
+        let offset = grid.offset + area.pos.as_vec2();
+        let grid_entity = create_grid(&mut commands, &assets, &area.dimens, offset);
+        commands.entity(grid_entity).insert(BagGrid);
+    }
+    let overseer_height = layout.middle_width() * 0.3;
+    let overseer_center = overseer_y(&layout, &grid) + overseer_height * 0.5;
+    for (mut transform, eyes) in query_overseer.iter_mut() {
+        // The iris follows the whites of the eyes by itself.
+        transform.translation.y = if eyes.is_some() {
+            overseer_center + 0.32
+        } else {
+            overseer_center
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::ItemKind;
+
+    fn grid() -> GridData {
+        GridData {
+            offset: Vec2::ZERO,
+            inventory: Coords::new(Pos::new(0, 0), Dimens::new(8, 5)),
+            crafting: Coords::new(Pos::new(9, 1), Dimens::new(4, 3)),
+            pockets: vec![],
+        }
+    }
+
+    fn belt() -> BagUpgrade {
+        BagUpgrade::Pocket(Pocket {
+            coords: Coords::new(Pos::new(8, 0), Dimens::new(1, 5)),
+            only: Some(ItemKind::Potion),
+        })
+    }
+
+    #[test]
+    fn grows_until_the_bounds_are_reached() {
+        let bounds = Coords::new(Pos::new(0, 0), Dimens::new(9, 6));
+        let grow = BagUpgrade::Grow {
+            columns: 0,
+            rows: 1,
+        };
+        let upgraded = upgrade_grid(&grid(), &grow, &bounds).unwrap();
+        assert_eq!(upgraded.inventory.dimens, Dimens::new(8, 6));
+        assert!(upgrade_grid(&upgraded, &grow, &bounds).is_err());
+    }
+
+    #[test]
+    fn pockets_may_not_overlap_the_rest_of_the_bag() {
+        let bounds = Coords::new(Pos::new(0, 0), Dimens::new(9, 8));
+        let with_belt = upgrade_grid(&grid(), &belt(), &bounds).unwrap();
+        assert!(with_belt.can_store(
+            &Coords::new(Pos::new(8, 2), Dimens::unit()),
+            ItemKind::Potion
+        ));
+        assert!(!with_belt.can_store(&Coords::new(Pos::new(8, 2), Dimens::unit()), ItemKind::Gear));
+        assert!(upgrade_grid(&with_belt, &belt(), &bounds).is_err());
+        let wider = BagUpgrade::Grow {
+            columns: 1,
+            rows: 0,
+        };
+        assert!(upgrade_grid(&with_belt, &wider, &bounds).is_err());
+    }
+}
@@ -1,13 +1,17 @@
+use std::cmp::Reverse;
+
 use bevy::prelude::*;
 
 use crate::audio::sound_event::SoundEvent;
 use crate::config::data_items::ItemsData;
 use crate::config::data_recipes::RecipesData;
 use crate::game::items::Item;
+use crate::game::occupancy::Occupancy;
+use crate::game::recipe_book::LearnRecipeEvent;
 use crate::game::recipes::Recipe;
-use crate::game::{find_free_space, ItemId, SoundId, SpawnItemEvent};
+use crate::game::{find_free_space, find_stack_with_room, ItemId, SoundId, SpawnItemEvent, Stack};
 use crate::mouse::MouseInteractive;
-use crate::positioning::{Coords, GridData};
+use crate::positioning::{Coords, GridData, Pos};
 
 use super::items::CraftItem;
 
@@ -21,53 +25,115 @@ pub fn combine_items_system(
     mut commands: Commands,
     mut spawn_event_writer: EventWriter<SpawnItemEvent>,
     mut audio: EventWriter<SoundEvent>,
+    mut learn_events: EventWriter<LearnRecipeEvent>,
     recipes_data: Res<RecipesData>,
     items_data: Res<ItemsData>,
     grid: Res<GridData>,
     combine_button_query: Query<&MouseInteractive, With<CombineButton>>,
-    crafting_items_query: Query<(Entity, &Item), With<CraftItem>>,
-    items_query: Query<&Coords, With<Item>>,
+    crafting_items_query: Query<(Entity, &Item, &Coords, &Stack), With<CraftItem>>,
+    occupancy: Res<Occupancy>,
+    stacks_query: Query<(Entity, &Item, &Coords, &Stack)>,
 ) {
     if let Ok(combine_button) = combine_button_query.get_single() {
         if combine_button.clicked {
-            let number_of_crafting_items = crafting_items_query.iter().count();
+            let number_of_crafting_items: i32 = crafting_items_query
+                .iter()
+                .map(|(_, _, _, stack)| stack.0)
+                .sum();
             if number_of_crafting_items <= 1 {
                 return;
             }
 
-            let mut items = Vec::new();
-            for (_, item) in crafting_items_query.iter() {
-                items.push(item.clone());
-            }
+            // A stack counts as that many copies of the item in the same spot.
+            let items: Vec<(Pos, ItemId)> = crafting_items_query
+                .iter()
+                .flat_map(|(_, item, coords, stack)| {
+                    vec![(coords.pos, item.id.clone()); stack.0.max(0) as usize]
+                })
+                .collect();
 
             let possible_recipe = try_get_recipe(&recipes_data, &items);
             trace!("found possible recipe: {:?}", possible_recipe);
 
             if let Some(recipe) = possible_recipe {
                 // debug!("found recipe: {:?}", recipe);
-                if let Some((dimens, item)) = items_data.try_get_item(recipe.result) {
-                    // debug!("got random item: {:?}", item);
-
-                    if let Some(free_coords) = find_free_space(&grid, dimens, &items_query, &[]) {
-                        // ^ this is failing
-                        debug!("found free space to place the item");
-                        // Spawn the result of the recipe
-                        spawn_event_writer.send(SpawnItemEvent::new(
+                // Find space for all of the outputs before touching anything, so that a craft
+                // either completes entirely or not at all.
+                let mut placements: Vec<SpawnItemEvent> = vec![];
+                let mut same_tick_items: Vec<Coords> = vec![];
+                let mut same_tick_stacks: Vec<Entity> = vec![];
+                for output in recipe.outputs() {
+                    let (dimens, item) = match items_data.try_get_item(output.clone()) {
+                        Some(found) => found,
+                        None => {
+                            warn!("Recipe output {:?} is missing from the item data.", output);
+                            return;
+                        }
+                    };
+                    let item = repaired_gear(
+                        &output,
+                        crafting_items_query.iter().map(|(_, item, _, _)| item),
+                    )
+                    .unwrap_or(item);
+                    if let Some((stack, coords)) =
+                        find_stack_with_room(&grid, &item, &stacks_query, &same_tick_stacks)
+                    {
+                        same_tick_stacks.push(stack);
+                        placements.push(SpawnItemEvent::onto_stack(
                             item,
-                            free_coords,
+                            stack,
+                            coords,
                             grid.center_crafting(),
-                            true,
                         ));
-                        // Delete the craft items entities
-                        for (entity, _) in crafting_items_query.iter() {
+                        continue;
+                    }
+                    let footprint = item.footprint(dimens);
+                    match find_free_space(
+                        &grid,
+                        &occupancy,
+                        footprint,
+                        item.kind(),
+                        &same_tick_items,
+                    ) {
+                        Some((free_coords, rotated)) => {
+                            same_tick_items.push(free_coords);
+                            placements.push(
+                                SpawnItemEvent::new(
+                                    item,
+                                    free_coords,
+                                    grid.center_crafting(),
+                                    true,
+                                )
+                                .with_rotation(rotated),
+                            );
+                        }
+                        None => {
+                            warn!("Tried to find free space but failed.");
+                            audio.send(SoundEvent::Sfx(SoundId::CombineCant));
+                            return;
+                        }
+                    }
+                }
+                debug!("found free space to place the items");
+                // Spawn the results of the recipe
+                spawn_event_writer.send_batch(placements.into_iter());
+                // Use up the craft items, except for the catalysts
+                let mut consumed = recipe.consumed_counts();
+                for (entity, item, _, stack) in crafting_items_query.iter() {
+                    if let Some(quantity) = consumed.get_mut(&item.id) {
+                        let used = stack.0.min(*quantity);
+                        *quantity -= used;
+                        if used >= stack.0 {
                             commands.entity(entity).despawn_recursive();
+                        } else if used > 0 {
+                            commands.entity(entity).insert(Stack(stack.0 - used));
                         }
-                        // Alchemy sound not working or extremely low volume?
-                        audio.send(SoundEvent::Sfx(SoundId::CombineAlchemy))
-                    } else {
-                        warn!("Tried to find free space but failed.");
                     }
                 }
+                // Successful experiments are recorded in the recipe book.
+                learn_events.send(LearnRecipeEvent(recipe.clone()));
+                // Alchemy sound not working or extremely low volume?
+                audio.send(SoundEvent::Sfx(SoundId::CombineAlchemy))
             } else {
                 audio.send(SoundEvent::Sfx(SoundId::CombineCant))
             }
@@ -75,29 +141,272 @@ pub fn combine_items_system(
     }
 }
 
-pub fn try_get_recipe(data: &RecipesData, items: &Vec<Item>) -> Option<Recipe> {
-    let mut possible_recipe: Option<Recipe> = None;
+/// If the crafted item is gear that also went into the recipe, that is a repair: the gear comes
+/// out as good as new, but keeps its rarity and affixes. Returns None for anything else, in
+/// which case a fresh item is crafted.
+fn repaired_gear<'a>(
+    output: &ItemId,
+    mut ingredients: impl Iterator<Item = &'a Item>,
+) -> Option<Item> {
+    let mut gear = ingredients
+        .find(|item| item.id == *output && item.durability.is_some())?
+        .clone();
+    if let Some(durability) = gear.durability.as_mut() {
+        durability.repair();
+    }
+    Some(gear)
+}
+
+/// Finds the recipe whose ingredients exactly match the given items and their positions in the
+/// crafting area. If several recipes match, the one with the highest priority wins. Shaped recipes
+/// are more specific than shapeless ones, so they win ties. Any remaining ties are resolved by
+/// the order of the recipes in the data file.
+pub fn try_get_recipe(data: &RecipesData, items: &[(Pos, ItemId)]) -> Option<Recipe> {
+    data.recipes
+        .iter()
+        .filter(|recipe| recipe.matches(items))
+        .min_by_key(|recipe| (Reverse(recipe.priority), recipe.shape.is_none()))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::affixes::{AffixId, Affixes, Rarity};
+    use crate::game::durability::Durability;
+    use crate::game::recipes::Ingredient;
+
+    fn shipped_recipes() -> RecipesData {
+        ron::de::from_str(include_str!("../../assets/config/default/data.recipes.ron"))
+            .expect("data.recipes.ron should deserialize")
+    }
+
+    /// Lays the given ingredients out in a row, one item after another.
+    fn in_a_row(ids: &[ItemId]) -> Vec<(Pos, ItemId)> {
+        ids.iter()
+            .enumerate()
+            .map(|(x, id)| (Pos::new(x as i32, 0), id.clone()))
+            .collect()
+    }
+
+    fn flatten(recipe: &Recipe) -> Vec<ItemId> {
+        recipe
+            .ingredients
+            .iter()
+            .flat_map(|ingredient| vec![ingredient.item_id.clone(); ingredient.quantity as usize])
+            .collect()
+    }
+
+    fn recipe(result: ItemId, ingredients: &[(ItemId, i32)], priority: i32) -> Recipe {
+        Recipe {
+            result,
+            ingredients: ingredients
+                .iter()
+                .map(|(item_id, quantity)| Ingredient {
+                    item_id: item_id.clone(),
+                    quantity: *quantity,
+                    catalyst: false,
+                })
+                .collect(),
+            byproducts: vec![],
+            taught_by: vec![],
+            priority,
+            shape: None,
+        }
+    }
 
-    let mut flat_recipe = Vec::<ItemId>::new();
-    let items_ids: Vec<ItemId> = items.into_iter().map(|f| f.id.clone()).collect();
+    #[test]
+    fn every_shipped_recipe_matches_its_own_ingredients() {
+        let data = shipped_recipes();
+        for recipe in &data.recipes {
+            let found = try_get_recipe(&data, &in_a_row(&flatten(recipe)));
+            assert_eq!(found.as_ref(), Some(recipe), "{:?}", recipe.result);
+        }
+    }
 
-    for recipe in &data.recipes {
-        flat_recipe.clear();
-        for ingr in &recipe.ingredients {
-            for _ in 0..(ingr.quantity) {
-                flat_recipe.push(ingr.item_id.clone());
+    #[test]
+    fn no_two_shipped_recipes_share_the_same_ingredients() {
+        let data = shipped_recipes();
+        for (i, a) in data.recipes.iter().enumerate() {
+            for b in data.recipes.iter().skip(i + 1) {
+                assert!(
+                    a.ingredient_counts() != b.ingredient_counts() || a.priority != b.priority,
+                    "{:?} and {:?} are ambiguous",
+                    a.result,
+                    b.result
+                );
             }
         }
-        let difference: Vec<_> = items_ids
-            .clone()
-            .into_iter()
-            .filter(|item| !flat_recipe.contains(item))
-            .collect();
-        if difference.len() == 0 {
-            possible_recipe = Option::from(recipe.clone());
-            break;
+    }
+
+    /// A shaped recipe whose shape doesn't hold exactly its ingredients can never be crafted.
+    fn shape_fits_ingredients(recipe: &Recipe) -> bool {
+        match &recipe.shape {
+            Some(shape) => recipe.matches(shape),
+            None => true,
+        }
+    }
+
+    #[test]
+    fn shipped_shapes_contain_exactly_the_ingredients() {
+        // None of the shipped recipes has a shape yet, so check the check itself as well.
+        let mut shaped = recipe(
+            ItemId::FlaskHealing,
+            &[(ItemId::Vial, 1), (ItemId::HerbRed, 2)],
+            0,
+        );
+        shaped.shape = Some(vec![
+            (Pos::new(0, 0), ItemId::Vial),
+            (Pos::new(0, 2), ItemId::HerbRed),
+            (Pos::new(1, 2), ItemId::HerbRed),
+        ]);
+        assert!(shape_fits_ingredients(&shaped));
+        shaped.shape.as_mut().unwrap().pop();
+        assert!(!shape_fits_ingredients(&shaped));
+        let data = shipped_recipes();
+        for recipe in &data.recipes {
+            assert!(shape_fits_ingredients(recipe), "{:?}", recipe.result);
         }
     }
 
-    possible_recipe
+    #[test]
+    fn missing_ingredients_do_not_match() {
+        let data = shipped_recipes();
+        // Flask of Healing needs two Essences of Vitality.
+        let items = in_a_row(&[ItemId::EssenceVitality]);
+        assert_eq!(try_get_recipe(&data, &items), None);
+        let items = in_a_row(&[ItemId::HerbRed, ItemId::EssenceAlacrity]);
+        assert_eq!(try_get_recipe(&data, &items), None);
+    }
+
+    #[test]
+    fn surplus_ingredients_do_not_match() {
+        let data = shipped_recipes();
+        let items = in_a_row(&[ItemId::Vial, ItemId::HerbRed, ItemId::HerbRed]);
+        assert_eq!(try_get_recipe(&data, &items), None);
+        let items = in_a_row(&[ItemId::Vial, ItemId::HerbRed, ItemId::Vial]);
+        assert_eq!(try_get_recipe(&data, &items), None);
+    }
+
+    #[test]
+    fn unrelated_items_do_not_match() {
+        let data = shipped_recipes();
+        let items = in_a_row(&[ItemId::Vial, ItemId::SwordRusty]);
+        assert_eq!(try_get_recipe(&data, &items), None);
+    }
+
+    #[test]
+    fn highest_priority_wins() {
+        let low = recipe(ItemId::Sword, &[(ItemId::SwordRusty, 2)], 0);
+        let high = recipe(ItemId::Axe, &[(ItemId::SwordRusty, 2)], 5);
+        let data = RecipesData {
+            recipes: vec![low, high.clone()],
+        };
+        let items = in_a_row(&[ItemId::SwordRusty, ItemId::SwordRusty]);
+        assert_eq!(try_get_recipe(&data, &items), Some(high));
+    }
+
+    #[test]
+    fn equal_priorities_fall_back_to_data_order() {
+        let first = recipe(ItemId::Sword, &[(ItemId::SwordRusty, 2)], 0);
+        let second = recipe(ItemId::Axe, &[(ItemId::SwordRusty, 2)], 0);
+        let data = RecipesData {
+            recipes: vec![first.clone(), second],
+        };
+        let items = in_a_row(&[ItemId::SwordRusty, ItemId::SwordRusty]);
+        assert_eq!(try_get_recipe(&data, &items), Some(first));
+    }
+
+    #[test]
+    fn shaped_recipes_require_the_arrangement() {
+        let mut shaped = recipe(
+            ItemId::FlaskHealing,
+            &[(ItemId::Vial, 1), (ItemId::HerbRed, 1)],
+            0,
+        );
+        // The herb goes directly on top of the vial.
+        shaped.shape = Some(vec![
+            (Pos::new(0, 0), ItemId::Vial),
+            (Pos::new(0, 2), ItemId::HerbRed),
+        ]);
+        let shapeless = recipe(
+            ItemId::EssenceVitality,
+            &[(ItemId::Vial, 1), (ItemId::HerbRed, 1)],
+            0,
+        );
+        let data = RecipesData {
+            recipes: vec![shapeless.clone(), shaped.clone()],
+        };
+        let stacked = vec![
+            (Pos::new(11, 1), ItemId::Vial),
+            (Pos::new(11, 3), ItemId::HerbRed),
+        ];
+        assert_eq!(try_get_recipe(&data, &stacked), Some(shaped));
+        let side_by_side = vec![
+            (Pos::new(11, 1), ItemId::Vial),
+            (Pos::new(12, 1), ItemId::HerbRed),
+        ];
+        assert_eq!(try_get_recipe(&data, &side_by_side), Some(shapeless));
+    }
+
+    #[test]
+    fn catalysts_are_required_but_not_consumed() {
+        let mut sharpen = recipe(
+            ItemId::Sword,
+            &[(ItemId::SwordRusty, 1), (ItemId::FireEssence, 1)],
+            0,
+        );
+        sharpen.ingredients[1].catalyst = true;
+        let data = RecipesData {
+            recipes: vec![sharpen.clone()],
+        };
+        let items = in_a_row(&[ItemId::SwordRusty]);
+        assert_eq!(try_get_recipe(&data, &items), None);
+        let items = in_a_row(&[ItemId::SwordRusty, ItemId::FireEssence]);
+        assert_eq!(try_get_recipe(&data, &items), Some(sharpen.clone()));
+        let consumed = sharpen.consumed_counts();
+        assert_eq!(consumed.get(&ItemId::SwordRusty), Some(&1));
+        assert_eq!(consumed.get(&ItemId::FireEssence), None);
+    }
+
+    #[test]
+    fn outputs_start_with_the_result() {
+        let mut recipe = recipe(ItemId::FlaskHealing, &[(ItemId::EssenceVitality, 2)], 0);
+        recipe.byproducts = vec![ItemId::Vial];
+        assert_eq!(recipe.outputs(), vec![ItemId::FlaskHealing, ItemId::Vial]);
+    }
+
+    #[test]
+    fn a_single_stack_can_satisfy_an_ingredient_quantity() {
+        let data = shipped_recipes();
+        // A stack of two Essences of Vitality in one slot.
+        let items = vec![(Pos::new(9, 1), ItemId::EssenceVitality); 2];
+        let found = try_get_recipe(&data, &items).map(|recipe| recipe.result);
+        assert_eq!(found, Some(ItemId::FlaskHealing));
+    }
+
+    #[test]
+    fn repairs_keep_the_rolled_gear() {
+        let worn = Item {
+            id: ItemId::Sword,
+            rarity: Rarity::Rare,
+            affixes: Affixes {
+                prefix: Some(AffixId::Keen),
+                suffix: None,
+            },
+            durability: Some(Durability { max: 20, wear: 7 }),
+            ..default()
+        };
+        let scrap = Item {
+            id: ItemId::SwordRusty,
+            durability: Some(Durability { max: 5, wear: 0 }),
+            ..default()
+        };
+        let repaired = repaired_gear(&ItemId::Sword, [&scrap, &worn].into_iter()).unwrap();
+        assert_eq!(repaired.rarity, Rarity::Rare);
+        assert_eq!(repaired.affixes.prefix, Some(AffixId::Keen));
+        assert_eq!(repaired.durability, Some(Durability { max: 20, wear: 0 }));
+        // Crafting new gear from other items is not a repair.
+        assert!(repaired_gear(&ItemId::Axe, [&scrap, &worn].into_iter()).is_none());
+    }
 }
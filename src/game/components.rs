@@ -7,6 +7,9 @@ pub struct Player {
     pub gold: Gold,
 }
 
+#[derive(Component)]
+pub struct Overseer;
+
 #[derive(Component)]
 pub struct Eyes;
 
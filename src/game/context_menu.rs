@@ -0,0 +1,426 @@
+use bevy::prelude::*;
+
+use crate::config::config_input::InputAction;
+use crate::config::data_layout::LayoutData;
+use crate::config::data_locale::UiText;
+use crate::config::data_recipes::RecipesData;
+use crate::game::feed::{AddFeedItemEvent, FeedCategory, MessageColour};
+use crate::game::hero_stats::{spawn_text_tooltip, stat_change_line, HeroStat};
+use crate::game::items::{item_rotation, CraftItem, Item, Rotated, Stack};
+use crate::game::occupancy::Occupancy;
+use crate::game::status_tray::modifier_lines;
+use crate::game::{
+    find_free_space, find_free_space_in, AssetStorage, CleanupOnGameplayEnd, FontId, Silhouette,
+    SpawnItemEvent,
+};
+use crate::input::Actions;
+use crate::locale::Locale;
+use crate::mouse::{Mouse, MouseInteractive};
+use crate::positioning::{Coords, Depth, GridData};
+
+const ENTRY_WIDTH: f32 = 2.4;
+const ENTRY_HEIGHT: f32 = 0.45;
+const PADDING: f32 = 0.1;
+
+/// Something that can be done with an item from its context menu.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MenuAction {
+    Use,
+    Equip,
+    Split,
+    MoveToCrafting,
+    MoveToBag,
+    Discard,
+    Inspect,
+}
+
+impl MenuAction {
+    pub fn label(&self) -> UiText {
+        match self {
+            MenuAction::Use => UiText::MenuUse,
+            MenuAction::Equip => UiText::MenuEquip,
+            MenuAction::Split => UiText::MenuSplit,
+            MenuAction::MoveToCrafting => UiText::MenuMoveToCrafting,
+            MenuAction::MoveToBag => UiText::MenuMoveToBag,
+            MenuAction::Discard => UiText::MenuDiscard,
+            MenuAction::Inspect => UiText::MenuInspect,
+        }
+    }
+}
+
+/// The menu that is opened by right-clicking an item. It closes when something is picked from
+/// it, or when clicking anywhere else.
+#[derive(Component)]
+pub struct ContextMenu {
+    /// The item that the menu is for.
+    pub item: Entity,
+}
+
+/// One of the entries of a context menu.
+#[derive(Component)]
+pub struct ContextMenuEntry(pub MenuAction);
+
+/// Marker component for the panel that shows everything about an item, opened with Inspect.
+#[derive(Component)]
+pub struct InspectPanel;
+
+/// The actions that make sense for the item, in the order they are listed in its menu.
+pub fn menu_actions(
+    item: &Item,
+    count: i32,
+    in_crafting: bool,
+    teaches_recipes: bool,
+) -> Vec<MenuAction> {
+    let mut actions = vec![];
+    if item.wearable.is_some() {
+        actions.push(MenuAction::Equip);
+    } else if item.target.hero()
+        && (item.stat_bonuses.is_some()
+            || item.temporary_effect.is_some()
+            || item.bag_upgrade.is_some()
+            || teaches_recipes)
+    {
+        actions.push(MenuAction::Use);
+    }
+    if count > 1 {
+        actions.push(MenuAction::Split);
+    }
+    actions.push(if in_crafting {
+        MenuAction::MoveToBag
+    } else {
+        MenuAction::MoveToCrafting
+    });
+    actions.push(MenuAction::Discard);
+    actions.push(MenuAction::Inspect);
+    actions
+}
+
+/// Everything there is to know about an item, for the inspect panel.
+pub fn inspect_lines(item: &Item, count: i32, locale: &Locale) -> Vec<String> {
+    let mut lines = vec![item.display_name(locale)];
+    if let Some(slot) = item.wearable {
+        lines.push(item.rarity.slot_name(slot, locale));
+    }
+    // The panel doesn't wrap lines by itself.
+    let mut line = String::new();
+    for word in item.description.split_whitespace() {
+        if !line.is_empty() && line.len() + word.len() > 36 {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    if let Some(durability) = item.durability {
+        lines.push(durability.describe(locale));
+    }
+    if let Some(bonus) = &item.stat_bonuses {
+        lines.extend(
+            HeroStat::ALL
+                .iter()
+                .map(|stat| (stat, stat.of_bonus(bonus)))
+                .filter(|(_, value)| *value != 0)
+                .map(|(stat, value)| stat_change_line(locale.ui(stat.ui_text()), value, locale)),
+        );
+    }
+    if let Some(modifier) = &item.temporary_effect {
+        lines.extend(modifier_lines(modifier, locale).into_iter().skip(1));
+    }
+    if let Some(damage) = item.damage {
+        lines.push(locale.ui_with(
+            UiText::InspectThrowDamage,
+            &[("damage", damage.to_string())],
+        ));
+    }
+    if count > 1 {
+        lines.push(locale.ui_with(UiText::InspectStack, &[("count", count.to_string())]));
+    }
+    lines
+}
+
+/// Opens the context menu of the item under the mouse when the ContextMenu action is triggered
+/// (right-click by default), and closes menus and inspect panels when clicking anywhere else.
+///
+/// Runs on the second mouse stage, right after the hovered entities have been found.
+pub fn open_context_menu(
+    mut commands: Commands,
+    actions: Actions,
+    mouse: Res<Mouse>,
+    assets: Res<AssetStorage>,
+    layout: Res<LayoutData>,
+    recipes_data: Res<RecipesData>,
+    locale: Res<Locale>,
+    query_menus: Query<(Entity, &MouseInteractive), With<ContextMenu>>,
+    query_entries: Query<&MouseInteractive, With<ContextMenuEntry>>,
+    query_inspect: Query<Entity, With<InspectPanel>>,
+    query_items: Query<
+        (Entity, &Item, &Stack, &MouseInteractive, Option<&CraftItem>),
+        Without<Silhouette>,
+    >,
+) {
+    let open = actions.just_pressed(InputAction::ContextMenu) && !mouse.is_dragging;
+    if !open && !actions.just_pressed(InputAction::Select) {
+        return;
+    }
+    let on_menu = query_menus
+        .iter()
+        .any(|(_, interactive)| interactive.hovered)
+        || query_entries.iter().any(|interactive| interactive.hovered);
+    if on_menu {
+        return;
+    }
+    for (menu, _) in query_menus.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+    for panel in query_inspect.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+    if !open {
+        return;
+    }
+    let (entity, item, stack, _, craft_item) = match query_items
+        .iter()
+        .find(|(_, _, _, interactive, _)| interactive.hovered)
+    {
+        Some(hovered) => hovered,
+        None => return,
+    };
+    let entries = menu_actions(
+        item,
+        stack.0,
+        craft_item.is_some(),
+        !recipes_data.taught_by(&item.id).is_empty(),
+    );
+    let size = Vec2::new(
+        ENTRY_WIDTH + PADDING * 2.,
+        entries.len() as f32 * ENTRY_HEIGHT + PADDING * 2.,
+    );
+    // The menu hangs down and to the right of the mouse, unless it would go off the screen.
+    let x = (mouse.position.x + size.x * 0.5).min(layout.screen_dimens.x - size.x * 0.5);
+    let y = (mouse.position.y - size.y * 0.5).max(size.y * 0.5);
+    let text_style = TextStyle {
+        font: assets.font(&FontId::FiraSansMedium),
+        font_size: 50.0,
+        color: Color::ANTIQUE_WHITE,
+    };
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.1, 0.1, 0.1, 0.95),
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_xyz(x, y, Depth::Menu.z()),
+            ..default()
+        })
+        .insert(Name::new("ContextMenu"))
+        .insert(ContextMenu { item: entity })
+        .insert(MouseInteractive::new(size, false))
+        .insert(CleanupOnGameplayEnd)
+        .with_children(|parent| {
+            for (i, action) in entries.iter().enumerate() {
+                let entry_y = size.y * 0.5 - PADDING - (i as f32 + 0.5) * ENTRY_HEIGHT;
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(0.2, 0.2, 0.2, 0.95),
+                            custom_size: Some(Vec2::new(ENTRY_WIDTH, ENTRY_HEIGHT - 0.04)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(0., entry_y, 1.),
+                        ..default()
+                    })
+                    .insert(ContextMenuEntry(*action))
+                    .insert(MouseInteractive::new(
+                        Vec2::new(ENTRY_WIDTH, ENTRY_HEIGHT),
+                        true,
+                    ))
+                    .with_children(|entry_parent| {
+                        entry_parent.spawn_bundle(Text2dBundle {
+                            text: Text::from_section(locale.ui(action.label()), text_style.clone())
+                                .with_alignment(TextAlignment {
+                                    vertical: VerticalAlign::Center,
+                                    horizontal: HorizontalAlign::Left,
+                                }),
+                            transform: Transform::from_xyz(ENTRY_WIDTH * -0.5 + PADDING, 0., 1.)
+                                .with_scale(Vec3::new(
+                                    1. / layout.text_factor,
+                                    1. / layout.text_factor,
+                                    1.,
+                                )),
+                            ..default()
+                        });
+                    });
+            }
+        });
+}
+
+/// Does what was picked from the context menu, and closes it. Using, equipping and discarding
+/// work as if the item was clicked with the matching chord, so they run this very frame.
+///
+/// Runs on the second mouse stage, right after the hovered entities have been found.
+pub fn use_context_menu(
+    mut commands: Commands,
+    assets: Res<AssetStorage>,
+    layout: Res<LayoutData>,
+    grid: Res<GridData>,
+    occupancy: Res<Occupancy>,
+    mut spawn: EventWriter<SpawnItemEvent>,
+    mut feed: EventWriter<AddFeedItemEvent>,
+    locale: Res<Locale>,
+    query_menus: Query<(Entity, &ContextMenu, &Transform)>,
+    mut query_entries: Query<(&ContextMenuEntry, &MouseInteractive, &mut Sprite)>,
+    mut query_items: Query<
+        (
+            &Item,
+            &mut Coords,
+            &mut Transform,
+            &mut Stack,
+            &mut MouseInteractive,
+            Option<&Rotated>,
+        ),
+        (Without<ContextMenuEntry>, Without<ContextMenu>),
+    >,
+) {
+    let mut picked = None;
+    for (entry, interactive, mut sprite) in query_entries.iter_mut() {
+        let colour = if interactive.hovered {
+            Color::rgba(0.35, 0.35, 0.35, 0.95)
+        } else {
+            Color::rgba(0.2, 0.2, 0.2, 0.95)
+        };
+        if sprite.color != colour {
+            sprite.color = colour;
+        }
+        if interactive.clicked {
+            picked = Some(entry.0);
+        }
+    }
+    let (menu_entity, menu, menu_transform) = match (picked, query_menus.get_single()) {
+        (Some(_), Ok(menu)) => menu,
+        _ => return,
+    };
+    commands.entity(menu_entity).despawn_recursive();
+    let (item, mut coords, mut transform, mut stack, mut interactive, rotated) =
+        match query_items.get_mut(menu.item) {
+            Ok(components) => components,
+            Err(_) => return,
+        };
+    let footprint = Coords {
+        pos: coords.pos,
+        ..item.footprint(if rotated.is_some() {
+            coords.dimens.swapped()
+        } else {
+            coords.dimens
+        })
+    };
+    match picked.unwrap() {
+        MenuAction::Use | MenuAction::Equip => interactive.shift_clicked = true,
+        MenuAction::Discard => interactive.ctrl_alt_clicked = true,
+        MenuAction::Split => {
+            let half = stack.0 / 2;
+            match find_free_space(&grid, &occupancy, footprint, item.kind(), &[]) {
+                Some((split_coords, turned)) => {
+                    stack.0 -= half;
+                    spawn.send(
+                        SpawnItemEvent::without_anim(item.clone(), split_coords)
+                            .with_count(half)
+                            .with_rotation(turned),
+                    );
+                }
+                None => feed.send(AddFeedItemEvent {
+                    message: locale.ui_with(
+                        UiText::NoRoomToSplit,
+                        &[("item", item.display_name(&locale))],
+                    ),
+                    font: FontId::FiraSansItalic,
+                    colour: MessageColour::MinorNegative,
+                    category: FeedCategory::Inventory,
+                }),
+            }
+        }
+        action @ (MenuAction::MoveToCrafting | MenuAction::MoveToBag) => {
+            let to_crafting = action == MenuAction::MoveToCrafting;
+            let found = if to_crafting {
+                find_free_space_in(&[grid.crafting], &occupancy, footprint, &[])
+            } else {
+                find_free_space(&grid, &occupancy, footprint, item.kind(), &[])
+            };
+            let (new_coords, turned) = match found {
+                Some(found) => found,
+                None => {
+                    feed.send(AddFeedItemEvent {
+                        message: locale.ui_with(
+                            UiText::NoRoomToMove,
+                            &[("item", item.display_name(&locale))],
+                        ),
+                        font: FontId::FiraSansItalic,
+                        colour: MessageColour::MinorNegative,
+                        category: FeedCategory::Inventory,
+                    });
+                    return;
+                }
+            };
+            *coords = new_coords;
+            transform.translation.x = grid.calc_x(&coords);
+            transform.translation.y = grid.calc_y(&coords);
+            transform.rotation = item_rotation(turned);
+            interactive.size = coords.dimens.as_vec2();
+            let mut entity = commands.entity(menu.item);
+            if turned {
+                entity.insert(Rotated);
+            } else {
+                entity.remove::<Rotated>();
+            }
+            if to_crafting {
+                entity.insert(CraftItem);
+            } else {
+                entity.remove::<CraftItem>();
+            }
+        }
+        MenuAction::Inspect => {
+            let lines = inspect_lines(item, stack.0, &locale);
+            let position = menu_transform.translation.truncate();
+            let panel = spawn_text_tooltip(&mut commands, &assets, &layout, position, &lines);
+            commands
+                .entity(panel)
+                .insert(Name::new("InspectPanel"))
+                .insert(InspectPanel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::EquipmentSlot;
+
+    #[test]
+    fn menu_only_offers_what_the_item_supports() {
+        let plain = Item::default();
+        assert_eq!(
+            menu_actions(&plain, 1, false, false),
+            vec![
+                MenuAction::MoveToCrafting,
+                MenuAction::Discard,
+                MenuAction::Inspect
+            ]
+        );
+        let sword = Item {
+            wearable: Some(EquipmentSlot::Weapon),
+            ..default()
+        };
+        assert_eq!(
+            menu_actions(&sword, 1, true, false)[..2],
+            [MenuAction::Equip, MenuAction::MoveToBag]
+        );
+        assert_eq!(
+            menu_actions(&plain, 3, false, true)[..2],
+            [MenuAction::Use, MenuAction::Split]
+        );
+    }
+}
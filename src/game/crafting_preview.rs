@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use bevy::text::Text2dBounds;
+
+use crate::config::data_items::ItemsData;
+use crate::config::data_layout::LayoutData;
+use crate::config::data_locale::UiText;
+use crate::config::data_recipes::RecipesData;
+use crate::game::combining_system::try_get_recipe;
+use crate::game::recipe_book::{icon_dimens, RecipeBook};
+use crate::game::{
+    AssetStorage, CleanupOnGameplayEnd, CraftItem, FontId, Item, ItemId, Silhouette, Stack,
+};
+use crate::locale::Locale;
+use crate::positioning::{Coords, Depth, Pos};
+
+/// Marker component for the preview of the item that will be crafted when the combine button is
+/// pressed. If the preview also has the Silhouette component, the recipe is not complete yet.
+#[derive(Component)]
+pub struct CraftingPreview;
+
+/// Shows a preview of the crafting result next to the combine button whenever the contents of the
+/// crafting area match a known recipe, or a hint if they are only a single ingredient short.
+pub fn update_crafting_preview(
+    mut commands: Commands,
+    mut last_contents: Local<Option<Vec<(Pos, ItemId)>>>,
+    assets: Res<AssetStorage>,
+    layout: Res<LayoutData>,
+    book: Res<RecipeBook>,
+    items_data: Res<ItemsData>,
+    recipes_data: Res<RecipesData>,
+    locale: Res<Locale>,
+    query_crafting: Query<(&Item, &Coords, &Stack), With<CraftItem>>,
+    query_preview: Query<Entity, With<CraftingPreview>>,
+) {
+    let mut contents: Vec<(Pos, ItemId)> = query_crafting
+        .iter()
+        .flat_map(|(item, coords, stack)| {
+            vec![(coords.pos, item.id.clone()); stack.0.max(0) as usize]
+        })
+        .collect();
+    contents.sort_by_key(|(pos, _)| *pos);
+    if last_contents.as_ref() == Some(&contents) && !book.is_changed() && !locale.is_changed() {
+        return;
+    }
+    for entity in query_preview.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if contents.is_empty() {
+        *last_contents = Some(contents);
+        return;
+    }
+
+    // The recipe that combining would use, so that the preview never shows a different result.
+    // Recipes that are not known yet are not given away.
+    let complete = try_get_recipe(&recipes_data, &contents).filter(|recipe| book.knows(recipe));
+    // Only hint at a recipe if a single ingredient is missing.
+    let almost = book.known.iter().find_map(|recipe| {
+        recipe
+            .missing_ingredients(&contents)
+            .filter(|missing| missing.len() == 1 && missing[0].quantity == 1)
+            .map(|missing| (recipe, missing[0].item_id.clone()))
+    });
+
+    let (recipe, hint, is_complete) = if let Some(recipe) = &complete {
+        (recipe, None, true)
+    } else if let Some((recipe, missing)) = almost {
+        let missing_name = items_data
+            .try_get_item(missing.clone())
+            .map(|(_, item)| item.name)
+            .unwrap_or_else(|| missing.to_string());
+        (
+            recipe,
+            Some(locale.ui_with(UiText::CraftingNeedsMore, &[("item", missing_name)])),
+            false,
+        )
+    } else {
+        *last_contents = Some(contents);
+        return;
+    };
+    *last_contents = Some(contents);
+    let (dimens, item) = match items_data.try_get_item(recipe.result.clone()) {
+        Some(found) => found,
+        None => return,
+    };
+    let title = recipe
+        .outputs()
+        .into_iter()
+        .map(|output| {
+            items_data
+                .try_get_item(output.clone())
+                .map(|(_, item)| item.name)
+                .unwrap_or_else(|| output.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join(" + ");
+
+    let icon_size = 0.8;
+    let x = layout.right_x();
+    let width = layout.right_width();
+    let y = layout.c_right.combine_button_y()
+        + layout.c_right.combine_button_height()
+        + layout.c_right.crafting.margin_bottom.unwrap_or(0.) * 0.5;
+    let text_style = TextStyle {
+        font: assets.font(&FontId::FiraSansItalic),
+        font_size: 50.0,
+        color: Color::ANTIQUE_WHITE,
+    };
+    let text_bounds = Vec2::new(width - icon_size - 0.2, 1.);
+    let mut preview = commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            custom_size: Some(icon_dimens(dimens, icon_size)),
+            ..default()
+        },
+        texture: assets.texture(&item.texture_id),
+        transform: Transform::from_xyz(x + icon_size * 0.5, y, Depth::Item.z()),
+        ..default()
+    });
+    preview
+        .insert(Name::new("CraftingPreview"))
+        .insert(CraftingPreview)
+        .insert(CleanupOnGameplayEnd)
+        .with_children(|parent| {
+            parent.spawn_bundle(Text2dBundle {
+                text: Text::from_sections([
+                    TextSection::new(
+                        format!("{}\n", title),
+                        TextStyle {
+                            font: assets.font(&FontId::FiraSansBold),
+                            ..text_style.clone()
+                        },
+                    ),
+                    TextSection::new(hint.unwrap_or(item.description), text_style),
+                ])
+                .with_alignment(TextAlignment {
+                    horizontal: HorizontalAlign::Left,
+                    vertical: VerticalAlign::Center,
+                }),
+                text_2d_bounds: Text2dBounds {
+                    size: text_bounds * layout.text_factor,
+                },
+                transform: Transform::from_xyz(icon_size * 0.5 + 0.2, 0., 0.).with_scale(
+                    Vec3::new(1. / layout.text_factor, 1. / layout.text_factor, 1.),
+                ),
+                ..default()
+            });
+        });
+    if !is_complete {
+        preview.insert(Silhouette);
+    }
+}
@@ -1,7 +1,19 @@
 use bevy::prelude::*;
 
-use crate::game::items::{CraftItem, Item};
-use crate::game::{AssetStorage, CleanupOnGameplayEnd, Silhouette};
+use crate::config::config_input::InputAction;
+use crate::config::data_items::ItemsData;
+use crate::game::create_widget_encounter::EncounterPanel;
+use crate::game::create_widget_hero::HeroEquipmentSlot;
+use crate::game::dungeon_sim::DungeonState;
+use crate::game::encounter::{enemy_in_reach, UseOnEnemyEvent};
+use crate::game::equipment::EquipEvent;
+use crate::game::item_info_system::TooltipBg;
+use crate::game::items::{item_rotation, CraftItem, EquippedItem, Item, Rotated, Stack};
+use crate::game::occupancy::Occupancy;
+use crate::game::status_tray::StatusIcon;
+use crate::game::trash::{DiscardEvent, TrashZone};
+use crate::game::{AssetStorage, CleanupOnGameplayEnd, Silhouette, SpawnItemEvent};
+use crate::input::Actions;
 use crate::mouse::{Mouse, MouseInteractive};
 use crate::positioning::Depth;
 use crate::positioning::Pos;
@@ -25,36 +37,79 @@ pub struct DragGhost {
     /// Accounts for cases where the player didn't start the dragging on the bottom-left corner.
     cursor_delta: Pos,
     placement_valid: bool,
+    /// Whether only half of the stack is being dragged. The other half stays where it was.
+    split: bool,
+    /// The stack that the dragged items will be added to, if the ghost is dropped onto a stack
+    /// of the same item.
+    merge_target: Option<Entity>,
+    /// Whether the item will be placed with a quarter turn. See the Rotated component.
+    rotated: bool,
+    /// The cells where the ghost can't be placed, because they overlap another item or lie
+    /// outside of the grids.
+    conflicts: Vec<Pos>,
+    /// The equipment slot on the hero panel that the item will be equipped in, if the ghost is
+    /// dropped onto a slot that fits it.
+    equip_target: Option<Entity>,
+    /// If the item is being dragged out of an equipment slot rather than from the grid, this is
+    /// the slot. The item stays equipped until it is dropped in the bag.
+    unequip_from: Option<Entity>,
+    /// Whether the item will be used on the enemy, because the ghost is dropped onto the
+    /// encounter panel during a fight.
+    enemy_target: bool,
+    /// Whether the item will be put in the trash, because the ghost is dropped onto the trash
+    /// zone.
+    trash_target: bool,
 }
 
-/// TODO: There's no logic separating normal clicks from drag initiation.
-///       Needs to be included down the road.
-///
-/// Handles initiating a dragging operation.
+/// Marker component for the red squares that highlight the cells where the ghost conflicts.
+#[derive(Component)]
+pub struct GhostConflictCell;
+
+/// Handles initiating a dragging operation, once the mouse has told a drag apart from a click.
 /// When an item starts being dragged;
 ///     - That item is marked with the BeingDragged component.
 ///     - A ghost item is spawned.
 ///     - The mouse is tagged as being in the middle of a dragging operation.
+/// Holding LControl when starting to drag a stack splits it in half.
 pub fn check_drag_begin(
     mut commands: Commands,
     assets: Res<AssetStorage>,
     grid: Res<GridData>,
+    actions: Actions,
     mut mouse: ResMut<Mouse>,
-    query: Query<(&Coords, Entity, &Item, &MouseInteractive), Without<Silhouette>>,
+    query: Query<
+        (
+            &Coords,
+            Entity,
+            &Item,
+            &Stack,
+            &MouseInteractive,
+            Option<&Rotated>,
+        ),
+        Without<Silhouette>,
+    >,
 ) {
     if mouse.is_dragging {
         return;
     }
-    for (coords, entity, item, interactive) in query.iter() {
-        if interactive.clicked && !interactive.shift_clicked && !interactive.ctrl_alt_clicked {
-            let hovered_over_cell = Pos::from(mouse.position - grid.offset);
+    for (coords, entity, item, stack, interactive, rotated) in query.iter() {
+        if interactive.drag_started {
+            // The item is held by the cell where the drag started, even if the mouse has moved
+            // off it since.
+            let hovered_over_cell = Pos::from(mouse.drag_from - grid.offset);
             commands.entity(entity).insert(BeingDragged);
             commands.entity(entity).insert(Silhouette);
+            let rotated = rotated.is_some();
+            let sprite_size = if rotated {
+                coords.dimens.swapped()
+            } else {
+                coords.dimens
+            };
             commands
                 .spawn_bundle(SpriteBundle {
                     sprite: Sprite {
                         color: Color::rgba(1., 1., 1., 0.5),
-                        custom_size: Some(coords.dimens.as_vec2()),
+                        custom_size: Some(sprite_size.as_vec2()),
                         ..default()
                     },
                     texture: assets.texture(&item.texture_id),
@@ -62,12 +117,67 @@ pub fn check_drag_begin(
                         grid.calc_x(coords),
                         grid.calc_y(coords),
                         Depth::FloatingItem.z(),
-                    ),
+                    )
+                    .with_rotation(item_rotation(rotated)),
                     ..Default::default()
                 })
                 .insert(*coords)
                 .insert(DragGhost {
                     cursor_delta: coords.pos - hovered_over_cell,
+                    split: actions.pressed(InputAction::Split) && stack.0 > 1,
+                    rotated,
+                    ..default()
+                })
+                .insert(CleanupOnGameplayEnd);
+            mouse.is_dragging = true;
+        }
+    }
+}
+
+/// Handles dragging an equipped item out of its slot on the hero panel. Only the ghost is spawned;
+/// the item itself stays in the slot until the ghost is dropped.
+pub fn check_equipment_drag_begin(
+    mut commands: Commands,
+    assets: Res<AssetStorage>,
+    grid: Res<GridData>,
+    items_data: Res<ItemsData>,
+    mut mouse: ResMut<Mouse>,
+    query_slots: Query<(Entity, &EquippedItem, &MouseInteractive)>,
+) {
+    if mouse.is_dragging {
+        return;
+    }
+    for (entity, equipped, interactive) in query_slots.iter() {
+        if interactive.drag_started {
+            let dimens = match items_data.try_get_item(equipped.item.id.clone()) {
+                Some((dimens, _)) => dimens,
+                None => continue,
+            };
+            // Hold the item by its middle.
+            let cursor_delta = Pos::new(-dimens.x / 2, -dimens.y / 2);
+            let coords = Coords {
+                pos: Pos::from(mouse.position - grid.offset) + cursor_delta,
+                ..equipped.item.footprint(dimens)
+            };
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(1., 1., 1., 0.5),
+                        custom_size: Some(dimens.as_vec2()),
+                        ..default()
+                    },
+                    texture: assets.texture(&equipped.item.texture_id),
+                    transform: Transform::from_xyz(
+                        grid.calc_x(&coords),
+                        grid.calc_y(&coords),
+                        Depth::FloatingItem.z(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(coords)
+                .insert(DragGhost {
+                    cursor_delta,
+                    unequip_from: Some(entity),
                     ..default()
                 })
                 .insert(CleanupOnGameplayEnd);
@@ -89,64 +199,298 @@ pub fn set_ghost_position(
     }
 }
 
-/// Checks if the dragging move would be valid. If not, tints the ghost red.
+/// Gives the dragged item a quarter turn when the Rotate action is triggered while dragging
+/// (R or right-click by default). Turning it again turns it back.
+pub fn rotate_drag_ghost(
+    actions: Actions,
+    mut query: Query<(&mut Transform, &mut Coords, &mut DragGhost)>,
+) {
+    if !actions.just_pressed(InputAction::Rotate) {
+        return;
+    }
+    if let Ok((mut transform, mut coords, mut ghost)) = query.get_single_mut() {
+        // Turn around the cell under the cursor, so that the cursor stays on the item.
+        let x = -ghost.cursor_delta.x;
+        let y = -ghost.cursor_delta.y;
+        let (new_x, new_y) = if ghost.rotated {
+            // Counter-clockwise, back to the original orientation.
+            (coords.dimens.y - 1 - y, x)
+        } else {
+            // Clockwise.
+            (y, coords.dimens.x - 1 - x)
+        };
+        ghost.cursor_delta = Pos::new(-new_x, -new_y);
+        *coords = coords.turned(!ghost.rotated);
+        ghost.rotated = !ghost.rotated;
+        transform.rotation = item_rotation(ghost.rotated);
+    }
+}
+
+/// Checks if the dragging move would be valid. If not, highlights the conflicting cells in red.
+/// Dropping the item onto a stack of the same item that still has room is also valid, and so is
+/// dropping gear onto the matching equipment slot on the hero panel, and dropping items that
+/// can be used on the enemy onto the encounter panel, and dropping items onto the trash zone.
+/// Pockets that don't accept the item count as off the grid.
 pub fn check_ghost_placement_validity(
+    mut commands: Commands,
     grid: Res<GridData>,
+    occupancy: Res<Occupancy>,
     mut query_ghost: Query<(&mut DragGhost, &mut Sprite, &Coords)>,
-    query_items: Query<&Coords, (With<Item>, Without<BeingDragged>)>,
+    query_dragged: Query<(Entity, &Item), With<BeingDragged>>,
+    query_items: Query<(&Item, &Stack)>,
+    query_slots: Query<(
+        Entity,
+        &HeroEquipmentSlot,
+        &MouseInteractive,
+        Option<&EquippedItem>,
+    )>,
+    dungeon: Res<DungeonState>,
+    query_encounter: Query<&MouseInteractive, With<EncounterPanel>>,
+    query_status_icons: Query<(&StatusIcon, &MouseInteractive)>,
+    query_trash: Query<&MouseInteractive, With<TrashZone>>,
+    query_conflict_cells: Query<Entity, With<GhostConflictCell>>,
 ) {
     if let Ok((mut ghost, mut sprite, coords)) = query_ghost.get_single_mut() {
-        let conflicts_with_item = query_items.iter().any(|item| coords.overlaps(item));
-        if !conflicts_with_item
-            && (grid.inventory.encloses(coords) || grid.crafting.encloses(coords))
+        let (dragged, dragged_item) = match (query_dragged.get_single(), ghost.unequip_from) {
+            (Ok((entity, item)), _) => (Some(entity), item),
+            (_, Some(slot)) => match query_slots.get(slot) {
+                Ok((_, _, _, Some(equipped))) => (None, &equipped.item),
+                _ => return,
+            },
+            _ => return,
+        };
+        ghost.equip_target = query_slots
+            .iter()
+            .find(|(_, slot, interactive, _)| {
+                interactive.hovered && dragged_item.wearable == Some(slot.0)
+            })
+            .map(|(entity, _, _, _)| entity);
+        ghost.enemy_target = dragged.is_some()
+            && dragged_item.target.enemy()
+            && enemy_in_reach(&dungeon)
+            && (query_encounter.iter().any(|interactive| interactive.hovered)
+                // The icons of the effects on the enemy sit on top of the panel.
+                || query_status_icons
+                    .iter()
+                    .any(|(icon, interactive)| !icon.on_hero && interactive.hovered));
+        ghost.trash_target =
+            dragged.is_some() && query_trash.iter().any(|interactive| interactive.hovered);
+        // When splitting a stack, the original stays in place, so the ghost can't overlap it.
+        let ignore: Vec<Entity> = match dragged {
+            Some(entity) if !ghost.split => vec![entity],
+            _ => vec![],
+        };
+        let overlapping: Vec<Entity> = occupancy
+            .occupants(coords)
+            .into_iter()
+            .filter(|entity| !ignore.contains(entity))
+            .collect();
+        ghost.merge_target = match overlapping.as_slice() {
+            [entity] if Some(*entity) != dragged => query_items
+                .get(*entity)
+                .ok()
+                .filter(|(item, stack)| item.id == dragged_item.id && stack.room(item) > 0)
+                .map(|_| *entity),
+            _ => None,
+        };
+        let mut conflicts: Vec<Pos> = vec![];
+        if ghost.merge_target.is_none()
+            && ghost.equip_target.is_none()
+            && !ghost.enemy_target
+            && !ghost.trash_target
         {
-            ghost.placement_valid = true;
-            sprite.color = Color::rgba(1., 1., 1., 0.5);
+            conflicts = coords
+                .cells()
+                .filter(|pos| {
+                    let occupied = match occupancy.occupant(pos) {
+                        Some(entity) => !ignore.contains(&entity),
+                        None => false,
+                    };
+                    occupied || !grid.accepts_cell(pos, dragged_item.kind())
+                })
+                .collect();
+        }
+        ghost.placement_valid = ghost.merge_target.is_some()
+            || ghost.equip_target.is_some()
+            || ghost.enemy_target
+            || ghost.trash_target
+            || (overlapping.is_empty()
+                && (grid.can_store(coords, dragged_item.kind()) || grid.crafting.encloses(coords)));
+        // Tint the whole ghost if it is invalid without any particular cell being at fault, such
+        // as when it straddles the inventory and the crafting area, or the inventory and a pocket.
+        sprite.color = if ghost.placement_valid || !conflicts.is_empty() {
+            Color::rgba(1., 1., 1., 0.5)
         } else {
-            ghost.placement_valid = false;
-            sprite.color = Color::rgba(1., 0., 0., 0.5);
+            Color::rgba(1., 0., 0., 0.5)
+        };
+        if conflicts != ghost.conflicts {
+            for entity in query_conflict_cells.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            for pos in &conflicts {
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(1., 0., 0., 0.5),
+                            custom_size: Some(Vec2::ONE),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(
+                            grid.offset.x + pos.x as f32 + 0.5,
+                            grid.offset.y + pos.y as f32 + 0.5,
+                            Depth::FloatingItem.z() + 1.,
+                        ),
+                        ..default()
+                    })
+                    .insert(GhostConflictCell)
+                    .insert(CleanupOnGameplayEnd);
+            }
+            ghost.conflicts = conflicts;
         }
     }
 }
 
-/// Check if the dragging operation should be concluded. If so;
+/// Check if the dragging operation should be concluded: when the Select button is let go of, or
+/// when Grab is triggered again for a drag that was started with Grab. If so;
 /// - Delete the ghost entity.
 /// - Mark the mouse as no longer in the middle of a drag operation.
 /// - Broadcast a DragEvent.
 pub fn check_drag_end(
     mut writer: EventWriter<DragEvent>,
     mut mouse: ResMut<Mouse>,
-    input: Res<Input<MouseButton>>,
+    actions: Actions,
     query_ghost: Query<&Coords, With<DragGhost>>,
 ) {
-    if !mouse.is_dragging || !input.just_released(MouseButton::Left) {
+    let dropped = if mouse.click_to_drag {
+        actions.just_pressed(InputAction::Grab)
+    } else {
+        actions.just_released(InputAction::Select)
+    };
+    if !mouse.is_dragging || !dropped {
         return;
     }
-    mouse.is_dragging = false;
-    let ghost_coords = query_ghost.single();
-    writer.send(DragEvent(ghost_coords.pos));
+    // The ghost of a drag that was started by this same key press hasn't been spawned yet.
+    if let Ok(ghost_coords) = query_ghost.get_single() {
+        mouse.is_dragging = false;
+        writer.send(DragEvent(ghost_coords.pos));
+    }
 }
 
 pub fn process_drag_event(
     mut commands: Commands,
+    query_conflict_cells: Query<Entity, With<GhostConflictCell>>,
     grid: Res<GridData>,
+    mut occupancy: ResMut<Occupancy>,
     mut events: EventReader<DragEvent>,
-    query_ghost: Query<(Entity, &DragGhost)>,
-    mut query_item: Query<(Entity, &mut Transform, &mut Coords), With<BeingDragged>>,
+    mut spawn: EventWriter<SpawnItemEvent>,
+    mut equip: EventWriter<EquipEvent>,
+    mut use_on_enemy: EventWriter<UseOnEnemyEvent>,
+    mut discard: EventWriter<DiscardEvent>,
+    query_ghost: Query<(Entity, &DragGhost, &Coords)>,
+    mut query_item: Query<
+        (
+            Entity,
+            &Item,
+            &mut Transform,
+            &mut Coords,
+            &mut Stack,
+            &mut MouseInteractive,
+        ),
+        (With<BeingDragged>, Without<DragGhost>),
+    >,
+    mut query_stacks: Query<(&Item, &mut Stack), Without<BeingDragged>>,
+    query_equipped: Query<&EquippedItem>,
+    tooltips: Query<Entity, With<TooltipBg>>,
 ) {
     for DragEvent(end) in events.iter() {
-        if let Ok((entity, mut transform, mut coords)) = query_item.get_single_mut() {
-            let (ghost_entity, ghost) = query_ghost.single();
-            commands.entity(ghost_entity).despawn_recursive();
+        let (ghost_entity, ghost, ghost_coords) = query_ghost.single();
+        commands.entity(ghost_entity).despawn_recursive();
+        for cell in query_conflict_cells.iter() {
+            commands.entity(cell).despawn_recursive();
+        }
+        // Dragging an item out of its equipment slot puts it back in the bag.
+        if let Some(slot) = ghost.unequip_from {
+            if let Ok(equipped) = query_equipped.get(slot) {
+                if ghost.placement_valid && ghost.equip_target.is_none() {
+                    equip.send(EquipEvent::Unequip(equipped.slot));
+                    spawn.send(
+                        SpawnItemEvent::without_anim(
+                            equipped.item.clone(),
+                            Coords {
+                                pos: *end,
+                                ..*ghost_coords
+                            },
+                        )
+                        .with_rotation(ghost.rotated),
+                    );
+                }
+            }
+            continue;
+        }
+        if let Ok((entity, item, mut transform, mut coords, mut stack, mut interactive)) =
+            query_item.get_single_mut()
+        {
             commands.entity(entity).remove::<BeingDragged>();
             commands.entity(entity).remove::<Silhouette>();
-            if ghost.placement_valid {
-                coords.pos = *end;
+            if !ghost.placement_valid {
+                continue;
+            }
+            let moved = if ghost.split { stack.0 / 2 } else { stack.0 };
+            if ghost.trash_target {
+                discard.send(DiscardEvent {
+                    entity,
+                    count: moved,
+                });
+            } else if ghost.enemy_target {
+                use_on_enemy.send(UseOnEnemyEvent(entity));
+            } else if ghost.equip_target.is_some() {
+                commands.entity(entity).despawn_recursive();
+                // Free up the cells right away, so that a swapped out item can take them.
+                occupancy.remove(entity);
+                for tooltip in tooltips.iter() {
+                    commands.entity(tooltip).despawn_recursive();
+                }
+                equip.send(EquipEvent::Equip(item.clone()));
+            } else if let Some(target) = ghost.merge_target {
+                if let Ok((target_item, mut target_stack)) = query_stacks.get_mut(target) {
+                    let merged = moved.min(target_stack.room(target_item));
+                    target_stack.0 += merged;
+                    stack.0 -= merged;
+                    if stack.0 <= 0 {
+                        commands.entity(entity).despawn_recursive();
+                        for tooltip in tooltips.iter() {
+                            commands.entity(tooltip).despawn_recursive();
+                        }
+                    }
+                }
+            } else if ghost.split {
+                stack.0 -= moved;
+                let split_coords = Coords {
+                    pos: *end,
+                    ..*ghost_coords
+                };
+                spawn.send(
+                    SpawnItemEvent::without_anim(item.clone(), split_coords)
+                        .with_count(moved)
+                        .with_rotation(ghost.rotated),
+                );
+            } else {
+                *coords = Coords {
+                    pos: *end,
+                    ..*ghost_coords
+                };
                 transform.translation.x = grid.calc_x(&coords);
                 transform.translation.y = grid.calc_y(&coords);
+                transform.rotation = item_rotation(ghost.rotated);
+                interactive.size = coords.dimens.as_vec2();
+                if ghost.rotated {
+                    commands.entity(entity).insert(Rotated);
+                } else {
+                    commands.entity(entity).remove::<Rotated>();
+                }
                 if grid.crafting.encloses(&coords) {
                     commands.entity(entity).insert(CraftItem);
-                } else if grid.inventory.encloses(&coords) {
+                } else if grid.in_storage(&coords) {
                     commands.entity(entity).remove::<CraftItem>();
                 }
             }
@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::audio::sound_event::SoundEvent;
+use crate::config::data_locale::UiText;
+use crate::game::feed::{AddFeedItemEvent, FeedCategory, MessageColour};
+use crate::game::{EquipmentSlot, EquippedItem, FontId, SoundId};
+use crate::locale::Locale;
+
+/// How much use a piece of gear can take before it breaks. The item data only gives the max;
+/// the wear is kept with each item, so it carries over when the item is moved or unequipped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Durability {
+    pub max: i32,
+    #[serde(default)]
+    pub wear: i32,
+}
+
+impl Durability {
+    pub fn remaining(&self) -> i32 {
+        (self.max - self.wear).max(0)
+    }
+
+    /// The part of the durability that is left, between 0 and 1.
+    pub fn fraction(&self) -> f32 {
+        if self.max <= 0 {
+            return 0.;
+        }
+        self.remaining() as f32 / self.max as f32
+    }
+
+    /// E.g. "Durability: 3/10".
+    pub fn describe(&self, locale: &Locale) -> String {
+        locale.ui_with(
+            UiText::Durability,
+            &[
+                ("remaining", self.remaining().to_string()),
+                ("max", self.max.to_string()),
+            ],
+        )
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Makes the item as good as new.
+    pub fn repair(&mut self) {
+        self.wear = 0;
+    }
+
+    /// Wears the item down by one. Returns true iff this breaks the item.
+    pub fn wear_down(&mut self) -> bool {
+        if self.is_broken() {
+            return false;
+        }
+        self.wear += 1;
+        self.is_broken()
+    }
+}
+
+/// The colour of a durability bar: green while the item is in good shape, turning red as it is
+/// about to break.
+pub fn durability_colour(fraction: f32) -> Color {
+    let fraction = fraction.clamp(0., 1.);
+    Color::rgb(
+        (2. * (1. - fraction)).min(1.),
+        (2. * fraction).min(1.) * 0.8,
+        0.1,
+    )
+}
+
+/// Broadcast this event to wear down the gear in a slot, e.g. when the hero hits or gets hit.
+pub struct WearGearEvent(pub EquipmentSlot);
+
+/// Wears down the equipped gear. Gear without durability lasts forever. Gear that runs out of
+/// durability breaks, and is gone for good.
+pub fn wear_gear(
+    mut commands: Commands,
+    mut events: EventReader<WearGearEvent>,
+    mut feed: EventWriter<AddFeedItemEvent>,
+    mut audio: EventWriter<SoundEvent>,
+    mut query_slots: Query<(Entity, &mut EquippedItem)>,
+    locale: Res<Locale>,
+) {
+    for WearGearEvent(slot) in events.iter() {
+        let (entity, mut equipped) = match query_slots
+            .iter_mut()
+            .find(|(_, equipped)| equipped.slot == *slot)
+        {
+            Some(found) => found,
+            None => continue,
+        };
+        let broke = match &mut equipped.item.durability {
+            Some(durability) => durability.wear_down(),
+            None => false,
+        };
+        if broke {
+            commands.entity(entity).remove::<EquippedItem>();
+            audio.send(SoundEvent::Sfx(SoundId::SwordClang));
+            feed.send(AddFeedItemEvent {
+                message: locale.ui_with(
+                    UiText::GearBreaks,
+                    &[("item", equipped.item.display_name(&locale))],
+                ),
+                font: FontId::FiraSansMedium,
+                colour: MessageColour::MajorNegative,
+                category: FeedCategory::Combat,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaks_once_when_worn_down() {
+        let mut durability = Durability { max: 2, wear: 0 };
+        assert!(!durability.wear_down());
+        assert_eq!(durability.fraction(), 0.5);
+        assert!(durability.wear_down());
+        // Wearing down a broken item doesn't break it again.
+        assert!(!durability.wear_down());
+        assert_eq!(durability.remaining(), 0);
+    }
+}
@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+
+use crate::config::data_locale::UiText;
+use crate::game::combat::{CombatState, Enemy, EnemyId};
+use crate::game::create_widget_encounter::{
+    EncounterEnemyName, EncounterHealthBar, EncounterHealthText, EncounterPanel, EncounterPortrait,
+    EncounterPortraitInitial, EncounterStats,
+};
+use crate::game::dungeon_sim::DungeonState;
+use crate::game::feed::{AddFeedItemEvent, FeedCategory, MessageColour};
+use crate::game::item_info_system::TooltipBg;
+use crate::game::occupancy::Occupancy;
+use crate::game::timed_effect::{ApplyEffectEvent, TemporaryModifier};
+use crate::game::{AssetStorage, FontId, Item, Stack};
+use crate::locale::Locale;
+
+/// Broadcast this event to use one of the items of the given stack on the enemy. It is only used
+/// up if the enemy can still be reached.
+pub struct UseOnEnemyEvent(pub Entity);
+
+/// Whether items can be used on the enemy right now.
+pub fn enemy_in_reach(state: &DungeonState) -> bool {
+    state.combat_state == CombatState::InProgress
+}
+
+/// How long the encounter panel takes to slide in or out, in seconds.
+const SLIDE_SECONDS: f32 = 0.4;
+/// How quickly the health bar catches up with the enemy's health, in fractions per second.
+const HEALTH_BAR_SPEED: f32 = 1.5;
+
+/// Whether there is an enemy in the current room that hasn't been dealt with yet. The panel is on
+/// screen from the moment the fight is announced until it has ended.
+fn enemy_present(state: &DungeonState, enemy: &Enemy) -> bool {
+    let room = match &state.current_level {
+        Some(level) => level.rooms.get(state.current_room_idx as usize),
+        None => None,
+    };
+    matches!(room, Some(room) if room.combat && !room.init)
+        && state.combat_state != CombatState::Ended
+        && enemy.enemy_id != EnemyId::None
+}
+
+/// Slides the encounter panel in when a fight starts, and out again when it is over.
+pub fn slide_encounter_panel(
+    time: Res<Time>,
+    state: Res<DungeonState>,
+    enemy: Res<Enemy>,
+    mut query_panel: Query<(&mut EncounterPanel, &mut Transform, &mut Visibility)>,
+) {
+    let target = if enemy_present(&state, &enemy) {
+        1.
+    } else {
+        0.
+    };
+    for (mut panel, mut transform, mut visibility) in query_panel.iter_mut() {
+        if panel.progress == target {
+            continue;
+        }
+        let step = time.delta_seconds() / SLIDE_SECONDS;
+        panel.progress = if target > panel.progress {
+            (panel.progress + step).min(target)
+        } else {
+            (panel.progress - step).max(target)
+        };
+        // Ease out, so that the panel comes to a gentle stop.
+        let eased = 1. - (1. - panel.progress).powi(3);
+        transform.translation.y = panel.hidden_y + (panel.shown_y - panel.hidden_y) * eased;
+        visibility.is_visible = panel.progress > 0.;
+    }
+}
+
+/// Keeps the contents of the encounter panel in line with the enemy: name, portrait, health and
+/// stats. The effects on the enemy are shown by its status tray. The health bar runs down to the
+/// enemy's health rather than jumping, except when a new enemy shows up.
+pub fn update_encounter_panel(
+    time: Res<Time>,
+    enemy: Res<Enemy>,
+    assets: Res<AssetStorage>,
+    locale: Res<Locale>,
+    mut last_encounter: Local<u32>,
+    mut query_portrait: Query<(&mut Handle<Image>, &mut Visibility), With<EncounterPortrait>>,
+    mut query_bar: Query<(&mut EncounterHealthBar, &mut Sprite, &mut Transform)>,
+    mut texts: ParamSet<(
+        Query<&mut Text, With<EncounterEnemyName>>,
+        Query<&mut Text, With<EncounterPortraitInitial>>,
+        Query<&mut Text, With<EncounterHealthText>>,
+        Query<&mut Text, With<EncounterStats>>,
+    )>,
+) {
+    let stats = &enemy.combat_stats;
+    let new_enemy = *last_encounter != enemy.encounter;
+    *last_encounter = enemy.encounter;
+    // Also when the name changes with the language, rather than only for a new enemy.
+    if enemy.is_changed() || locale.is_changed() {
+        for mut text in texts.p0().iter_mut() {
+            text.sections[0].value = enemy.name.clone();
+        }
+        let initial = match enemy.portrait {
+            Some(_) => String::new(),
+            None => enemy.name.chars().take(1).collect(),
+        };
+        for mut text in texts.p1().iter_mut() {
+            text.sections[0].value = initial.clone();
+        }
+        for (mut texture, mut visibility) in query_portrait.iter_mut() {
+            visibility.is_visible = enemy.portrait.is_some();
+            if let Some(portrait) = &enemy.portrait {
+                *texture = assets.texture(portrait);
+            }
+        }
+        for mut text in texts.p2().iter_mut() {
+            text.sections[0].value = format!("{}/{}", stats.health.max(0), stats.max_health);
+        }
+        for mut text in texts.p3().iter_mut() {
+            text.sections[0].value = locale.ui_with(
+                UiText::EncounterStats,
+                &[
+                    ("proficiency", stats.proficiency.to_string()),
+                    ("resistance", stats.damage_res.to_string()),
+                    ("damage", format!("{:+}", stats.damage_bonus)),
+                ],
+            );
+        }
+    }
+    let fraction = if stats.max_health > 0 {
+        (stats.health as f32 / stats.max_health as f32).clamp(0., 1.)
+    } else {
+        0.
+    };
+    for (mut bar, mut sprite, mut transform) in query_bar.iter_mut() {
+        if new_enemy {
+            bar.shown = fraction;
+        } else if bar.shown != fraction {
+            let step = HEALTH_BAR_SPEED * time.delta_seconds();
+            bar.shown = if fraction > bar.shown {
+                (bar.shown + step).min(fraction)
+            } else {
+                (bar.shown - step).max(fraction)
+            };
+        } else {
+            continue;
+        }
+        let width = bar.full_width * bar.shown;
+        sprite.custom_size = Some(Vec2::new(width, 0.25));
+        transform.translation.x = bar.left + width * 0.5;
+    }
+}
+
+/// Deals the damage of the item to the enemy, and puts its effect on the enemy rather than on
+/// the hero. An enemy that is killed this way is dead right away.
+pub fn use_items_on_enemy(
+    mut events: EventReader<UseOnEnemyEvent>,
+    mut state: ResMut<DungeonState>,
+    mut enemy: ResMut<Enemy>,
+    mut feed: EventWriter<AddFeedItemEvent>,
+    mut effect_events: EventWriter<ApplyEffectEvent>,
+    mut commands: Commands,
+    mut occupancy: ResMut<Occupancy>,
+    mut query_items: Query<(&Item, &mut Stack)>,
+    tooltips: Query<Entity, With<TooltipBg>>,
+    locale: Res<Locale>,
+) {
+    for UseOnEnemyEvent(entity) in events.iter() {
+        // The fight may have ended since the item was dropped, in which case it is kept.
+        if !enemy_in_reach(&state) {
+            continue;
+        }
+        let (item, mut stack) = match query_items.get_mut(*entity) {
+            Ok(components) => components,
+            Err(_) => continue,
+        };
+        // Only one item is thrown, even if the whole stack is dragged.
+        stack.0 -= 1;
+        if stack.0 <= 0 {
+            commands.entity(*entity).despawn_recursive();
+            occupancy.remove(*entity);
+            for tooltip in tooltips.iter() {
+                commands.entity(tooltip).despawn_recursive();
+            }
+        }
+        let mut message = locale.ui_with(
+            UiText::ThrowAtEnemy,
+            &[
+                ("item", item.display_name(&locale)),
+                ("enemy", enemy.name.clone()),
+            ],
+        );
+        if let Some(damage) = item.damage {
+            enemy.combat_stats.health -= damage;
+            message.push(' ');
+            message
+                .push_str(&locale.ui_with(UiText::ThrowDamage, &[("damage", damage.to_string())]));
+        }
+        if let Some(modifier) = &item.temporary_effect {
+            effect_events.send(ApplyEffectEvent(TemporaryModifier {
+                on_hero: false,
+                source: Some(item.display_name(&locale)),
+                icon: Some(item.texture_id),
+                item: Some(item.id.clone()),
+                ..modifier.clone()
+            }));
+        }
+        feed.send(AddFeedItemEvent {
+            message,
+            font: FontId::FiraSansMedium,
+            colour: MessageColour::MinorPositive,
+            category: FeedCategory::Combat,
+        });
+        if enemy.combat_stats.health < 1 {
+            state.combat_state = CombatState::EnemyDead;
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+
+use crate::config::data_items::ItemsData;
+use crate::config::data_locale::UiText;
+use crate::game::create_widget_hero::{
+    HeroEquipmentDurabilityBar, HeroEquipmentIcon, HeroEquipmentLabel, HeroEquipmentSlot,
+};
+use crate::game::durability::durability_colour;
+use crate::game::feed::{AddFeedItemEvent, FeedCategory, MessageColour};
+use crate::game::occupancy::Occupancy;
+use crate::game::recipe_book::icon_dimens;
+use crate::game::{
+    find_free_space, AssetStorage, EquipmentSlot, EquippedItem, FontId, Item, SpawnItemEvent,
+};
+use crate::locale::Locale;
+use crate::positioning::{Coords, Dimens, GridData};
+
+/// Broadcast this event to change what the hero is wearing.
+pub enum EquipEvent {
+    /// Puts the item in its slot. Whatever was in the slot goes back into the bag, or is left on
+    /// the floor if the bag is full.
+    Equip(Item),
+    /// Empties the slot. Whoever sends this takes care of the item that was in it.
+    Unequip(EquipmentSlot),
+}
+
+/// Puts items in the hero's equipment slots and takes them out again. The stat bonuses of
+/// equipped items are picked up by `update_hero_stats`.
+pub fn equip_items(
+    mut commands: Commands,
+    mut events: EventReader<EquipEvent>,
+    grid: Res<GridData>,
+    occupancy: Res<Occupancy>,
+    items_data: Res<ItemsData>,
+    mut spawn: EventWriter<SpawnItemEvent>,
+    mut feed: EventWriter<AddFeedItemEvent>,
+    locale: Res<Locale>,
+    query_slots: Query<(
+        Entity,
+        &HeroEquipmentSlot,
+        &GlobalTransform,
+        Option<&EquippedItem>,
+    )>,
+) {
+    let mut same_tick_items: Vec<Coords> = vec![];
+    for event in events.iter() {
+        let (slot, new_item) = match event {
+            EquipEvent::Equip(item) => match item.wearable {
+                Some(slot) => (slot, Some(item)),
+                None => continue,
+            },
+            EquipEvent::Unequip(slot) => (*slot, None),
+        };
+        let (entity, _, transform, equipped) = match query_slots
+            .iter()
+            .find(|(_, slot_widget, _, _)| slot_widget.0 == slot)
+        {
+            Some(found) => found,
+            None => continue,
+        };
+        if let Some(old_item) = equipped.map(|equipped| equipped.item.clone()) {
+            commands.entity(entity).remove::<EquippedItem>();
+            // A swapped out item goes back into the bag.
+            if new_item.is_some() {
+                let free_coords =
+                    items_data
+                        .try_get_item(old_item.id.clone())
+                        .and_then(|(dimens, _)| {
+                            find_free_space(
+                                &grid,
+                                &occupancy,
+                                old_item.footprint(dimens),
+                                old_item.kind(),
+                                &same_tick_items,
+                            )
+                        });
+                match free_coords {
+                    Some((coords, rotated)) => {
+                        same_tick_items.push(coords);
+                        spawn.send(
+                            SpawnItemEvent::new(
+                                old_item,
+                                coords,
+                                transform.translation().truncate(),
+                                false,
+                            )
+                            .with_rotation(rotated),
+                        );
+                    }
+                    None => feed.send(AddFeedItemEvent {
+                        message: locale.ui_with(
+                            UiText::NoRoomToUnequip,
+                            &[("item", old_item.display_name(&locale))],
+                        ),
+                        font: FontId::FiraSansItalic,
+                        colour: MessageColour::MinorNegative,
+                        category: FeedCategory::Inventory,
+                    }),
+                }
+            }
+        }
+        if let Some(item) = new_item {
+            commands.entity(entity).insert(EquippedItem {
+                slot,
+                item: item.clone(),
+            });
+        }
+    }
+}
+
+/// Shows the texture of the equipped item in each slot on the hero panel, or the name of the
+/// slot if it is empty. Items with durability get a bar that shows how much is left.
+pub fn update_equipment_slots(
+    assets: Res<AssetStorage>,
+    items_data: Res<ItemsData>,
+    query_slots: Query<(&Children, Option<&EquippedItem>), With<HeroEquipmentSlot>>,
+    mut query_icons: Query<
+        (&mut Handle<Image>, &mut Sprite, &mut Visibility),
+        (
+            With<HeroEquipmentIcon>,
+            Without<HeroEquipmentLabel>,
+            Without<HeroEquipmentDurabilityBar>,
+        ),
+    >,
+    mut query_labels: Query<
+        &mut Visibility,
+        (
+            With<HeroEquipmentLabel>,
+            Without<HeroEquipmentDurabilityBar>,
+        ),
+    >,
+    mut query_bars: Query<(
+        &HeroEquipmentDurabilityBar,
+        &mut Sprite,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    for (children, equipped) in query_slots.iter() {
+        for child in children.iter() {
+            if let Ok((mut texture, mut sprite, mut visibility)) = query_icons.get_mut(*child) {
+                visibility.is_visible = equipped.is_some();
+                if let Some(equipped) = equipped {
+                    let handle = assets.texture(&equipped.item.texture_id);
+                    if *texture != handle {
+                        *texture = handle;
+                        let dimens = items_data
+                            .try_get_item(equipped.item.id.clone())
+                            .map(|(dimens, _)| dimens)
+                            .unwrap_or_else(Dimens::unit);
+                        let size = sprite.custom_size.unwrap_or(Vec2::ONE).max_element();
+                        sprite.custom_size = Some(icon_dimens(dimens, size));
+                    }
+                }
+            }
+            if let Ok(mut visibility) = query_labels.get_mut(*child) {
+                visibility.is_visible = equipped.is_none();
+            }
+            if let Ok((bar, mut sprite, mut transform, mut visibility)) = query_bars.get_mut(*child)
+            {
+                let durability = equipped.and_then(|equipped| equipped.item.durability);
+                visibility.is_visible = durability.is_some();
+                if let Some(durability) = durability {
+                    let width = bar.full_width * durability.fraction();
+                    let height = sprite.custom_size.map(|size| size.y).unwrap_or(0.1);
+                    sprite.custom_size = Some(Vec2::new(width, height));
+                    sprite.color = durability_colour(durability.fraction());
+                    // The bar shrinks towards the left.
+                    transform.translation.x = (width - bar.full_width) * 0.5;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use bevy::prelude::*;
+
+use crate::config::config_input::InputAction;
+use crate::config::data_locale::UiText;
+use crate::game::feed::{AddFeedItemEvent, FeedCategory, MessageColour};
+use crate::game::occupancy::Occupancy;
+use crate::game::{CleanupOnGameplayEnd, CombineButton, DragGhost, FontId, Item};
+use crate::input::Actions;
+use crate::locale::Locale;
+use crate::mouse::{Mouse, MouseInteractive};
+use crate::positioning::{Coords, Depth, Dimens, GridData, Pos};
+
+/// How thick the lines of the selection frame are.
+const FRAME_THICKNESS: f32 = 0.08;
+
+/// Resource. A cursor that is moved over the cells of the bag and the crafting area with the
+/// keyboard. While it is active it stands in for the mouse: the mouse position is kept in the
+/// middle of its cell, so that hovering, tooltips and dragging work just like with the mouse.
+#[derive(Default)]
+pub struct GridCursor {
+    pub pos: Pos,
+    /// Whether the keyboard is in control. Moving the mouse hands control back to it.
+    pub active: bool,
+    /// The item that DeleteConfirmed was triggered on once. Triggering it again on the same item
+    /// puts it in the trash.
+    pub confirm_delete: Option<Entity>,
+}
+
+/// One of the four lines of the selection frame around the grid cursor, in the order bottom,
+/// top, left, right.
+#[derive(Component)]
+pub struct GridCursorEdge(usize);
+
+pub fn create_layout_grid_cursor(mut commands: Commands) {
+    for side in 0..4 {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(1., 0.85, 0.3),
+                    ..default()
+                },
+                visibility: Visibility { is_visible: false },
+                transform: Transform::from_xyz(0., 0., Depth::Cursor.z()),
+                ..default()
+            })
+            .insert(Name::new("GridCursorEdge"))
+            .insert(GridCursorEdge(side))
+            .insert(CleanupOnGameplayEnd);
+    }
+}
+
+/// The cells that the cursor can move over: the inventory, the pockets and the crafting area.
+fn cursor_cells(grid: &GridData) -> Vec<Pos> {
+    let mut areas = vec![grid.inventory, grid.crafting];
+    areas.extend(grid.pockets.iter().map(|pocket| pocket.coords));
+    areas.iter().flat_map(Coords::cells).collect()
+}
+
+/// The cell that the cursor moves to from `from` when stepping in the given direction. This is
+/// the nearest cell ahead, preferring cells in line with the cursor, so that the cursor can jump
+/// the gaps between the inventory, the pockets and the crafting area.
+pub fn cursor_step(cells: &[Pos], from: Pos, step: IVec2) -> Option<Pos> {
+    let across = IVec2::new(step.y, step.x);
+    cells
+        .iter()
+        .copied()
+        .filter(|cell| (**cell - *from).dot(step) > 0)
+        .min_by_key(|cell| {
+            let delta = **cell - *from;
+            (delta.dot(step), delta.dot(across).abs())
+        })
+}
+
+/// Moves the grid cursor with the cursor keys, and puts the mouse position on it. The first key
+/// press only takes control, starting from the cell under the mouse if it is on the grid.
+///
+/// Runs on the first mouse stage, right after the mouse position has been calculated.
+pub fn move_grid_cursor(
+    actions: Actions,
+    grid: Res<GridData>,
+    mut cursor: ResMut<GridCursor>,
+    mut mouse: ResMut<Mouse>,
+    mut cursor_moved: EventReader<CursorMoved>,
+) {
+    if cursor_moved.iter().count() > 0 {
+        cursor.active = false;
+    }
+    let step = [
+        (InputAction::CursorUp, IVec2::Y),
+        (InputAction::CursorDown, IVec2::NEG_Y),
+        (InputAction::CursorLeft, IVec2::NEG_X),
+        (InputAction::CursorRight, IVec2::X),
+    ]
+    .into_iter()
+    .find(|(action, _)| actions.just_pressed(*action))
+    .map(|(_, step)| step);
+    if let Some(step) = step {
+        let cells = cursor_cells(&grid);
+        if !cursor.active {
+            cursor.active = true;
+            let under_mouse = Pos::from(mouse.position - grid.offset);
+            cursor.pos = if cells.contains(&under_mouse) {
+                under_mouse
+            } else {
+                grid.inventory.pos
+            };
+        } else if let Some(pos) = cursor_step(&cells, cursor.pos, step) {
+            cursor.pos = pos;
+        }
+    }
+    if cursor.active {
+        mouse.position = grid.offset + cursor.pos.as_vec2() + Vec2::splat(0.5);
+    }
+}
+
+/// Handles the keyboard actions that have no single mouse click to stand in for: trashing the
+/// hovered item once it is confirmed, and pressing the combine button from anywhere.
+///
+/// Runs on the second mouse stage, right after the hovered entities have been found.
+pub fn grid_cursor_actions(
+    actions: Actions,
+    mouse: Res<Mouse>,
+    mut cursor: ResMut<GridCursor>,
+    mut feed: EventWriter<AddFeedItemEvent>,
+    locale: Res<Locale>,
+    mut query_items: Query<(Entity, &Item, &mut MouseInteractive), Without<CombineButton>>,
+    mut query_combine: Query<&mut MouseInteractive, With<CombineButton>>,
+) {
+    let hovered = query_items
+        .iter()
+        .find(|(_, _, interactive)| interactive.hovered)
+        .map(|(entity, item, _)| (entity, item.display_name(&locale)));
+    // Moving on to another item cancels it.
+    if cursor.confirm_delete.is_some() && cursor.confirm_delete != hovered.as_ref().map(|h| h.0) {
+        cursor.confirm_delete = None;
+    }
+    if actions.just_pressed(InputAction::DeleteConfirmed) && !mouse.is_dragging {
+        if let Some((entity, name)) = hovered {
+            if cursor.confirm_delete == Some(entity) {
+                cursor.confirm_delete = None;
+                if let Ok((_, _, mut interactive)) = query_items.get_mut(entity) {
+                    interactive.ctrl_alt_clicked = true;
+                }
+            } else {
+                cursor.confirm_delete = Some(entity);
+                feed.send(AddFeedItemEvent {
+                    message: locale.ui_with(
+                        UiText::TrashConfirm,
+                        &[
+                            ("key", actions.key_name(InputAction::DeleteConfirmed)),
+                            ("item", name),
+                        ],
+                    ),
+                    font: FontId::FiraSansItalic,
+                    colour: MessageColour::Neutral,
+                    category: FeedCategory::Inventory,
+                });
+            }
+        }
+    }
+    if actions.just_pressed(InputAction::Combine) {
+        for mut interactive in query_combine.iter_mut() {
+            interactive.clicked = true;
+        }
+    }
+}
+
+/// Draws the selection frame around the item under the grid cursor, or around its cell if it is
+/// empty. While dragging, the frame goes around the ghost instead.
+pub fn draw_grid_cursor(
+    cursor: Res<GridCursor>,
+    grid: Res<GridData>,
+    occupancy: Res<Occupancy>,
+    query_ghost: Query<&Coords, With<DragGhost>>,
+    query_items: Query<&Coords, With<Item>>,
+    mut query_edges: Query<(
+        &GridCursorEdge,
+        &mut Sprite,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    let framed = query_ghost
+        .get_single()
+        .ok()
+        .or_else(|| {
+            occupancy
+                .occupant(&cursor.pos)
+                .and_then(|entity| query_items.get(entity).ok())
+        })
+        .copied()
+        .unwrap_or_else(|| Coords::new(cursor.pos, Dimens::unit()));
+    let min = grid.offset + framed.pos.as_vec2();
+    let size = framed.dimens.as_vec2();
+    let t = FRAME_THICKNESS;
+    for (edge, mut sprite, mut transform, mut visibility) in query_edges.iter_mut() {
+        if visibility.is_visible != cursor.active {
+            visibility.is_visible = cursor.active;
+        }
+        if !cursor.active {
+            continue;
+        }
+        let (center, edge_size) = match edge.0 {
+            0 => (
+                Vec2::new(min.x + size.x * 0.5, min.y),
+                Vec2::new(size.x + t, t),
+            ),
+            1 => (
+                Vec2::new(min.x + size.x * 0.5, min.y + size.y),
+                Vec2::new(size.x + t, t),
+            ),
+            2 => (
+                Vec2::new(min.x, min.y + size.y * 0.5),
+                Vec2::new(t, size.y + t),
+            ),
+            _ => (
+                Vec2::new(min.x + size.x, min.y + size.y * 0.5),
+                Vec2::new(t, size.y + t),
+            ),
+        };
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        sprite.custom_size = Some(edge_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_jumps_the_gap_to_the_nearest_cell_ahead() {
+        let inventory = Coords::new(Pos::new(0, 0), Dimens::new(8, 5));
+        let crafting = Coords::new(Pos::new(9, 1), Dimens::new(4, 3));
+        let cells: Vec<Pos> = inventory.cells().chain(crafting.cells()).collect();
+        assert_eq!(
+            cursor_step(&cells, Pos::new(3, 2), IVec2::X),
+            Some(Pos::new(4, 2))
+        );
+        assert_eq!(
+            cursor_step(&cells, Pos::new(7, 0), IVec2::X),
+            Some(Pos::new(9, 1))
+        );
+        assert_eq!(
+            cursor_step(&cells, Pos::new(9, 3), IVec2::NEG_X),
+            Some(Pos::new(7, 3))
+        );
+        assert_eq!(cursor_step(&cells, Pos::new(3, 4), IVec2::Y), None);
+    }
+}
@@ -1,7 +1,9 @@
+use crate::config::data_locale::UiText;
 use crate::game::create_widget_hero::{
     HeroCurrentArmourDisplay, HeroCurrentShieldDisplay, HeroCurrentWeaponDisplay,
 };
 use crate::game::{EquipmentSlot, EquippedItem};
+use crate::locale::Locale;
 use bevy::prelude::*;
 
 use super::{
@@ -36,15 +38,17 @@ pub fn update_hero_stats_display(
         Query<&mut Text, With<HeroCurrentWeaponDisplay>>,
     )>,
     equipped_items_query: Query<&EquippedItem>,
+    locale: Res<Locale>,
 ) {
+    let stat = |id: UiText, value: i32| locale.ui_with(id, &[("value", value.to_string())]);
     if let Ok(mut text) = query.p0().get_single_mut() {
-        text.sections[0].value = format!("Combat Proficiency: {}", hero.combat_stats.proficiency);
+        text.sections[0].value = stat(UiText::StatProficiency, hero.combat_stats.proficiency);
     }
     if let Ok(mut text) = query.p1().get_single_mut() {
-        text.sections[0].value = format!("Damage Resistance: {}", hero.combat_stats.damage_res);
+        text.sections[0].value = stat(UiText::StatDamageRes, hero.combat_stats.damage_res);
     }
     if let Ok(mut text) = query.p2().get_single_mut() {
-        text.sections[0].value = format!("Damage Bonus: {}", hero.combat_stats.damage_bonus);
+        text.sections[0].value = stat(UiText::StatDamageBonus, hero.combat_stats.damage_bonus);
     }
     if let Ok(mut text) = query.p3().get_single_mut() {
         text.sections[0].value = format!(
@@ -52,25 +56,21 @@ pub fn update_hero_stats_display(
             hero.combat_stats.health, hero.combat_stats.max_health
         );
     }
+    let equipped = |slot: EquipmentSlot| {
+        let name = equipped_items_query
+            .iter()
+            .find(|equipped_item| equipped_item.slot == slot)
+            .map(|equipped_item| equipped_item.item.display_name(&locale))
+            .unwrap_or_else(|| locale.ui(UiText::NothingEquipped));
+        locale.ui_with(slot.equipped_ui_text(), &[("item", name)])
+    };
     if let Ok(mut text) = query.p4().get_single_mut() {
-        for equipped_item in equipped_items_query.iter() {
-            if equipped_item.slot == EquipmentSlot::Armour {
-                text.sections[0].value = format!("Armour: {}", equipped_item.name);
-            }
-        }
+        text.sections[0].value = equipped(EquipmentSlot::Armour);
     }
     if let Ok(mut text) = query.p5().get_single_mut() {
-        for equipped_item in equipped_items_query.iter() {
-            if equipped_item.slot == EquipmentSlot::Shield {
-                text.sections[0].value = format!("Shield: {}", equipped_item.name);
-            }
-        }
+        text.sections[0].value = equipped(EquipmentSlot::Shield);
     }
     if let Ok(mut text) = query.p6().get_single_mut() {
-        for equipped_item in equipped_items_query.iter() {
-            if equipped_item.slot == EquipmentSlot::Weapon {
-                text.sections[0].value = format!("Weapon: {}", equipped_item.name);
-            }
-        }
+        text.sections[0].value = equipped(EquipmentSlot::Weapon);
     }
 }
@@ -0,0 +1,334 @@
+use bevy::prelude::*;
+use bevy::text::Text2dBounds;
+
+use crate::config::data_layout::LayoutData;
+use crate::config::data_locale::UiText;
+use crate::game::combat::{Combatant, Hero};
+use crate::game::create_widget_hero::HeroStatBreakdownArea;
+use crate::game::timed_effect::TemporaryModifier;
+use crate::game::{AssetStorage, CleanupOnGameplayEnd, EquippedItem, FontId, StatBonus, TextureId};
+use crate::locale::Locale;
+use crate::mouse::{Mouse, MouseInteractive};
+use crate::positioning::Depth;
+
+/// One of the stats that gear and effects can change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeroStat {
+    MaxHealth,
+    Proficiency,
+    DamageRes,
+    DamageBonus,
+}
+
+impl HeroStat {
+    pub const ALL: [HeroStat; 4] = [
+        HeroStat::MaxHealth,
+        HeroStat::Proficiency,
+        HeroStat::DamageRes,
+        HeroStat::DamageBonus,
+    ];
+
+    /// The name of the stat.
+    pub fn ui_text(&self) -> UiText {
+        match self {
+            HeroStat::MaxHealth => UiText::StatNameMaxHealth,
+            HeroStat::Proficiency => UiText::StatNameProficiency,
+            HeroStat::DamageRes => UiText::StatNameDamageRes,
+            HeroStat::DamageBonus => UiText::StatNameDamageBonus,
+        }
+    }
+
+    pub fn of_bonus(&self, bonus: &StatBonus) -> i32 {
+        match self {
+            HeroStat::MaxHealth => bonus.max_health,
+            HeroStat::Proficiency => bonus.proficiency,
+            HeroStat::DamageRes => bonus.damage_res,
+            HeroStat::DamageBonus => bonus.damage_bonus,
+        }
+    }
+
+    pub fn of_combatant(&self, stats: &Combatant) -> i32 {
+        match self {
+            HeroStat::MaxHealth => stats.max_health,
+            HeroStat::Proficiency => stats.proficiency,
+            HeroStat::DamageRes => stats.damage_res,
+            HeroStat::DamageBonus => stats.damage_bonus,
+        }
+    }
+}
+
+/// Something that adds to the hero's stats, such as a piece of gear or an active potion effect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatSource {
+    pub name: String,
+    pub bonus: StatBonus,
+}
+
+/// Resource. Everything that currently adds to the hero's base stats, in the order in which it
+/// is added up: permanent bonuses, then gear, then active effects.
+/// Kept up to date by `update_hero_stats`.
+#[derive(Default)]
+pub struct HeroStatSources(pub Vec<StatSource>);
+
+/// Adds up the base stats and all sources. Health and negative feedback change during combat
+/// rather than being derived, so they are taken from the current stats. Health is capped at the
+/// new max health.
+pub fn effective_stats(base: &Combatant, current: &Combatant, sources: &[StatSource]) -> Combatant {
+    let mut stats = *base;
+    for source in sources {
+        stats.boost(&source.bonus);
+    }
+    stats.health = current.health.min(stats.max_health);
+    stats.negative_feedback = current.negative_feedback;
+    stats
+}
+
+/// The hero's health after using an item with the given bonus. The item's max health bonus is
+/// counted first, so that an item that does both can heal up to the new maximum.
+pub fn healed_health(stats: &Combatant, bonus: &StatBonus) -> i32 {
+    (stats.health + bonus.health)
+        .min(stats.max_health + bonus.max_health)
+        .max(0)
+}
+
+/// A line such as "Damage Bonus: +2".
+pub fn stat_change_line(name: String, value: i32, locale: &Locale) -> String {
+    locale.ui_with(
+        UiText::StatChange,
+        &[("stat", name), ("value", format!("{:+}", value))],
+    )
+}
+
+/// The lines of the breakdown of a single stat: the base value, every source that changes it,
+/// and the total.
+pub fn breakdown_lines(
+    stat: HeroStat,
+    base: &Combatant,
+    sources: &[StatSource],
+    locale: &Locale,
+) -> Vec<String> {
+    let mut total = stat.of_combatant(base);
+    let mut lines = vec![locale.ui_with(UiText::BreakdownBase, &[("value", total.to_string())])];
+    for source in sources {
+        let value = stat.of_bonus(&source.bonus);
+        if value != 0 {
+            total += value;
+            lines.push(stat_change_line(source.name.clone(), value, locale));
+        }
+    }
+    lines.push(locale.ui_with(UiText::BreakdownTotal, &[("value", total.to_string())]));
+    lines
+}
+
+/// Recomputes the hero's effective stats from the base stats, permanent bonuses, equipped gear
+/// and active effects. Nothing else should change the hero's stats, apart from health.
+pub fn update_hero_stats(
+    mut hero: ResMut<Hero>,
+    mut sources: ResMut<HeroStatSources>,
+    query_equipped: Query<&EquippedItem>,
+    query_modifiers: Query<&TemporaryModifier>,
+    locale: Res<Locale>,
+) {
+    let mut gear: Vec<StatSource> = query_equipped
+        .iter()
+        .filter_map(|equipped| {
+            equipped.item.stat_bonuses.map(|bonus| StatSource {
+                name: equipped.item.display_name(&locale),
+                bonus,
+            })
+        })
+        .collect();
+    gear.sort_by(|a, b| a.name.cmp(&b.name));
+    let effects = query_modifiers
+        .iter()
+        .filter(|modifier| modifier.on_hero)
+        .map(|modifier| StatSource {
+            name: modifier
+                .source
+                .clone()
+                .unwrap_or_else(|| locale.ui(UiText::TemporaryEffect)),
+            bonus: modifier.stat_bonus(),
+        });
+    let new_sources: Vec<StatSource> = hero
+        .permanent_bonuses
+        .iter()
+        .cloned()
+        .chain(gear)
+        .chain(effects)
+        .collect();
+    if sources.0 != new_sources {
+        sources.0 = new_sources;
+    }
+    let stats = effective_stats(&hero.base_stats, &hero.combat_stats, &sources.0);
+    if stats != hero.combat_stats {
+        hero.combat_stats = stats;
+    }
+}
+
+/// Marker component for the tooltip that breaks a stat down into where it comes from.
+#[derive(Component)]
+pub struct StatBreakdownTooltip;
+
+/// Shows the breakdown of a stat while its line on the hero panel is hovered over.
+pub fn show_stat_breakdown(
+    mut commands: Commands,
+    mut shown: Local<Option<Vec<String>>>,
+    hero: Res<Hero>,
+    sources: Res<HeroStatSources>,
+    mouse: Res<Mouse>,
+    assets: Res<AssetStorage>,
+    layout: Res<LayoutData>,
+    query_areas: Query<(&HeroStatBreakdownArea, &MouseInteractive)>,
+    query_tooltip: Query<Entity, With<StatBreakdownTooltip>>,
+    locale: Res<Locale>,
+) {
+    let lines = query_areas
+        .iter()
+        .find(|(_, interactive)| interactive.hovered && !mouse.is_dragging)
+        .map(|(area, _)| {
+            let mut lines = vec![locale.ui(area.0.ui_text())];
+            lines.extend(breakdown_lines(
+                area.0,
+                &hero.base_stats,
+                &sources.0,
+                &locale,
+            ));
+            lines
+        });
+    if *shown == lines {
+        return;
+    }
+    for entity in query_tooltip.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *shown = lines.clone();
+    let lines = match lines {
+        Some(lines) => lines,
+        None => return,
+    };
+    let entity = spawn_text_tooltip(&mut commands, &assets, &layout, mouse.position, &lines);
+    commands
+        .entity(entity)
+        .insert(Name::new("StatBreakdown"))
+        .insert(StatBreakdownTooltip);
+}
+
+/// Spawns a tooltip with a bold first line, next to the given position. It opens towards the
+/// middle of the screen, so that it doesn't cover the panels on the right.
+pub fn spawn_text_tooltip(
+    commands: &mut Commands,
+    assets: &AssetStorage,
+    layout: &LayoutData,
+    position: Vec2,
+    lines: &[String],
+) -> Entity {
+    let padding = 0.2;
+    let line_height = 0.3;
+    let size = Vec2::new(4., lines.len() as f32 * line_height + padding * 2.);
+    let x = position.x - padding - size.x * 0.5;
+    let y = (position.y - size.y * 0.5).max(size.y * 0.5);
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(size),
+                ..default()
+            },
+            texture: assets.texture(&TextureId::TooltipBackground),
+            transform: Transform::from_xyz(x, y, Depth::Cursor.z()),
+            ..default()
+        })
+        .insert(CleanupOnGameplayEnd)
+        .with_children(|parent| {
+            let text_style = TextStyle {
+                font: assets.font(&FontId::FiraSansMedium),
+                font_size: 50.0,
+                color: Color::ANTIQUE_WHITE,
+            };
+            let sections: Vec<TextSection> = lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let style = if i == 0 {
+                        TextStyle {
+                            font: assets.font(&FontId::FiraSansBold),
+                            ..text_style.clone()
+                        }
+                    } else {
+                        text_style.clone()
+                    };
+                    TextSection::new(format!("{}\n", line), style)
+                })
+                .collect();
+            parent.spawn_bundle(Text2dBundle {
+                text: Text::from_sections(sections).with_alignment(TextAlignment {
+                    horizontal: HorizontalAlign::Left,
+                    vertical: VerticalAlign::Center,
+                }),
+                text_2d_bounds: Text2dBounds {
+                    size: (size - padding * 2.) * layout.text_factor,
+                },
+                transform: Transform::from_xyz(size.x * -0.5 + padding, 0., 1.).with_scale(
+                    Vec3::new(1. / layout.text_factor, 1. / layout.text_factor, 1.),
+                ),
+                ..default()
+            });
+        })
+        .id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Combatant {
+        Combatant {
+            health: 20,
+            max_health: 20,
+            proficiency: 1,
+            ..default()
+        }
+    }
+
+    fn source(name: &str, max_health: i32, proficiency: i32) -> StatSource {
+        StatSource {
+            name: name.to_string(),
+            bonus: StatBonus {
+                max_health,
+                proficiency,
+                ..default()
+            },
+        }
+    }
+
+    #[test]
+    fn stats_do_not_drift_when_swapping_gear() {
+        let current = Combatant {
+            health: 15,
+            ..base()
+        };
+        let with_armour = effective_stats(&base(), &current, &[source("Armor", 5, 0)]);
+        assert_eq!(with_armour.max_health, 25);
+        let swapped = effective_stats(&base(), &with_armour, &[source("Rusty Armor", 2, 0)]);
+        let swapped_back = effective_stats(&base(), &swapped, &[source("Armor", 5, 0)]);
+        assert_eq!(swapped_back, with_armour);
+        // Health is kept, but capped at the new max.
+        let without = effective_stats(
+            &base(),
+            &Combatant {
+                health: 25,
+                ..with_armour
+            },
+            &[],
+        );
+        assert_eq!(without.health, 20);
+    }
+
+    #[test]
+    fn breakdown_lists_only_sources_that_change_the_stat() {
+        let sources = [source("Sword", 0, 2), source("Armor", 5, 0)];
+        assert_eq!(
+            breakdown_lines(HeroStat::Proficiency, &base(), &sources, &Locale::english()),
+            vec!["Base: 1", "Sword: +2", "Total: 3"]
+        );
+    }
+}
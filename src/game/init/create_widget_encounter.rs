@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+
+use crate::config::data_layout::LayoutData;
+use crate::game::status_tray::StatusTray;
+use crate::game::{AssetStorage, CleanupOnGameplayEnd, FontId};
+use crate::mouse::MouseInteractive;
+use crate::positioning::Depth;
+
+/// The panel that shows the enemy during a fight. Items that can be used on the enemy are
+/// dragged onto it.
+#[derive(Component)]
+pub struct EncounterPanel {
+    /// The height of the panel while it is on screen.
+    pub shown_y: f32,
+    /// The height of the panel while it is tucked away above the screen.
+    pub hidden_y: f32,
+    /// How far the panel has slid onto the screen, from 0 to 1.
+    pub progress: f32,
+}
+
+/// Marker component for the text that shows the name of the enemy.
+#[derive(Component)]
+pub struct EncounterEnemyName;
+
+/// Marker component for the sprite that shows the enemy's portrait.
+#[derive(Component)]
+pub struct EncounterPortrait;
+
+/// Marker component for the letter that stands in for a missing portrait.
+#[derive(Component)]
+pub struct EncounterPortraitInitial;
+
+/// The enemy's health bar. It catches up with the enemy's health over a short while, so that
+/// hits can be seen.
+#[derive(Component)]
+pub struct EncounterHealthBar {
+    /// The left edge of the bar, which stays put while the bar runs down.
+    pub left: f32,
+    pub full_width: f32,
+    /// The fraction of health that the bar currently shows.
+    pub shown: f32,
+}
+
+/// Marker component for the text that shows the enemy's health.
+#[derive(Component)]
+pub struct EncounterHealthText;
+
+/// Marker component for the text that shows the enemy's stats.
+#[derive(Component)]
+pub struct EncounterStats;
+
+/// Sets up the panel along the top of the middle column. It waits above the screen until a
+/// fight starts.
+pub fn create_layout_encounter(
+    mut commands: Commands,
+    layout: Res<LayoutData>,
+    assets: Res<AssetStorage>,
+) {
+    let x = layout.middle_x();
+    let width = layout.middle_width();
+    let y = layout.c_mid.encounter_y(&layout);
+    let height = layout.c_mid.encounter_height();
+    let hidden_y = layout.screen_dimens.y + height * 0.5;
+    let padding = 0.2;
+    let portrait_size = height - padding * 2.;
+    let portrait_x = width * -0.5 + padding + portrait_size * 0.5;
+    let content_x = portrait_x + portrait_size * 0.5 + 0.3;
+    let content_width = width * 0.5 - padding - content_x;
+    let text_scale = Vec3::new(1. / layout.text_factor, 1. / layout.text_factor, 1.);
+    let text_style = TextStyle {
+        font: assets.font(&FontId::FiraSansMedium),
+        font_size: 50.0,
+        color: Color::ANTIQUE_WHITE,
+    };
+    let left = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Left,
+    };
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.2, 0.2, 0.2, 0.9),
+                custom_size: Some(Vec2::new(width, height)),
+                ..default()
+            },
+            visibility: Visibility { is_visible: false },
+            transform: Transform::from_xyz(x + width * 0.5, hidden_y, Depth::Grid.z() + 50.),
+            ..default()
+        })
+        .insert(Name::new("Encounter"))
+        .insert(EncounterPanel {
+            shown_y: y + height * 0.5,
+            hidden_y,
+            progress: 0.,
+        })
+        .insert(MouseInteractive::new(Vec2::new(width, height), true))
+        .insert(CleanupOnGameplayEnd)
+        .with_children(|parent| {
+            // Portrait
+            parent
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(0.05, 0.05, 0.05, 0.8),
+                        custom_size: Some(Vec2::splat(portrait_size)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(portrait_x, 0., 1.),
+                    ..default()
+                })
+                .with_children(|portrait_parent| {
+                    portrait_parent
+                        .spawn_bundle(SpriteBundle {
+                            sprite: Sprite {
+                                custom_size: Some(Vec2::splat(portrait_size)),
+                                ..default()
+                            },
+                            visibility: Visibility { is_visible: false },
+                            transform: Transform::from_xyz(0., 0., 1.),
+                            ..default()
+                        })
+                        .insert(EncounterPortrait);
+                    portrait_parent
+                        .spawn_bundle(Text2dBundle {
+                            text: Text::from_section(
+                                "",
+                                TextStyle {
+                                    font: assets.font(&FontId::FiraSansBold),
+                                    font_size: 200.0,
+                                    color: Color::rgba(1., 1., 1., 0.4),
+                                },
+                            )
+                            .with_alignment(TextAlignment::CENTER),
+                            transform: Transform::from_xyz(0., 0., 1.).with_scale(text_scale),
+                            ..default()
+                        })
+                        .insert(EncounterPortraitInitial);
+                });
+            // Name
+            parent
+                .spawn_bundle(Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: assets.font(&FontId::FiraSansBold),
+                            font_size: 70.0,
+                            color: Color::ANTIQUE_WHITE,
+                        },
+                    )
+                    .with_alignment(left),
+                    transform: Transform::from_xyz(content_x, 0.6, 1.).with_scale(text_scale),
+                    ..default()
+                })
+                .insert(EncounterEnemyName);
+            // Health
+            parent
+                .spawn_bundle(Text2dBundle {
+                    text: Text::from_section("", text_style.clone()).with_alignment(
+                        TextAlignment {
+                            vertical: VerticalAlign::Center,
+                            horizontal: HorizontalAlign::Right,
+                        },
+                    ),
+                    transform: Transform::from_xyz(content_x + content_width, 0.6, 1.)
+                        .with_scale(text_scale),
+                    ..default()
+                })
+                .insert(EncounterHealthText);
+            let bar_size = Vec2::new(content_width, 0.25);
+            parent.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.05, 0.05, 0.05, 0.8),
+                    custom_size: Some(bar_size),
+                    ..default()
+                },
+                transform: Transform::from_xyz(content_x + content_width * 0.5, 0.15, 1.),
+                ..default()
+            });
+            parent
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb(0.8, 0.15, 0.1),
+                        custom_size: Some(bar_size),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(content_x + content_width * 0.5, 0.15, 2.),
+                    ..default()
+                })
+                .insert(EncounterHealthBar {
+                    left: content_x,
+                    full_width: content_width,
+                    shown: 1.,
+                });
+            // Stats
+            parent
+                .spawn_bundle(Text2dBundle {
+                    text: Text::from_section("", text_style.clone()).with_alignment(left),
+                    transform: Transform::from_xyz(content_x, -0.3, 1.).with_scale(text_scale),
+                    ..default()
+                })
+                .insert(EncounterStats);
+            // The effects on the enemy
+            parent
+                .spawn_bundle(SpatialBundle::from_transform(Transform::from_xyz(
+                    content_x, -0.6, 1.,
+                )))
+                .insert(Name::new("EnemyStatusTray"))
+                .insert(StatusTray {
+                    on_hero: false,
+                    icon_size: 0.5,
+                });
+        });
+}
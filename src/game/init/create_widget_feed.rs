@@ -1,8 +1,9 @@
 use bevy::prelude::*;
 
 use crate::config::data_layout::LayoutData;
-use crate::game::feed::{EventFeed, EventFeedContainer};
+use crate::game::feed::{spawn_feed_lines, EventFeed, EventFeedContainer};
 use crate::game::CleanupOnGameplayEnd;
+use crate::mouse::MouseInteractive;
 use crate::positioning::Depth;
 
 pub fn create_layout_feed(mut commands: Commands, layout: Res<LayoutData>) {
@@ -10,7 +11,7 @@ pub fn create_layout_feed(mut commands: Commands, layout: Res<LayoutData>) {
     let width = layout.left_width();
     let y = layout.c_left.feed_y();
     let height = layout.c_left.feed_height(&layout);
-    commands
+    let container = commands
         .spawn_bundle(SpriteBundle {
             sprite: Sprite {
                 color: Color::rgba(0.2, 0.2, 0.2, 0.8),
@@ -22,6 +23,10 @@ pub fn create_layout_feed(mut commands: Commands, layout: Res<LayoutData>) {
         })
         .insert(Name::new("EventFeed"))
         .insert(EventFeedContainer)
-        .insert(CleanupOnGameplayEnd);
-    commands.insert_resource(EventFeed { next_id: 0 })
+        // Hovered so that the mouse wheel scrolls it.
+        .insert(MouseInteractive::new(Vec2::new(width, height), false))
+        .insert(CleanupOnGameplayEnd)
+        .id();
+    spawn_feed_lines(&mut commands, &layout, container, width, height);
+    commands.insert_resource(EventFeed::default())
 }
@@ -2,9 +2,14 @@ use bevy::prelude::*;
 use bevy::text::Text2dBounds;
 
 use crate::config::data_layout::LayoutData;
+use crate::config::data_locale::UiText;
+use crate::game::auto_sort::SortButton;
+use crate::game::bag_upgrades::BagGrid;
+use crate::game::trash::{TrashZone, TrashZoneText};
 use crate::game::{
-    AssetStorage, CleanupOnGameplayEnd, CombineButton, Eyes, FontId, Iris, TextureId,
+    AssetStorage, CleanupOnGameplayEnd, CombineButton, Eyes, FontId, Iris, Overseer, TextureId,
 };
+use crate::locale::Locale;
 use crate::mouse::MouseInteractive;
 use crate::positioning::{Coords, Depth, Dimens, GridData, Pos};
 
@@ -18,17 +23,35 @@ pub fn create_layout_grids(
         + layout.c_mid.toasts.height.unwrap()
         + layout.c_mid.inventory.margin_bottom.unwrap_or(0.);
     let inventory_coords = Coords::new(Pos::new(0, 0), Dimens::new(8, 5));
-    create_grid(
+    let inventory_grid = create_grid(
         &mut commands,
         &assets,
         &inventory_coords.dimens,
         Vec2::new(inventory_x, inventory_y),
     );
+    commands.entity(inventory_grid).insert(BagGrid);
+
+    let x_crafting = layout.right_x() + 0.3333;
+    let y_crafting = layout.c_right.crafting_y();
+    let crafting_coords = Coords::new(Pos::new(9, 1), Dimens::new(4, 3));
+    create_grid(
+        &mut commands,
+        &assets,
+        &crafting_coords.dimens,
+        Vec2::new(x_crafting, y_crafting),
+    );
+
+    let grid = GridData {
+        offset: Vec2::new(inventory_x, inventory_y),
+        inventory: inventory_coords,
+        crafting: crafting_coords,
+        pockets: vec![],
+    };
+
     let overseer_width = layout.middle_width();
     let overseer_height = overseer_width * 0.3; // Image is 1000x300.
-    let inventory_height = 5.;
     let overseer_x = inventory_x;
-    let overseer_y = inventory_y + inventory_height - overseer_height * layout.overseer_baseline;
+    let overseer_y = overseer_y(&layout, &grid);
     commands
         .spawn_bundle(SpriteBundle {
             sprite: Sprite {
@@ -44,6 +67,7 @@ pub fn create_layout_grids(
             ..default()
         })
         .insert(Name::new("Overseer"))
+        .insert(Overseer)
         .insert(CleanupOnGameplayEnd);
 
     commands
@@ -82,21 +106,14 @@ pub fn create_layout_grids(
         .insert(Iris)
         .insert(CleanupOnGameplayEnd);
 
-    let x_crafting = layout.right_x() + 0.3333;
-    let y_crafting = layout.c_right.crafting_y();
-    let crafting_coords = Coords::new(Pos::new(9, 1), Dimens::new(4, 3));
-    create_grid(
-        &mut commands,
-        &assets,
-        &crafting_coords.dimens,
-        Vec2::new(x_crafting, y_crafting),
-    );
+    commands.insert_resource(grid);
+}
 
-    commands.insert_resource(GridData {
-        offset: Vec2::new(inventory_x, inventory_y),
-        inventory: inventory_coords,
-        crafting: crafting_coords,
-    });
+/// The y position of the bottom of the overseer, who peeks over the top of the inventory.
+pub fn overseer_y(layout: &LayoutData, grid: &GridData) -> f32 {
+    let overseer_height = layout.middle_width() * 0.3; // Image is 1000x300.
+    let inventory_top = grid.offset.y + (grid.inventory.pos.y + grid.inventory.dimens.y) as f32;
+    inventory_top - overseer_height * layout.overseer_baseline
 }
 
 /// Sets up the lower-right container.
@@ -126,10 +143,7 @@ pub fn create_layout_combine_button(
         })
         .insert(Name::new("Combine Button"))
         .insert(CombineButton {
-            coords: Coords {
-                pos: Pos::new(18, 8),
-                dimens: Dimens::new(14, 4),
-            },
+            coords: Coords::new(Pos::new(18, 8), Dimens::new(14, 4)),
         })
         .insert(MouseInteractive::new(Vec2::new(width, height), true))
         .insert(CleanupOnGameplayEnd)
@@ -172,7 +186,122 @@ pub fn create_layout_combine_button(
         });
 }
 
-fn create_grid(commands: &mut Commands, assets: &AssetStorage, dimens: &Dimens, offset: Vec2) {
+/// Sets up the button that sorts the inventory, at the right end of the toasts bar.
+pub fn create_layout_sort_button(
+    mut commands: Commands,
+    layout: Res<LayoutData>,
+    assets: Res<AssetStorage>,
+) {
+    let width = 1.3333;
+    let height = layout.c_mid.toasts.height.unwrap();
+    let x = layout.middle_x() + layout.middle_width() - width;
+    let y = layout.c_mid.toasts.margin_bottom.unwrap_or(0.);
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.35, 0.35, 0.35, 0.9),
+                custom_size: Some(Vec2::new(width, height)),
+                ..default()
+            },
+            transform: Transform::from_xyz(
+                x + width * 0.5,
+                y + height * 0.5,
+                Depth::Grid.z() + 20.,
+            ),
+            ..default()
+        })
+        .insert(Name::new("Sort Button"))
+        .insert(SortButton)
+        .insert(MouseInteractive::new(Vec2::new(width, height), true))
+        .insert(CleanupOnGameplayEnd)
+        .with_children(|parent| {
+            parent.spawn_bundle(Text2dBundle {
+                text: Text::from_section(
+                    "SORT",
+                    TextStyle {
+                        font: assets.font(&FontId::FiraSansMedium),
+                        font_size: 60.0,
+                        color: Color::ANTIQUE_WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment {
+                    horizontal: HorizontalAlign::Center,
+                    vertical: VerticalAlign::Center,
+                }),
+                transform: Transform::from_xyz(0., 0., 1.).with_scale(Vec3::new(
+                    1. / layout.text_factor,
+                    1. / layout.text_factor,
+                    1.,
+                )),
+                ..default()
+            });
+        });
+}
+
+/// Sets up the trash zone, at the left end of the toasts bar.
+pub fn create_layout_trash_zone(
+    mut commands: Commands,
+    layout: Res<LayoutData>,
+    assets: Res<AssetStorage>,
+    locale: Res<Locale>,
+) {
+    let width = 1.3333;
+    let height = layout.c_mid.toasts.height.unwrap();
+    let x = layout.middle_x();
+    let y = layout.c_mid.toasts.margin_bottom.unwrap_or(0.);
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.3, 0.15, 0.15, 0.9),
+                custom_size: Some(Vec2::new(width, height)),
+                ..default()
+            },
+            transform: Transform::from_xyz(
+                x + width * 0.5,
+                y + height * 0.5,
+                Depth::Grid.z() + 20.,
+            ),
+            ..default()
+        })
+        .insert(Name::new("Trash Zone"))
+        .insert(TrashZone)
+        .insert(MouseInteractive::new(Vec2::new(width, height), true))
+        .insert(CleanupOnGameplayEnd)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(Text2dBundle {
+                    text: Text::from_section(
+                        locale.ui(UiText::Trash),
+                        TextStyle {
+                            font: assets.font(&FontId::FiraSansMedium),
+                            font_size: 60.0,
+                            color: Color::ANTIQUE_WHITE,
+                        },
+                    )
+                    .with_alignment(TextAlignment {
+                        horizontal: HorizontalAlign::Center,
+                        vertical: VerticalAlign::Center,
+                    }),
+                    transform: Transform::from_xyz(0., 0., 1.).with_scale(Vec3::new(
+                        1. / layout.text_factor,
+                        1. / layout.text_factor,
+                        1.,
+                    )),
+                    ..default()
+                })
+                .insert(TrashZoneText);
+        });
+}
+
+/// Spawns a grid of tiles with the given dimensions, with its bottom-left corner at the offset.
+pub fn create_grid(
+    commands: &mut Commands,
+    assets: &AssetStorage,
+    dimens: &Dimens,
+    offset: Vec2,
+) -> Entity {
     commands
         .spawn_bundle(SpriteBundle {
             sprite: Sprite {
@@ -209,5 +338,6 @@ fn create_grid(commands: &mut Commands, assets: &AssetStorage, dimens: &Dimens,
                     });
                 }
             }
-        });
+        })
+        .id()
 }
@@ -2,8 +2,13 @@ use bevy::prelude::*;
 use bevy::text::Text2dBounds;
 
 use crate::config::data_layout::LayoutData;
+use crate::config::data_locale::UiText;
 use crate::game::combat::Hero;
-use crate::game::{AssetStorage, CleanupOnGameplayEnd, FontId, HealthBar};
+use crate::game::hero_stats::HeroStat;
+use crate::game::status_tray::StatusTray;
+use crate::game::{AssetStorage, CleanupOnGameplayEnd, EquipmentSlot, FontId, HealthBar};
+use crate::locale::Locale;
+use crate::mouse::MouseInteractive;
 use crate::positioning::Depth;
 
 #[derive(Component)]
@@ -36,12 +41,38 @@ pub struct HeroCurrentShieldDisplay;
 #[derive(Component)]
 pub struct HeroCurrentWeaponDisplay;
 
+/// One of the slots on the hero panel where gear can be dropped to equip it.
+/// Has the EquippedItem component while there is an item in it.
+#[derive(Component)]
+pub struct HeroEquipmentSlot(pub EquipmentSlot);
+
+/// Marker component for the sprite that shows the item in an equipment slot.
+#[derive(Component)]
+pub struct HeroEquipmentIcon;
+
+/// Marker component for the text that names an empty equipment slot.
+#[derive(Component)]
+pub struct HeroEquipmentLabel;
+
+/// The bar along the bottom of an equipment slot that shows how much durability the equipped
+/// item has left.
+#[derive(Component)]
+pub struct HeroEquipmentDurabilityBar {
+    /// The width of the bar when the item is as good as new.
+    pub full_width: f32,
+}
+
+/// The area on the hero panel that shows where a stat comes from when hovered over.
+#[derive(Component)]
+pub struct HeroStatBreakdownArea(pub HeroStat);
+
 // Ridiculous amount of magic number for the hero name and stats..
 pub fn create_layout_hero(
     mut commands: Commands,
     layout: Res<LayoutData>,
     assets: Res<AssetStorage>,
     hero: Res<Hero>,
+    locale: Res<Locale>,
 ) {
     let x = layout.right_x();
     let width = layout.right_width();
@@ -169,7 +200,10 @@ pub fn create_layout_hero(
                 .insert(HeroProficiencyDisplay)
                 .insert_bundle(Text2dBundle {
                     text: Text::from_section(
-                        format!("Combat Proficiency: {}", hero.combat_stats.proficiency),
+                        locale.ui_with(
+                            UiText::StatProficiency,
+                            &[("value", hero.combat_stats.proficiency.to_string())],
+                        ),
                         text_style_sm.clone(),
                     )
                     .with_alignment(text_alignment),
@@ -198,7 +232,10 @@ pub fn create_layout_hero(
                 .insert(HeroDamageResDisplay)
                 .insert_bundle(Text2dBundle {
                     text: Text::from_section(
-                        format!("Damage Resistance: {}", hero.combat_stats.damage_res),
+                        locale.ui_with(
+                            UiText::StatDamageRes,
+                            &[("value", hero.combat_stats.damage_res.to_string())],
+                        ),
                         text_style_sm.clone(),
                     )
                     .with_alignment(text_alignment),
@@ -227,7 +264,10 @@ pub fn create_layout_hero(
                 .insert(HeroDamageBonusDisplay)
                 .insert_bundle(Text2dBundle {
                     text: Text::from_section(
-                        format!("Damage Bonus: {}", hero.combat_stats.damage_bonus),
+                        locale.ui_with(
+                            UiText::StatDamageBonus,
+                            &[("value", hero.combat_stats.damage_bonus.to_string())],
+                        ),
                         text_style_sm.clone(),
                     )
                     .with_alignment(text_alignment),
@@ -256,8 +296,14 @@ pub fn create_layout_hero(
                 .insert(HeroStatsDisplay)
                 .insert(HeroCurrentArmourDisplay)
                 .insert_bundle(Text2dBundle {
-                    text: Text::from_section(format!("Armour: None"), text_style_sm.clone())
-                        .with_alignment(text_alignment),
+                    text: Text::from_section(
+                        locale.ui_with(
+                            EquipmentSlot::Armour.equipped_ui_text(),
+                            &[("item", locale.ui(UiText::NothingEquipped))],
+                        ),
+                        text_style_sm.clone(),
+                    )
+                    .with_alignment(text_alignment),
                     // The max size that it should fit in:
                     text_2d_bounds: Text2dBounds {
                         size: Vec2::new(
@@ -282,8 +328,14 @@ pub fn create_layout_hero(
                 .insert(HeroStatsDisplay)
                 .insert(HeroCurrentShieldDisplay)
                 .insert_bundle(Text2dBundle {
-                    text: Text::from_section(format!("Shield: None"), text_style_sm.clone())
-                        .with_alignment(text_alignment),
+                    text: Text::from_section(
+                        locale.ui_with(
+                            EquipmentSlot::Shield.equipped_ui_text(),
+                            &[("item", locale.ui(UiText::NothingEquipped))],
+                        ),
+                        text_style_sm.clone(),
+                    )
+                    .with_alignment(text_alignment),
                     // The max size that it should fit in:
                     text_2d_bounds: Text2dBounds {
                         size: Vec2::new(
@@ -308,8 +360,14 @@ pub fn create_layout_hero(
                 .insert(HeroStatsDisplay)
                 .insert(HeroCurrentWeaponDisplay)
                 .insert_bundle(Text2dBundle {
-                    text: Text::from_section(format!("Weapon: None"), text_style_sm.clone())
-                        .with_alignment(text_alignment),
+                    text: Text::from_section(
+                        locale.ui_with(
+                            EquipmentSlot::Weapon.equipped_ui_text(),
+                            &[("item", locale.ui(UiText::NothingEquipped))],
+                        ),
+                        text_style_sm.clone(),
+                    )
+                    .with_alignment(text_alignment),
                     // The max size that it should fit in:
                     text_2d_bounds: Text2dBounds {
                         size: Vec2::new(
@@ -329,6 +387,118 @@ pub fn create_layout_hero(
                     )),
                     ..default()
                 });
+            // Equipment slots, stacked along the right edge.
+            let slot_size = 1.;
+            let slot_x = width * 0.5 - health_bar_margin - slot_size * 0.5;
+            let slots = [
+                EquipmentSlot::Weapon,
+                EquipmentSlot::Armour,
+                EquipmentSlot::Shield,
+            ];
+            for (i, slot) in slots.into_iter().enumerate() {
+                let slot_y = height * 0.5
+                    - health_bar_size.y
+                    - health_bar_margin * 2.
+                    - (i as f32 + 0.5) * (slot_size + 0.1);
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(0.1, 0.1, 0.1, 0.8),
+                            custom_size: Some(Vec2::splat(slot_size)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(slot_x, slot_y, 11.),
+                        ..default()
+                    })
+                    .insert(Name::new("EquipmentSlot"))
+                    .insert(HeroEquipmentSlot(slot))
+                    .insert(MouseInteractive::new(Vec2::splat(slot_size), true))
+                    .with_children(|slot_parent| {
+                        slot_parent
+                            .spawn_bundle(SpriteBundle {
+                                sprite: Sprite {
+                                    custom_size: Some(Vec2::splat(slot_size * 0.9)),
+                                    ..default()
+                                },
+                                visibility: Visibility { is_visible: false },
+                                transform: Transform::from_xyz(0., 0., 1.),
+                                ..default()
+                            })
+                            .insert(HeroEquipmentIcon);
+                        slot_parent
+                            .spawn_bundle(SpriteBundle {
+                                sprite: Sprite {
+                                    custom_size: Some(Vec2::new(slot_size * 0.9, 0.08)),
+                                    ..default()
+                                },
+                                visibility: Visibility { is_visible: false },
+                                transform: Transform::from_xyz(0., slot_size * -0.5 + 0.07, 2.),
+                                ..default()
+                            })
+                            .insert(HeroEquipmentDurabilityBar {
+                                full_width: slot_size * 0.9,
+                            });
+                        slot_parent
+                            .spawn_bundle(Text2dBundle {
+                                text: Text::from_section(
+                                    locale.ui(slot.ui_text()),
+                                    TextStyle {
+                                        font: assets.font(&FontId::FiraSansItalic),
+                                        font_size: 40.0,
+                                        color: Color::rgba(1., 1., 1., 0.4),
+                                    },
+                                )
+                                .with_alignment(TextAlignment {
+                                    vertical: VerticalAlign::Center,
+                                    horizontal: HorizontalAlign::Center,
+                                }),
+                                transform: Transform::from_xyz(0., 0., 1.).with_scale(Vec3::new(
+                                    1. / layout.text_factor,
+                                    1. / layout.text_factor,
+                                    1.,
+                                )),
+                                ..default()
+                            })
+                            .insert(HeroEquipmentLabel);
+                    });
+            }
+            // Hovering over a stat shows where it comes from.
+            let stats_top = height * 0.5 - health_bar_size.y * 0.5 - health_bar_margin;
+            let stats_right = slot_x - slot_size * 0.5;
+            let breakdown_areas = [
+                (HeroStat::MaxHealth, 0., health_bar_size.x),
+                (HeroStat::Proficiency, 1., stats_right + 2.),
+                (HeroStat::DamageRes, 1.5, stats_right + 2.),
+                (HeroStat::DamageBonus, 2., stats_right + 2.),
+            ];
+            for (stat, offset, area_width) in breakdown_areas {
+                let area_x = if stat == HeroStat::MaxHealth {
+                    0.
+                } else {
+                    -2. + area_width * 0.5
+                };
+                parent
+                    .spawn_bundle(SpatialBundle::from_transform(Transform::from_xyz(
+                        area_x,
+                        stats_top - offset,
+                        11.,
+                    )))
+                    .insert(Name::new("StatBreakdownArea"))
+                    .insert(HeroStatBreakdownArea(stat))
+                    .insert(MouseInteractive::new(Vec2::new(area_width, 0.45), false));
+            }
+            // The effects on the hero, in the gap below the panel.
+            parent
+                .spawn_bundle(SpatialBundle::from_transform(Transform::from_xyz(
+                    width * -0.5,
+                    height * -0.5 - 0.5,
+                    11.,
+                )))
+                .insert(Name::new("HeroStatusTray"))
+                .insert(StatusTray {
+                    on_hero: true,
+                    icon_size: 0.6,
+                });
             // Health Bar
             parent
                 .spawn_bundle(SpriteBundle {
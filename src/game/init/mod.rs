@@ -1,4 +1,5 @@
 pub mod create_backpack;
+pub mod create_widget_encounter;
 pub mod create_widget_feed;
 pub mod create_widget_grids;
 pub mod create_widget_hero;
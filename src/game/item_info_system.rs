@@ -1,8 +1,15 @@
 use bevy::prelude::*;
 use bevy::text::{Text2dBounds, Text2dSize};
 
+use crate::config::data_items::ItemsData;
 use crate::config::data_layout::LayoutData;
-use crate::game::{AssetStorage, EquipmentSlot, FontId, Item, TextureId};
+use crate::config::data_locale::UiText;
+use crate::game::combat::{Combatant, Hero};
+use crate::game::durability::durability_colour;
+use crate::game::hero_stats::{healed_health, stat_change_line, HeroStat};
+use crate::game::recipe_book::RecipeBook;
+use crate::game::{AssetStorage, EquippedItem, FontId, Item, StatBonus, TextureId};
+use crate::locale::Locale;
 use crate::mouse::MouseInteractive;
 use crate::positioning::Depth;
 use crate::Mouse;
@@ -29,6 +36,18 @@ pub struct TooltipWearable;
 #[derive(Component, Debug)]
 pub struct TooltipStats;
 
+/// How the item would change the hero's stats: the difference to the equipped gear for
+/// wearables, or the stats after using it for consumables. Also lists the known recipes that use
+/// the item.
+#[derive(Component, Debug)]
+pub struct TooltipComparison;
+
+/// The bar that shows how much durability the item has left.
+#[derive(Component, Debug)]
+pub struct TooltipDurabilityBar {
+    pub fraction: f32,
+}
+
 /// === Systems ===
 
 /// Adds and removes MouseOver components to items that are being hovered over.
@@ -41,11 +60,24 @@ pub fn update_mouse_over_item_info_system(
     assets: Res<AssetStorage>,
     mouse: Res<Mouse>,
     layout: Res<LayoutData>,
+    hero: Res<Hero>,
+    recipe_book: Res<RecipeBook>,
+    items_data: Res<ItemsData>,
+    query_equipped: Query<&EquippedItem>,
+    locale: Res<Locale>,
 ) {
     // Add new item info
     for (item_entity, mouse_interaction, item) in new_mouse_over_items_query.iter() {
         if mouse_interaction.hovered {
             commands.entity(item_entity).insert(MousedOver);
+            let comparison = comparison_lines(
+                item,
+                &hero.combat_stats,
+                &recipe_book,
+                &items_data,
+                &query_equipped,
+                &locale,
+            );
 
             // Spawn the container with the sprite background:
             commands
@@ -75,11 +107,11 @@ pub fn update_mouse_over_item_info_system(
                         .spawn_bundle(Text2dBundle {
                             // Default text, will probably never be seen:
                             text: Text::from_section(
-                                &item.name,
+                                item.display_name(&locale),
                                 TextStyle {
                                     font: assets.font(&FontId::FiraSansBold),
                                     font_size: 80.0,
-                                    color: Color::ANTIQUE_WHITE,
+                                    color: item.rarity.colour().unwrap_or(Color::ANTIQUE_WHITE),
                                 },
                             )
                             .with_alignment(text_alignment),
@@ -130,11 +162,11 @@ pub fn update_mouse_over_item_info_system(
                         .insert(TooltipDescription);
                     // If applicable, spawn the wearable text:
                     if let Some(slot) = item.wearable {
-                        let slot_name = match slot {
-                            EquipmentSlot::Armour => "Armour".to_string(),
-                            EquipmentSlot::Shield => "Shield".to_string(),
-                            EquipmentSlot::Weapon => "Weapon".to_string(),
-                        };
+                        let mut slot_name = item.rarity.slot_name(slot, &locale);
+                        if let Some(durability) = item.durability {
+                            slot_name.push('\n');
+                            slot_name.push_str(&durability.describe(&locale));
+                        }
                         parent
                             .spawn_bundle(Text2dBundle {
                                 // Default text, will probably never be seen:
@@ -168,35 +200,21 @@ pub fn update_mouse_over_item_info_system(
                     let stats_text = item
                         .stat_bonuses
                         .map(|stat_bonus| {
-                            let mut stats: String = format!("Stats:\n");
+                            let mut stats = format!("{}\n", locale.ui(UiText::TooltipStats));
                             let mut stats_present = false;
-                            if stat_bonus.proficiency > 0 {
-                                stats_present = true;
-                                stats.push_str(&*format!(
-                                    "    | Combat Proficiency: {}\n",
-                                    stat_bonus.proficiency
-                                ));
-                            }
-                            if stat_bonus.damage_bonus > 0 {
-                                stats_present = true;
-                                stats.push_str(&*format!(
-                                    "    | Damage: {}\n",
-                                    stat_bonus.damage_bonus
-                                ));
-                            }
-                            if stat_bonus.damage_res > 0 {
-                                stats_present = true;
-                                stats.push_str(&*format!(
-                                    "    | Damage Resistance: {}\n",
-                                    stat_bonus.damage_res
-                                ));
-                            }
-                            if stat_bonus.max_health > 0 {
-                                stats_present = true;
-                                stats.push_str(&*format!(
-                                    "    | Max HP: {}\n",
-                                    stat_bonus.max_health
-                                ));
+                            for (id, value) in [
+                                (UiText::StatProficiency, stat_bonus.proficiency),
+                                (UiText::StatDamageBonus, stat_bonus.damage_bonus),
+                                (UiText::StatDamageRes, stat_bonus.damage_res),
+                                (UiText::StatMaxHealth, stat_bonus.max_health),
+                            ] {
+                                if value > 0 {
+                                    stats_present = true;
+                                    stats.push_str(&format!(
+                                        "    | {}\n",
+                                        locale.ui_with(id, &[("value", value.to_string())])
+                                    ));
+                                }
                             }
                             if stats_present {
                                 Some(stats)
@@ -235,6 +253,61 @@ pub fn update_mouse_over_item_info_system(
                             })
                             .insert(TooltipStats);
                     }
+                    // If applicable, spawn the durability bar:
+                    if let Some(durability) = item.durability {
+                        parent
+                            .spawn_bundle(SpriteBundle {
+                                sprite: Sprite {
+                                    color: durability_colour(durability.fraction()),
+                                    custom_size: Some(Vec2::splat(0.)), //Will be adjusted later.
+                                    ..default()
+                                },
+                                transform: Transform::from_xyz(0., 0., 1.),
+                                ..default()
+                            })
+                            .insert(TooltipDurabilityBar {
+                                fraction: durability.fraction(),
+                            });
+                    }
+                    // If applicable, spawn the comparison with the hero's current stats:
+                    if !comparison.is_empty() {
+                        let last = comparison.len() - 1;
+                        let sections =
+                            comparison
+                                .iter()
+                                .enumerate()
+                                .map(|(i, (line, change))| TextSection {
+                                    value: if i == last {
+                                        line.clone()
+                                    } else {
+                                        format!("{}\n", line)
+                                    },
+                                    style: TextStyle {
+                                        font: assets.font(&FontId::FiraSansMedium),
+                                        font_size: 60.0,
+                                        color: change_colour(*change),
+                                    },
+                                });
+                        parent
+                            .spawn_bundle(Text2dBundle {
+                                text: Text::from_sections(sections).with_alignment(text_alignment),
+                                // The max size that it should fit in:
+                                text_2d_bounds: Text2dBounds {
+                                    size: Vec2::new(
+                                        text_bounds.x * layout.text_factor,
+                                        text_bounds.y * layout.text_factor,
+                                    ),
+                                },
+                                transform: Transform::from_translation(Vec3::new(0., 0., 1.0))
+                                    .with_scale(Vec3::new(
+                                        1. / layout.text_factor,
+                                        1. / layout.text_factor,
+                                        1.,
+                                    )),
+                                ..default()
+                            })
+                            .insert(TooltipComparison);
+                    }
                 });
         }
     }
@@ -260,6 +333,8 @@ pub fn update_mouse_over_item_info_style_position_system(
         Query<(&Text2dSize, &mut Transform), With<TooltipDescription>>,
         Query<(&Text2dSize, &mut Transform), With<TooltipWearable>>,
         Query<(&Text2dSize, &mut Transform), With<TooltipStats>>,
+        Query<(&TooltipDurabilityBar, &mut Sprite, &mut Transform)>,
+        Query<(&Text2dSize, &mut Transform), With<TooltipComparison>>,
     )>,
 ) {
     if queries.p0().get_single().is_err() {
@@ -279,36 +354,70 @@ pub fn update_mouse_over_item_info_style_position_system(
         .map(|(size, _)| size.size)
         .unwrap_or(Vec2::splat(0.))
         / layout.text_factor;
+    let size_comparison = queries
+        .p6()
+        .get_single()
+        .map(|(size, _)| size.size)
+        .unwrap_or(Vec2::splat(0.))
+        / layout.text_factor;
     let padding = 0.25;
+    let bar_height = 0.12;
+    // The durability bar sits between the wearable text and the stats.
+    let size_bar = if queries.p5().is_empty() {
+        0.
+    } else {
+        bar_height + 0.1
+    };
     let container_size = Vec2::new(
         padding * 2.
             + size_name
                 .x
                 .max(size_description.x)
                 .max(size_wearable.x)
-                .max(size_stats.x),
-        padding * 2. + size_stats.y + size_wearable.y + size_description.y + size_name.y,
+                .max(size_stats.x)
+                .max(size_comparison.x),
+        padding * 2.
+            + size_comparison.y
+            + size_stats.y
+            + size_bar
+            + size_wearable.y
+            + size_description.y
+            + size_name.y,
     );
     let anchor = container_size * -0.5;
+    // Everything else is stacked on top of the comparison at the bottom.
+    let bottom = anchor.y + padding + size_comparison.y;
+
+    if let Ok((_, mut transform)) = queries.p6().get_single_mut() {
+        transform.translation.x = anchor.x + padding;
+        transform.translation.y = anchor.y + padding + size_comparison.y * 0.5;
+    }
+
+    if let Ok((bar, mut sprite, mut transform)) = queries.p5().get_single_mut() {
+        let width = (container_size.x - padding * 2.) * bar.fraction;
+        sprite.custom_size = Some(Vec2::new(width, bar_height));
+        transform.translation.x = anchor.x + padding + width * 0.5;
+        transform.translation.y = bottom + size_stats.y + size_bar * 0.5;
+    }
 
     if let Ok((_, mut transform)) = queries.p4().get_single_mut() {
         transform.translation.x = anchor.x + padding;
-        transform.translation.y = anchor.y + padding + size_stats.y * 0.5;
+        transform.translation.y = bottom + size_stats.y * 0.5;
     }
     if let Ok((_, mut transform)) = queries.p3().get_single_mut() {
         transform.translation.x = anchor.x + padding;
-        transform.translation.y = anchor.y + padding + size_stats.y + size_wearable.y * 0.5;
+        transform.translation.y = bottom + size_stats.y + size_bar + size_wearable.y * 0.5;
     }
     if let Ok((_, mut transform)) = queries.p2().get_single_mut() {
         transform.translation.x = anchor.x + padding;
         transform.translation.y =
-            anchor.y + padding + size_stats.y + size_wearable.y + size_description.y * 0.5;
+            bottom + size_stats.y + size_bar + size_wearable.y + size_description.y * 0.5;
     }
     if let Ok((_, mut transform)) = queries.p1().get_single_mut() {
         transform.translation.x = anchor.x + padding;
-        transform.translation.y = anchor.y
-            + padding
+        transform.translation.y = bottom
             + size_stats.y
+            + size_bar
             + size_wearable.y
             + size_description.y
             + size_name.y * 0.5;
@@ -332,3 +441,206 @@ pub fn update_mouse_over_item_info_style_position_system(
         };
     }
 }
+
+// === Comparison ===
+
+/// Green for changes for the better, red for changes for the worse.
+fn change_colour(change: i32) -> Color {
+    match change.signum() {
+        1 => Color::rgb(0.5, 1., 0.5),
+        -1 => Color::rgb(1., 0.5, 0.5),
+        _ => Color::ANTIQUE_WHITE,
+    }
+}
+
+/// The lines at the bottom of the tooltip, each with the change it describes. A positive change
+/// is good for the hero.
+fn comparison_lines(
+    item: &Item,
+    hero_stats: &Combatant,
+    recipe_book: &RecipeBook,
+    items_data: &ItemsData,
+    query_equipped: &Query<&EquippedItem>,
+    locale: &Locale,
+) -> Vec<(String, i32)> {
+    let mut lines = Vec::new();
+    if let Some(slot) = item.wearable {
+        let equipped = query_equipped
+            .iter()
+            .find(|equipped| equipped.slot == slot)
+            .map(|equipped| &equipped.item);
+        lines.extend(gear_comparison(item, equipped, locale));
+    } else if item.target.hero() {
+        if let Some(bonus) = item.stat_bonuses {
+            let preview = stats_preview(hero_stats, &bonus, locale);
+            if !preview.is_empty() {
+                lines.push((locale.ui(UiText::TooltipAfterUse), 0));
+                lines.extend(preview);
+            }
+        }
+        if let Some(modifier) = item.temporary_effect.as_ref().filter(|m| m.on_hero) {
+            let preview = stats_preview(hero_stats, &modifier.stat_bonus(), locale);
+            if !preview.is_empty() {
+                lines.push((
+                    locale.ui_with(
+                        UiText::TooltipWhileItLasts,
+                        &[("duration", modifier.duration.describe(locale))],
+                    ),
+                    0,
+                ));
+                lines.extend(preview);
+            }
+        }
+    }
+    let mut used_in: Vec<String> = recipe_book
+        .known
+        .iter()
+        .filter(|recipe| {
+            recipe
+                .ingredients
+                .iter()
+                .any(|ingredient| ingredient.item_id == item.id)
+        })
+        .map(|recipe| {
+            items_data
+                .try_get_item(recipe.result.clone())
+                .map(|(_, result)| result.name)
+                .unwrap_or_else(|| recipe.result.to_string())
+        })
+        .collect();
+    used_in.dedup();
+    if !used_in.is_empty() {
+        lines.push((
+            locale.ui_with(
+                UiText::TooltipIngredientIn,
+                &[("recipes", used_in.join(", "))],
+            ),
+            0,
+        ));
+    }
+    lines
+}
+
+/// How the hero's stats change when the given wearable takes the place of whatever is equipped
+/// in its slot.
+fn gear_comparison(item: &Item, equipped: Option<&Item>, locale: &Locale) -> Vec<(String, i32)> {
+    let new = item.stat_bonuses.unwrap_or_default();
+    let old = equipped
+        .and_then(|equipped| equipped.stat_bonuses)
+        .unwrap_or_default();
+    let mut lines = vec![(
+        match equipped {
+            Some(equipped) => locale.ui_with(
+                UiText::TooltipComparedTo,
+                &[("item", equipped.name.clone())],
+            ),
+            None => locale.ui(UiText::TooltipNothingEquipped),
+        },
+        0,
+    )];
+    let deltas: Vec<(String, i32)> = HeroStat::ALL
+        .iter()
+        .map(|stat| (stat, stat.of_bonus(&new) - stat.of_bonus(&old)))
+        .filter(|(_, delta)| *delta != 0)
+        .map(|(stat, delta)| {
+            let line = stat_change_line(locale.ui(stat.ui_text()), delta, locale);
+            (format!("    | {}", line), delta)
+        })
+        .collect();
+    if deltas.is_empty() {
+        lines.push((format!("    | {}", locale.ui(UiText::TooltipNoChange)), 0));
+    }
+    lines.extend(deltas);
+    lines
+}
+
+/// The hero's stats after the bonus is applied, such as "HP 12 → 20/20". Only the stats that
+/// change are listed.
+pub fn stats_preview(stats: &Combatant, bonus: &StatBonus, locale: &Locale) -> Vec<(String, i32)> {
+    let mut lines = Vec::new();
+    if bonus.health != 0 || bonus.max_health != 0 {
+        let max_health = stats.max_health + bonus.max_health;
+        let health = healed_health(stats, bonus);
+        let change = if health != stats.health {
+            health - stats.health
+        } else {
+            bonus.max_health
+        };
+        let line = locale.ui_with(
+            UiText::PreviewHealth,
+            &[
+                ("from", stats.health.to_string()),
+                ("to", health.to_string()),
+                ("max", max_health.to_string()),
+            ],
+        );
+        lines.push((format!("    | {}", line), change));
+    }
+    for stat in [
+        HeroStat::Proficiency,
+        HeroStat::DamageRes,
+        HeroStat::DamageBonus,
+    ] {
+        let delta = stat.of_bonus(bonus);
+        if delta != 0 {
+            let current = stat.of_combatant(stats);
+            let line = locale.ui_with(
+                UiText::PreviewStat,
+                &[
+                    ("stat", locale.ui(stat.ui_text())),
+                    ("from", current.to_string()),
+                    ("to", (current + delta).to_string()),
+                ],
+            );
+            lines.push((format!("    | {}", line), delta));
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_heals_up_to_the_new_maximum() {
+        let stats = Combatant {
+            health: 12,
+            max_health: 20,
+            proficiency: 3,
+            damage_res: 0,
+            damage_bonus: 1,
+            negative_feedback: 0,
+        };
+        let potion = StatBonus {
+            health: 15,
+            ..default()
+        };
+        assert_eq!(
+            stats_preview(&stats, &potion, &Locale::english()),
+            vec![("    | HP 12 → 20/20".to_string(), 8)]
+        );
+        let elixir = StatBonus {
+            max_health: 5,
+            damage_bonus: -1,
+            ..default()
+        };
+        assert_eq!(
+            stats_preview(&stats, &elixir, &Locale::english()),
+            vec![
+                ("    | HP 12 → 12/25".to_string(), 5),
+                ("    | Damage Bonus 1 → 0".to_string(), -1),
+            ]
+        );
+        // The max health bonus counts before healing is capped.
+        let tonic = StatBonus {
+            health: 15,
+            max_health: 5,
+            ..default()
+        };
+        assert_eq!(
+            stats_preview(&stats, &tonic, &Locale::english()),
+            vec![("    | HP 12 → 25/25".to_string(), 13)]
+        );
+    }
+}
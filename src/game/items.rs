@@ -1,16 +1,28 @@
 use std::fmt::Formatter;
 
 use crate::game::item_info_system::MousedOver;
+use crate::game::occupancy::Occupancy;
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::config::data_locale::UiText;
+use crate::config::data_recipes::RecipesData;
+use crate::game::affixes::{Affixes, Rarity};
+use crate::game::bag_upgrades::BagUpgrade;
+use crate::game::crafting_preview::CraftingPreview;
+use crate::game::durability::Durability;
+use crate::game::equipment::EquipEvent;
+use crate::game::hero_stats::healed_health;
+use crate::game::recipe_book::LearnRecipeEvent;
 use crate::game::TextureId;
+use crate::locale::Locale;
 use crate::mouse::MouseInteractive;
-use crate::positioning::Coords;
+use crate::positioning::{Coords, Dimens, Pos};
 
 use super::combat::Hero;
 use super::item_info_system::TooltipBg;
-use super::timed_effect::{apply_timed_modifier, TemporaryModifier};
+use super::timed_effect::{ApplyEffectEvent, TemporaryModifier};
+use super::trash::DiscardEvent;
 
 /// Marker component. This item is currently in the crafting window.
 #[derive(Component)]
@@ -39,6 +51,35 @@ impl FallingItem {
     }
 }
 
+/// The number of copies of the item that this entity represents. Every item entity has one.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Stack(pub i32);
+
+impl Stack {
+    /// How many more copies of the item fit on this stack.
+    pub fn room(&self, item: &Item) -> i32 {
+        (item.stack_limit() - self.0).max(0)
+    }
+}
+
+/// Marker component for the text that shows how many items are in a stack.
+#[derive(Component)]
+pub struct StackBadge;
+
+/// Marker component. This item has been given a quarter turn clockwise, so the dimens in its
+/// Coords are swapped compared to the item data.
+#[derive(Component)]
+pub struct Rotated;
+
+/// The rotation of an item's sprite, depending on whether it has been given a quarter turn.
+pub fn item_rotation(rotated: bool) -> Quat {
+    if rotated {
+        Quat::from_rotation_z(-std::f32::consts::FRAC_PI_2)
+    } else {
+        Quat::IDENTITY
+    }
+}
+
 /// Marker component. This item is unavailable, and must be rendered as a dark silhouette.
 #[derive(Component)]
 pub struct Silhouette;
@@ -46,7 +87,10 @@ pub struct Silhouette;
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct Item {
     pub id: ItemId,
+    /// The name and description are in the strings of each language, see `Locale::item`.
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
     pub description: String,
     pub texture_id: TextureId,
     /// If this is an item that can be worn by the hero, which slot is it in and what is the
@@ -54,6 +98,129 @@ pub struct Item {
     pub wearable: Option<EquipmentSlot>,
     pub stat_bonuses: Option<StatBonus>,
     pub temporary_effect: Option<TemporaryModifier>,
+    /// How many of this item fit in a single grid slot. Items without a max stack don't stack.
+    pub max_stack: Option<i32>,
+    /// The cells that the item occupies, relative to its bottom-left corner, for items that
+    /// aren't a filled rectangle. Items without a shape occupy every cell of their dimensions.
+    pub shape: Option<Vec<Pos>>,
+    /// Using the item makes the bag bigger.
+    pub bag_upgrade: Option<BagUpgrade>,
+    /// Gear with durability wears down in combat, and breaks when it runs out.
+    pub durability: Option<Durability>,
+    /// Loot rolls its rarity when it is found. Rarer items have affixes, which are already part
+    /// of their stat bonuses, and are named along with the item, see `display_name`.
+    #[serde(default)]
+    pub rarity: Rarity,
+    #[serde(default)]
+    pub affixes: Affixes,
+    /// Who the item can be used on. Items are used on the hero by SHIFT-clicking them, and on
+    /// the enemy by dragging them onto the encounter panel during a fight.
+    #[serde(default)]
+    pub target: UseTarget,
+    /// The damage that the item deals when it is used on the enemy.
+    pub damage: Option<i32>,
+}
+
+/// Who an item can be used on.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UseTarget {
+    #[default]
+    Hero,
+    Enemy,
+    Either,
+}
+
+impl UseTarget {
+    pub fn hero(&self) -> bool {
+        matches!(self, UseTarget::Hero | UseTarget::Either)
+    }
+
+    pub fn enemy(&self) -> bool {
+        matches!(self, UseTarget::Enemy | UseTarget::Either)
+    }
+}
+
+/// Broad categories of items, used to keep similar items together.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ItemKind {
+    Gear,
+    Potion,
+    Ingredient,
+    Scroll,
+    Other,
+}
+
+impl Item {
+    /// The name that the item is shown by, along with its affixes, e.g. "Keen Sword of Might".
+    pub fn display_name(&self, locale: &Locale) -> String {
+        locale.affixed_name(&self.name, &self.affixes)
+    }
+
+    pub fn kind(&self) -> ItemKind {
+        if self.wearable.is_some() {
+            return ItemKind::Gear;
+        }
+        match self.id {
+            ItemId::HerbRed
+            | ItemId::HerbGreen
+            | ItemId::HerbViolet
+            | ItemId::Vial
+            | ItemId::Athelas
+            | ItemId::TurtleHerb => ItemKind::Ingredient,
+            ItemId::EssenceMight
+            | ItemId::EssenceVitality
+            | ItemId::EssenceAlacrity
+            | ItemId::FlaskHealing
+            | ItemId::FlaskStrength
+            | ItemId::FlaskSkill
+            | ItemId::FlaskToughness
+            | ItemId::FlaskPoison
+            | ItemId::FireEssence
+            | ItemId::HealthPotion => ItemKind::Potion,
+            ItemId::ScrollBasic1
+            | ItemId::ScrollBasic2
+            | ItemId::ScrollBasic3
+            | ItemId::ScrollBasic4
+            | ItemId::ScrollBasic5
+            | ItemId::ScrollBasic6
+            | ItemId::ScrollBasic7
+            | ItemId::ScrollBasic8
+            | ItemId::ScrollBasic9
+            | ItemId::ScrollAlchemy1
+            | ItemId::ScrollAlchemy2
+            | ItemId::ScrollAlchemy3
+            | ItemId::ScrollAlchemy4
+            | ItemId::ScrollAlchemy5
+            | ItemId::ScrollAlchemy6
+            | ItemId::ScrollAlchemy7
+            | ItemId::ScrollAlchemy8
+            | ItemId::ScrollAlchemy9
+            | ItemId::ScrollAlchemy10
+            | ItemId::ScrollAlchemy11
+            | ItemId::ScrollAlchemy12
+            | ItemId::ScrollAlchemy13
+            | ItemId::ScrollAlchemy14
+            | ItemId::ScrollAlchemy15
+            | ItemId::ScrollKnowledge1
+            | ItemId::ScrollKnowledge2
+            | ItemId::ScrollKnowledge3 => ItemKind::Scroll,
+            _ => ItemKind::Other,
+        }
+    }
+
+    /// The grid cells that the item takes up at the origin, in its original orientation.
+    pub fn footprint(&self, dimens: Dimens) -> Coords {
+        let coords = Coords::new(Pos::default(), dimens);
+        match &self.shape {
+            Some(cells) => coords.with_cells(cells),
+            None => coords,
+        }
+    }
+
+    /// The maximum number of copies of this item that a single stack can hold.
+    pub fn stack_limit(&self) -> i32 {
+        self.max_stack.unwrap_or(1).max(1)
+    }
 }
 
 impl Default for Item {
@@ -66,11 +233,20 @@ impl Default for Item {
             wearable: None,
             stat_bonuses: Default::default(),
             temporary_effect: Default::default(),
+            max_stack: None,
+            shape: None,
+            bag_upgrade: None,
+            durability: None,
+            rarity: Rarity::Common,
+            affixes: Affixes::default(),
+            target: UseTarget::Hero,
+            damage: None,
         }
     }
 }
 
-#[derive(Default, Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StatBonus {
     pub health: i32,
     pub max_health: i32,
@@ -79,6 +255,16 @@ pub struct StatBonus {
     pub damage_res: i32,
 }
 
+impl std::ops::AddAssign for StatBonus {
+    fn add_assign(&mut self, other: Self) {
+        self.health += other.health;
+        self.max_health += other.max_health;
+        self.proficiency += other.proficiency;
+        self.damage_bonus += other.damage_bonus;
+        self.damage_res += other.damage_res;
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ItemId {
     Croissant,
@@ -103,6 +289,7 @@ pub enum ItemId {
     FlaskStrength,
     FlaskSkill,
     FlaskToughness,
+    FlaskPoison,
     SwordRusty,
     Sword,
     SwordMasterwork,
@@ -146,6 +333,8 @@ pub enum ItemId {
     ScrollKnowledge1,
     ScrollKnowledge2,
     ScrollKnowledge3,
+    BiggerPouch,
+    PotionBelt,
 }
 
 impl std::fmt::Display for ItemId {
@@ -161,99 +350,124 @@ pub enum EquipmentSlot {
     Shield,
 }
 
+impl EquipmentSlot {
+    /// The name of the slot.
+    pub fn ui_text(&self) -> UiText {
+        match self {
+            EquipmentSlot::Armour => UiText::SlotArmour,
+            EquipmentSlot::Weapon => UiText::SlotWeapon,
+            EquipmentSlot::Shield => UiText::SlotShield,
+        }
+    }
+
+    /// What is worn in the slot, e.g. "Armour: {item}".
+    pub fn equipped_ui_text(&self) -> UiText {
+        match self {
+            EquipmentSlot::Armour => UiText::EquippedArmour,
+            EquipmentSlot::Weapon => UiText::EquippedWeapon,
+            EquipmentSlot::Shield => UiText::EquippedShield,
+        }
+    }
+}
+
+/// Component for the slots on the hero panel that have an item in them.
 #[derive(Component, Debug)]
 pub struct EquippedItem {
     pub slot: EquipmentSlot,
-    pub name: String,
-    pub stat_bonus: StatBonus,
+    pub item: Item,
 }
 
 pub fn consume_item(
     mut commands: Commands,
     mut hero: ResMut<Hero>,
-    items: Query<(Entity, &Item, &MouseInteractive)>,
-    equipped_items_query: Query<&EquippedItem>,
+    mut learn_events: EventWriter<LearnRecipeEvent>,
+    mut equip_events: EventWriter<EquipEvent>,
+    mut effect_events: EventWriter<ApplyEffectEvent>,
+    mut occupancy: ResMut<Occupancy>,
+    recipes_data: Res<RecipesData>,
+    mut items: Query<(Entity, &Item, &MouseInteractive, &mut Stack)>,
     tooltips: Query<Entity, With<TooltipBg>>,
 ) {
-    for (e, item, interactive) in items.iter() {
-        if interactive.shift_clicked {
-            // Unequip any items already equipped that the new item can override.
-            if let Some(new_slot) = item.wearable {
-                for currently_equipped_item in equipped_items_query.iter() {
-                    if currently_equipped_item.slot == new_slot {
-                        hero.combat_stats.max_health -=
-                            currently_equipped_item.stat_bonus.max_health;
-                        hero.combat_stats.proficiency -=
-                            currently_equipped_item.stat_bonus.proficiency;
-                        hero.combat_stats.damage_res -=
-                            currently_equipped_item.stat_bonus.damage_res;
-                        hero.combat_stats.damage_bonus -=
-                            currently_equipped_item.stat_bonus.damage_bonus;
-                    }
-                }
-                // Create a new entity with an EquippedItem component to represent Equipped Items on the Hero
-                if let Some(stats) = item.stat_bonuses {
-                    commands.spawn().insert(EquippedItem {
-                        slot: new_slot,
-                        name: item.clone().name,
-                        stat_bonus: StatBonus {
-                            health: 0,
-                            max_health: stats.max_health,
-                            proficiency: stats.proficiency,
-                            damage_bonus: stats.damage_bonus,
-                            damage_res: stats.damage_res,
-                        },
-                    });
-                }
+    for (e, item, interactive, mut stack) in items.iter_mut() {
+        // Items for the enemy are thrown by dragging them onto the encounter panel instead.
+        if interactive.shift_clicked && item.target.hero() {
+            let mut consumed = false;
+            // Gear is put on rather than used up. Its stat bonuses last while it is equipped.
+            if item.wearable.is_some() {
+                equip_events.send(EquipEvent::Equip(item.clone()));
+                consumed = true;
+            } else if let Some(stats) = item.stat_bonuses {
+                hero.combat_stats.health = healed_health(&hero.combat_stats, &stats);
+                hero.add_permanent_bonus(&item.name, stats);
+                consumed = true;
             }
 
-            if let Some(stats) = item.stat_bonuses {
-                hero.combat_stats.health = (hero.combat_stats.health + stats.health)
-                    .clamp(0, hero.combat_stats.max_health);
-                hero.combat_stats.max_health += stats.max_health;
-                hero.combat_stats.proficiency += stats.proficiency;
-                hero.combat_stats.damage_res += stats.damage_res;
-                hero.combat_stats.damage_bonus += stats.damage_bonus;
-
-                commands.entity(e).despawn_recursive();
-                for tooltip in tooltips.iter() {
-                    commands.entity(tooltip).despawn_recursive();
+            // Reading a scroll teaches the recipes that are written on it.
+            let taught_recipes = recipes_data.taught_by(&item.id);
+            if !taught_recipes.is_empty() {
+                for recipe in taught_recipes {
+                    learn_events.send(LearnRecipeEvent(recipe));
                 }
+                consumed = true;
             }
 
             if let Some(modifier) = item.clone().temporary_effect {
-                apply_timed_modifier(modifier, &mut commands);
-                commands.entity(e).despawn_recursive();
-                for tooltip in tooltips.iter() {
-                    commands.entity(tooltip).despawn_recursive();
+                let modifier = TemporaryModifier {
+                    source: Some(item.name.clone()),
+                    icon: Some(item.texture_id),
+                    item: Some(item.id.clone()),
+                    ..modifier
+                };
+                effect_events.send(ApplyEffectEvent(modifier));
+                consumed = true;
+            }
+
+            // Only one item is used up, even if it is part of a stack.
+            if consumed {
+                stack.0 -= 1;
+                if stack.0 <= 0 {
+                    commands.entity(e).despawn_recursive();
+                    // Free up the cells right away, so that a swapped out item can take them.
+                    occupancy.remove(e);
+                    for tooltip in tooltips.iter() {
+                        commands.entity(tooltip).despawn_recursive();
+                    }
                 }
             }
         }
     }
 }
 
+/// Puts the whole stack in the trash rather than deleting it outright, so that it can be taken
+/// back out until the hero leaves the room.
 pub fn delete_item_system(
-    mut commands: Commands,
-    items: Query<(Entity, &MouseInteractive), With<Item>>,
-    tooltips: Query<Entity, With<TooltipBg>>,
+    mut discard: EventWriter<DiscardEvent>,
+    items: Query<(Entity, &MouseInteractive, &Stack), With<Item>>,
 ) {
-    for (e, interactive) in items.iter() {
+    for (entity, interactive, stack) in items.iter() {
         if interactive.ctrl_alt_clicked {
-            commands.entity(e).despawn_recursive();
-            for tooltip in tooltips.iter() {
-                commands.entity(tooltip).despawn_recursive();
-            }
+            discard.send(DiscardEvent {
+                entity,
+                count: stack.0,
+            });
         }
     }
 }
 
 /// Apply a dark scrim to the item that is being dragged.
-pub fn apply_silhouette(mut query: Query<(&mut Sprite, Option<&Silhouette>), With<Item>>) {
-    for (mut sprite, silhouette) in query.iter_mut() {
+/// The crafting preview is drawn translucent, to distinguish it from actual items.
+pub fn apply_silhouette(
+    mut query: Query<
+        (&mut Sprite, Option<&Silhouette>, Option<&CraftingPreview>),
+        Or<(With<Item>, With<CraftingPreview>)>,
+    >,
+) {
+    for (mut sprite, silhouette, preview) in query.iter_mut() {
+        let alpha = if preview.is_some() { 0.5 } else { 1. };
         sprite.color = if silhouette.is_some() {
-            Color::rgba(0.1, 0.1, 0.1, 1.)
+            Color::rgba(0.1, 0.1, 0.1, alpha)
         } else {
-            Color::rgb(1., 1., 1.)
+            Color::rgba(1., 1., 1., alpha)
         };
     }
 }
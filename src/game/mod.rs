@@ -11,17 +11,31 @@ pub use self::sim::*;
 pub use self::spawn_item_system::*;
 pub use self::state::*;
 
+pub mod affixes;
 pub mod assets;
+pub mod auto_sort;
+pub mod bag_upgrades;
 pub mod camera;
 pub mod combining_system;
 pub mod components;
+pub mod context_menu;
+pub mod crafting_preview;
 pub mod dragging;
+pub mod durability;
+pub mod encounter;
+pub mod equipment;
+pub mod grid_cursor;
 pub mod health_bar_system;
+pub mod hero_stats;
 pub mod init;
 pub mod item_info_system;
 pub mod items;
+pub mod occupancy;
+pub mod recipe_book;
 pub mod recipes;
 pub mod sim;
 pub mod spawn_item_system;
 pub mod state;
+pub mod status_tray;
 pub mod timed_effect;
+pub mod trash;
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::game::items::Item;
+use crate::positioning::{Coords, Pos};
+
+/// Resource. Keeps track of which item occupies each cell of the bag and crafting grids,
+/// so that placement checks don't have to look at every item.
+///
+/// Items that were moved or spawned are added at the start of every frame, so it includes every
+/// change made during the previous frame. Items that are spawned during the current frame are not
+/// in it yet. Despawned items are removed at the end of the frame that they were despawned in.
+#[derive(Default)]
+pub struct Occupancy {
+    cells: HashMap<Pos, Entity>,
+    items: HashMap<Entity, Coords>,
+}
+
+impl Occupancy {
+    /// Adds the item at the given Coords, or moves it there if it was already on the grid.
+    pub fn insert(&mut self, entity: Entity, coords: Coords) {
+        self.remove(entity);
+        for pos in coords.cells() {
+            self.cells.insert(pos, entity);
+        }
+        self.items.insert(entity, coords);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(coords) = self.items.remove(&entity) {
+            for pos in coords.cells() {
+                if self.cells.get(&pos) == Some(&entity) {
+                    self.cells.remove(&pos);
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.items.clear();
+    }
+
+    /// The item that occupies the given cell, if any.
+    pub fn occupant(&self, pos: &Pos) -> Option<Entity> {
+        self.cells.get(pos).copied()
+    }
+
+    /// All items that occupy at least one of the cells of the given Coords, without duplicates.
+    pub fn occupants(&self, coords: &Coords) -> Vec<Entity> {
+        let mut occupants: Vec<Entity> = coords
+            .cells()
+            .filter_map(|pos| self.occupant(&pos))
+            .collect();
+        occupants.sort();
+        occupants.dedup();
+        occupants
+    }
+
+    /// Returns true iff none of the cells of the given Coords are occupied, not counting the
+    /// items in `ignore`.
+    pub fn is_free(&self, coords: &Coords, ignore: &[Entity]) -> bool {
+        coords.cells().all(|pos| match self.occupant(&pos) {
+            Some(entity) => ignore.contains(&entity),
+            None => true,
+        })
+    }
+}
+
+/// Keeps the Occupancy up to date as items are spawned and moved.
+pub fn update_occupancy(
+    mut occupancy: ResMut<Occupancy>,
+    query: Query<(Entity, &Coords), (With<Item>, Changed<Coords>)>,
+) {
+    for (entity, coords) in query.iter() {
+        occupancy.insert(entity, *coords);
+    }
+}
+
+/// Takes despawned items out of the Occupancy. Removed components are only known until the end of
+/// the frame, so this has to run after the systems that despawn items, in the same frame.
+pub fn remove_despawned_items(mut occupancy: ResMut<Occupancy>, removed: RemovedComponents<Item>) {
+    for entity in removed.iter() {
+        occupancy.remove(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::data_items::ItemsData;
+    use crate::game::ItemId;
+    use crate::positioning::Dimens;
+
+    /// Despawns the items from a regular system, like combining or merging stacks does.
+    struct DespawnItems(bool);
+
+    fn despawn_items(
+        mut commands: Commands,
+        despawn: Res<DespawnItems>,
+        query: Query<Entity, With<Item>>,
+    ) {
+        if despawn.0 {
+            for entity in query.iter() {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    #[test]
+    fn despawned_items_free_their_cells() {
+        let items: ItemsData =
+            ron::de::from_str(include_str!("../../assets/config/default/data.items.ron")).unwrap();
+        let (_, vial) = items.try_get_item(ItemId::Vial).unwrap();
+        let mut app = App::new();
+        app.init_resource::<Occupancy>()
+            .insert_resource(DespawnItems(false))
+            .add_system_to_stage(CoreStage::PreUpdate, update_occupancy)
+            .add_system(despawn_items)
+            .add_system_to_stage(CoreStage::PostUpdate, remove_despawned_items);
+        let coords = Coords::new(Pos::new(1, 1), Dimens::unit());
+        app.world.spawn().insert(vial).insert(coords);
+        app.update();
+        assert!(!app.world.resource::<Occupancy>().is_free(&coords, &[]));
+        app.world.resource_mut::<DespawnItems>().0 = true;
+        app.update();
+        assert!(app.world.resource::<Occupancy>().is_free(&coords, &[]));
+    }
+}
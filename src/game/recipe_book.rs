@@ -0,0 +1,225 @@
+use bevy::prelude::*;
+use bevy::text::Text2dBounds;
+
+use crate::config::config_input::InputAction;
+use crate::config::data_items::ItemsData;
+use crate::config::data_layout::LayoutData;
+use crate::config::data_locale::UiText;
+use crate::game::feed::{AddFeedItemEvent, FeedCategory, MessageColour};
+use crate::game::recipes::Recipe;
+use crate::game::{AssetStorage, CleanupOnGameplayEnd, FontId, ItemId, TextureId};
+use crate::input::Actions;
+use crate::locale::Locale;
+use crate::positioning::{Depth, Dimens};
+
+/// Resource. Keeps track of all recipes that the player has learned so far, in the order in
+/// which they were learned.
+#[derive(Default)]
+pub struct RecipeBook {
+    pub known: Vec<Recipe>,
+    /// Whether the recipe book panel is currently shown.
+    pub open: bool,
+}
+
+impl RecipeBook {
+    pub fn knows(&self, recipe: &Recipe) -> bool {
+        self.known.contains(recipe)
+    }
+
+    /// Adds the recipe to the book. Returns false if the recipe was already known.
+    pub fn learn(&mut self, recipe: &Recipe) -> bool {
+        if self.knows(recipe) {
+            false
+        } else {
+            self.known.push(recipe.clone());
+            true
+        }
+    }
+}
+
+/// Broadcast this event to add a recipe to the recipe book.
+/// Sent when reading a scroll, or when a recipe is discovered by experimenting.
+pub struct LearnRecipeEvent(pub Recipe);
+
+/// Marker component for the recipe book panel.
+#[derive(Component)]
+pub struct RecipeBookPanel;
+
+pub fn learn_recipes(
+    mut events: EventReader<LearnRecipeEvent>,
+    mut feed: EventWriter<AddFeedItemEvent>,
+    mut book: ResMut<RecipeBook>,
+    items_data: Res<ItemsData>,
+    locale: Res<Locale>,
+) {
+    for LearnRecipeEvent(recipe) in events.iter() {
+        if book.learn(recipe) {
+            let name = items_data
+                .try_get_item(recipe.result.clone())
+                .map(|(_, item)| item.name)
+                .unwrap_or_else(|| recipe.result.to_string());
+            feed.send(AddFeedItemEvent {
+                message: locale.ui_with(UiText::RecipeLearned, &[("recipe", name)]),
+                font: FontId::FiraSansMedium,
+                colour: MessageColour::MinorPositive,
+                category: FeedCategory::Inventory,
+            });
+        }
+    }
+}
+
+pub fn toggle_recipe_book(actions: Actions, mut book: ResMut<RecipeBook>) {
+    if actions.just_pressed(InputAction::RecipeBook) {
+        book.open = !book.open;
+    }
+}
+
+/// Rebuilds the recipe book panel whenever the book is opened, closed or updated.
+pub fn draw_recipe_book(
+    mut commands: Commands,
+    book: Res<RecipeBook>,
+    assets: Res<AssetStorage>,
+    layout: Res<LayoutData>,
+    items_data: Res<ItemsData>,
+    actions: Actions,
+    locale: Res<Locale>,
+    query_panel: Query<Entity, With<RecipeBookPanel>>,
+) {
+    if !book.is_changed() && !locale.is_changed() {
+        return;
+    }
+    for entity in query_panel.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !book.open {
+        return;
+    }
+    let padding = 0.25;
+    let row_height = 0.55;
+    let header_height = 0.8;
+    let icon_size = 0.45;
+    let nr_columns = 2;
+    let nr_rows = book.known.len().div_ceil(nr_columns).max(1);
+    let width = layout.middle_width();
+    let column_width = (width - padding * 2.) / nr_columns as f32;
+    let height = padding * 2. + header_height + nr_rows as f32 * row_height;
+    let text_alignment = TextAlignment {
+        horizontal: HorizontalAlign::Left,
+        vertical: VerticalAlign::Center,
+    };
+    let text_scale = Vec3::new(1. / layout.text_factor, 1. / layout.text_factor, 1.);
+    let text_style = TextStyle {
+        font: assets.font(&FontId::FiraSansMedium),
+        font_size: 60.0,
+        color: Color::ANTIQUE_WHITE,
+    };
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(width, height)),
+                ..default()
+            },
+            texture: assets.texture(&TextureId::TooltipBackground),
+            transform: Transform::from_xyz(
+                layout.middle_x() + width * 0.5,
+                layout.screen_dimens.y * 0.5,
+                Depth::Foreground.z(),
+            ),
+            ..default()
+        })
+        .insert(Name::new("RecipeBook"))
+        .insert(RecipeBookPanel)
+        .insert(CleanupOnGameplayEnd)
+        .with_children(|parent| {
+            let anchor = Vec2::new(width * -0.5 + padding, height * 0.5 - padding);
+            let title = locale.ui_with(
+                if book.known.is_empty() {
+                    UiText::RecipeBookTitleEmpty
+                } else {
+                    UiText::RecipeBookTitle
+                },
+                &[("key", actions.key_name(InputAction::RecipeBook))],
+            );
+            parent.spawn_bundle(Text2dBundle {
+                text: Text::from_section(
+                    title,
+                    TextStyle {
+                        font: assets.font(&FontId::FiraSansBold),
+                        font_size: 80.0,
+                        color: Color::ANTIQUE_WHITE,
+                    },
+                )
+                .with_alignment(text_alignment),
+                text_2d_bounds: Text2dBounds {
+                    size: Vec2::new(
+                        (width - padding * 2.) * layout.text_factor,
+                        header_height * layout.text_factor,
+                    ),
+                },
+                transform: Transform::from_xyz(anchor.x, anchor.y - header_height * 0.5, 1.)
+                    .with_scale(text_scale),
+                ..default()
+            });
+            for (i, recipe) in book.known.iter().enumerate() {
+                let column = i % nr_columns;
+                let row = i / nr_columns;
+                let mut x = anchor.x + column as f32 * column_width;
+                let y = anchor.y - header_height - (row as f32 + 0.5) * row_height;
+                let spawn_icon = |parent: &mut ChildBuilder, x: f32, item_id: &ItemId| {
+                    if let Some((dimens, item)) = items_data.try_get_item(item_id.clone()) {
+                        parent.spawn_bundle(SpriteBundle {
+                            sprite: Sprite {
+                                custom_size: Some(icon_dimens(dimens, icon_size)),
+                                ..default()
+                            },
+                            texture: assets.texture(&item.texture_id),
+                            transform: Transform::from_xyz(x + icon_size * 0.5, y, 1.),
+                            ..default()
+                        });
+                    }
+                };
+                let spawn_text = |parent: &mut ChildBuilder, x: f32, text: &str| {
+                    parent.spawn_bundle(Text2dBundle {
+                        text: Text::from_section(text, text_style.clone())
+                            .with_alignment(text_alignment),
+                        transform: Transform::from_xyz(x, y, 1.).with_scale(text_scale),
+                        ..default()
+                    });
+                };
+                for (j, output) in recipe.outputs().iter().enumerate() {
+                    if j > 0 {
+                        spawn_text(parent, x, "+");
+                        x += 0.3;
+                    }
+                    spawn_icon(parent, x, output);
+                    x += icon_size + 0.1;
+                }
+                spawn_text(parent, x, "=");
+                x += 0.3;
+                // Catalysts are listed last, since they are not used up.
+                let mut ingredients = recipe.ingredients.clone();
+                ingredients.sort_by_key(|ingredient| ingredient.catalyst);
+                for (j, ingredient) in ingredients.iter().enumerate() {
+                    if ingredient.catalyst && (j == 0 || !ingredients[j - 1].catalyst) {
+                        spawn_text(parent, x, "with");
+                        x += 0.55;
+                    } else if j > 0 {
+                        spawn_text(parent, x, "+");
+                        x += 0.3;
+                    }
+                    if ingredient.quantity > 1 {
+                        spawn_text(parent, x, &format!("{}x", ingredient.quantity));
+                        x += 0.35;
+                    }
+                    spawn_icon(parent, x, &ingredient.item_id);
+                    x += icon_size + 0.1;
+                }
+            }
+        });
+}
+
+/// Scale the item's dimensions down so that it fits in a square icon of the given size, without
+/// distorting its aspect ratio.
+pub fn icon_dimens(dimens: Dimens, icon_size: f32) -> Vec2 {
+    dimens.as_vec2() * (icon_size / dimens.x.max(dimens.y) as f32)
+}
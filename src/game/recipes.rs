@@ -1,14 +1,129 @@
+use std::collections::HashMap;
+
 use crate::game::items::ItemId;
+use crate::positioning::Pos;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct Recipe {
+    /// The main product of the recipe. This is the item that the recipe is known by.
     pub result: ItemId,
+    /// Additional items that are produced alongside the result, such as an empty vial that is
+    /// left over.
+    #[serde(default)]
+    pub byproducts: Vec<ItemId>,
     pub ingredients: Vec<Ingredient>,
+    /// The items (typically scrolls) that teach this recipe when consumed.
+    /// A recipe that no item teaches can still be discovered by experimenting.
+    #[serde(default)]
+    pub taught_by: Vec<ItemId>,
+    /// If the contents of the crafting area match several recipes, the one with the highest
+    /// priority is used.
+    #[serde(default)]
+    pub priority: i32,
+    /// If present, the ingredients must be laid out in the crafting area in exactly this
+    /// arrangement. The positions are relative to each other, so the arrangement as a whole can
+    /// be placed anywhere in the crafting area.
+    /// Must contain exactly the same items as the list of ingredients.
+    #[serde(default)]
+    pub shape: Option<Vec<(Pos, ItemId)>>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct Ingredient {
     pub item_id: ItemId,
     pub quantity: i32,
+    /// Catalysts must be present in the crafting area, but are not used up by crafting.
+    #[serde(default)]
+    pub catalyst: bool,
+}
+
+impl Recipe {
+    /// Returns true iff the given items are exactly the ingredients of this recipe, no more and
+    /// no less. For shaped recipes, the positions of the items must also match the shape.
+    pub fn matches(&self, items: &[(Pos, ItemId)]) -> bool {
+        if count_ids(items.iter().map(|(_, id)| id)) != self.ingredient_counts() {
+            return false;
+        }
+        if let Some(shape) = &self.shape {
+            let mut expected = normalise(shape);
+            let mut actual = normalise(items);
+            expected.sort_by_key(|(pos, _)| *pos);
+            actual.sort_by_key(|(pos, _)| *pos);
+            return expected == actual;
+        }
+        true
+    }
+
+    /// If the given items are a subset of the ingredients of this recipe, returns the ingredients
+    /// that are still missing. Returns None if any of the items don't belong in this recipe.
+    /// The arrangement of shaped recipes is not taken into account.
+    pub fn missing_ingredients(&self, items: &[(Pos, ItemId)]) -> Option<Vec<Ingredient>> {
+        let mut remaining = self.ingredient_counts();
+        for (_, id) in items {
+            let quantity = remaining.get_mut(id)?;
+            if *quantity == 0 {
+                return None;
+            }
+            *quantity -= 1;
+        }
+        Some(
+            self.ingredients
+                .iter()
+                .filter_map(|ingredient| {
+                    let quantity = remaining.remove(&ingredient.item_id)?;
+                    (quantity > 0).then(|| Ingredient {
+                        item_id: ingredient.item_id.clone(),
+                        quantity,
+                        catalyst: ingredient.catalyst,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// All items produced by this recipe: the result, followed by the byproducts.
+    pub fn outputs(&self) -> Vec<ItemId> {
+        std::iter::once(self.result.clone())
+            .chain(self.byproducts.iter().cloned())
+            .collect()
+    }
+
+    /// How many of each item are used up by this recipe. Catalysts are left out.
+    pub fn consumed_counts(&self) -> HashMap<ItemId, i32> {
+        let mut counts = HashMap::new();
+        for ingredient in self.ingredients.iter().filter(|i| !i.catalyst) {
+            *counts.entry(ingredient.item_id.clone()).or_insert(0) += ingredient.quantity;
+        }
+        counts.retain(|_, quantity| *quantity > 0);
+        counts
+    }
+
+    /// How many of each item this recipe requires.
+    pub fn ingredient_counts(&self) -> HashMap<ItemId, i32> {
+        let mut counts = HashMap::new();
+        for ingredient in &self.ingredients {
+            *counts.entry(ingredient.item_id.clone()).or_insert(0) += ingredient.quantity;
+        }
+        counts.retain(|_, quantity| *quantity > 0);
+        counts
+    }
+}
+
+fn count_ids<'a>(ids: impl Iterator<Item = &'a ItemId>) -> HashMap<ItemId, i32> {
+    let mut counts = HashMap::new();
+    for id in ids {
+        *counts.entry(id.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Moves the arrangement so that its bottom-left corner is at the origin.
+fn normalise(items: &[(Pos, ItemId)]) -> Vec<(Pos, ItemId)> {
+    let min_x = items.iter().map(|(pos, _)| pos.x).min().unwrap_or(0);
+    let min_y = items.iter().map(|(pos, _)| pos.y).min().unwrap_or(0);
+    items
+        .iter()
+        .map(|(pos, id)| (pos.plus_xy(-min_x, -min_y), id.clone()))
+        .collect()
 }
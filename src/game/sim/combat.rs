@@ -1,13 +1,17 @@
-use crate::game::sim::event_handling::SimMessageEvent;
-use crate::game::ItemId;
+use crate::game::durability::WearGearEvent;
+use crate::game::hero_stats::StatSource;
+use crate::game::sim::event_handling::{MessagePayload, SimMessageEvent};
 use crate::game::{sim::dungeon_components::TextType, StatBonus};
+use crate::game::{EquipmentSlot, ItemId, TextureId};
 use bevy::prelude::*;
 use bevy_inspector_egui::Inspectable;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::fmt::Formatter;
 
-#[derive(Component, Default, Copy, Clone, Inspectable, Serialize, Deserialize, Debug)]
+#[derive(
+    Component, Default, Copy, Clone, Inspectable, Serialize, Deserialize, Debug, PartialEq, Eq,
+)]
 pub struct Combatant {
     pub health: i32,
     pub max_health: i32,
@@ -28,7 +32,7 @@ impl std::fmt::Display for Combatant {
 }
 
 impl Combatant {
-    pub fn boost(mut self, stats: StatBonus) {
+    pub fn boost(&mut self, stats: &StatBonus) {
         self.max_health += stats.max_health;
         self.proficiency += stats.proficiency;
         self.damage_res += stats.damage_res;
@@ -48,9 +52,41 @@ pub enum CombatState {
 
 #[derive(Default)]
 pub struct Hero {
+    /// The hero's stats without any gear or effects.
+    pub base_stats: Combatant,
+    /// Bonuses from consumables that last for the rest of the run.
+    pub permanent_bonuses: Vec<StatSource>,
+    /// The stats that are used in combat. Apart from health and negative feedback, these are
+    /// derived from the base stats and everything that adds to them, see `update_hero_stats`.
     pub combat_stats: Combatant,
 }
 
+impl Hero {
+    pub fn new(base_stats: Combatant) -> Self {
+        Hero {
+            base_stats,
+            permanent_bonuses: vec![],
+            combat_stats: base_stats,
+        }
+    }
+
+    /// Permanently raises the hero's stats. Bonuses from the same source are added together.
+    /// Healing is not a lasting bonus, so it is left out.
+    pub fn add_permanent_bonus(&mut self, name: &str, bonus: StatBonus) {
+        let bonus = StatBonus { health: 0, ..bonus };
+        if bonus == StatBonus::default() {
+            return;
+        }
+        match self.permanent_bonuses.iter_mut().find(|s| s.name == name) {
+            Some(source) => source.bonus += bonus,
+            None => self.permanent_bonuses.push(StatSource {
+                name: name.to_string(),
+                bonus,
+            }),
+        }
+    }
+}
+
 #[derive(Default, Clone, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
 pub enum EnemyId {
     #[default]
@@ -69,9 +105,22 @@ pub enum EnemyId {
 pub struct Enemy {
     pub enemy_id: EnemyId,
     pub combat_stats: Combatant,
+    /// The stats of the enemy before any effects. See `update_enemy_stats`.
+    #[serde(skip)]
+    pub base_stats: Combatant,
+    /// The name is in the strings of each language, see `Locale::enemy`.
+    #[serde(default)]
     pub name: String,
+    /// The picture of the enemy on the encounter panel. Enemies without one are shown by the
+    /// first letter of their name.
+    #[serde(default)]
+    pub portrait: Option<TextureId>,
     pub enter_combat_text: TextType,
     pub drop_table: DropTable,
+    /// Counts up whenever the hero meets an enemy, so that two enemies of the same kind in a row
+    /// can be told apart.
+    #[serde(skip)]
+    pub encounter: u32,
 }
 
 impl std::fmt::Display for Enemy {
@@ -91,15 +140,22 @@ impl Default for Enemy {
         Enemy {
             enemy_id: EnemyId::None,
             combat_stats: Default::default(),
+            base_stats: Default::default(),
             name: "Empty enemy".to_string(),
+            portrait: None,
             enter_combat_text: TextType::EnterRat,
             drop_table: DropTable::default(),
+            encounter: 0,
         }
     }
 }
 
+/// Resolves a single round of combat. Whoever hits wears down their weapon, and whoever gets hit
+/// wears down their armour and shield.
 pub fn process_combat(
     events: &mut EventWriter<SimMessageEvent>,
+    wear_events: &mut EventWriter<WearGearEvent>,
+    payload: &MessagePayload,
     mut monster: &mut Combatant,
     mut hero: &mut Combatant,
     cmbt_state: &mut CombatState,
@@ -113,7 +169,12 @@ pub fn process_combat(
         let diff = ((monster_roll - hero_roll) as f32 / 2.0).round() as i32;
         let damage = (monster.damage_bonus - hero.damage_res + diff).clamp(1, 500);
         hero.health -= damage;
-        events.send(SimMessageEvent(TextType::CombatHeroHit));
+        events.send(SimMessageEvent(
+            TextType::CombatHeroHit,
+            payload.clone().with_damage(damage),
+        ));
+        wear_events.send(WearGearEvent(EquipmentSlot::Armour));
+        wear_events.send(WearGearEvent(EquipmentSlot::Shield));
         monster.negative_feedback += 1;
         hero.negative_feedback = 0;
         debug!("Hero hit for {}: HP at {}.", damage, hero.health);
@@ -121,12 +182,19 @@ pub fn process_combat(
         let diff = ((hero_roll - monster_roll) as f32 / 2.0).round() as i32;
         let damage = (hero.damage_bonus + diff - monster.damage_res).clamp(1, 500);
         monster.health -= damage;
-        events.send(SimMessageEvent(TextType::CombatEnemyHit));
+        events.send(SimMessageEvent(
+            TextType::CombatEnemyHit,
+            payload.clone().with_damage(damage),
+        ));
+        wear_events.send(WearGearEvent(EquipmentSlot::Weapon));
         debug!("Monster hit for {}: HP at {}.", damage, monster.health);
         monster.negative_feedback = 0;
         hero.negative_feedback += 1;
     } else {
-        events.send(SimMessageEvent(TextType::CombatNoResolution));
+        events.send(SimMessageEvent(
+            TextType::CombatNoResolution,
+            payload.clone(),
+        ));
     }
 
     if hero.health < 1 {
@@ -4,7 +4,7 @@ use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::game::combat::{DropTable, Enemy};
-use crate::game::feed::MessageColour;
+use crate::game::feed::{FeedCategory, MessageColour};
 
 #[derive(Debug, Clone)]
 pub struct Room {
@@ -121,4 +121,27 @@ impl TextType {
             _ => MessageColour::Neutral,
         }
     }
+
+    pub fn category(&self) -> FeedCategory {
+        match self {
+            TextType::CombatEnemyHit
+            | TextType::CombatHeroHit
+            | TextType::CombatNoResolution
+            | TextType::CombatEnemyDied
+            | TextType::CombatHeroDied
+            | TextType::EnterRat
+            | TextType::EnterGoblinBrat
+            | TextType::EnterGoblinSwordsman
+            | TextType::EnterGoblinShieldBearer
+            | TextType::EnterOrcWarrior
+            | TextType::EnterSkeleton
+            | TextType::EnterZombie
+            | TextType::EnterOgreNecromancer => FeedCategory::Combat,
+            TextType::SearchingRoom
+            | TextType::SearchingBody
+            | TextType::FoundLoot
+            | TextType::FoundNothing => FeedCategory::Loot,
+            _ => FeedCategory::Exploration,
+        }
+    }
 }
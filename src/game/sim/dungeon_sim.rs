@@ -1,20 +1,29 @@
 use std::time::Duration;
 
+use crate::input::Actions;
 use crate::AppState;
 use bevy::prelude::*;
 use iyes_loopless::prelude::NextState;
 use rand::Rng;
 
+use crate::config::config_input::InputAction;
 use crate::config::config_sim::SimConfig;
+use crate::config::data_affixes::AffixesData;
 use crate::config::data_blueprint::BlueprintData;
 use crate::config::data_enemies::EnemiesData;
+use crate::config::data_items::ItemsData;
+use crate::config::data_locale::UiText;
+use crate::game::affixes::roll_item;
 use crate::game::combat::{DropTable, EnemyId};
-use crate::game::event_handling::SimMessageEvent;
+use crate::game::durability::WearGearEvent;
+use crate::game::event_handling::{MessagePayload, SimMessageEvent};
 use crate::game::sim::combat::{process_combat, CombatState, Enemy, Hero};
 use crate::game::sim::dungeon_components::{DungeonLevel, TextType};
 use crate::game::sim::dungeon_gen::generate_level;
 use crate::game::sim::event_handling::SimLootEvent;
-use crate::game::{GameResult, ItemId};
+use crate::game::timed_effect::EffectClockEvent;
+use crate::game::{GameResult, Item, ItemId};
+use crate::locale::Locale;
 
 /// Handle a state event. Mainly handle hero's death?
 pub struct SimStateEvent(String);
@@ -72,21 +81,27 @@ pub fn progress_dungeon_depth(
 
 pub fn tick_dungeon(
     mut msg_events: EventWriter<SimMessageEvent>,
+    mut wear_events: EventWriter<WearGearEvent>,
     mut loot_events: EventWriter<SimLootEvent>,
+    mut clock_events: EventWriter<EffectClockEvent>,
     dungeon_bp: Res<BlueprintData>,
     enemy_data: Res<EnemiesData>,
+    items_data: Res<ItemsData>,
+    affixes: Res<AffixesData>,
     time: Res<Time>,
     _config: ResMut<SimConfig>,
     mut state: ResMut<DungeonState>,
     mut hero: ResMut<Hero>,
     mut enemy: ResMut<Enemy>,
-    input: Res<Input<KeyCode>>,
+    actions: Actions,
     mut cmd: Commands,
     mut victory: ResMut<State<GameResult>>,
 ) {
     let mut just_resumed = false;
     if !state.running {
-        if input.just_pressed(KeyCode::Space) && state.combat_state != CombatState::HeroDead {
+        if actions.just_pressed(InputAction::Continue)
+            && state.combat_state != CombatState::HeroDead
+        {
             state.running = true;
             just_resumed = true;
         } else {
@@ -97,6 +112,7 @@ pub fn tick_dungeon(
         if just_resumed {
             state.msg_cooldown.reset();
         }
+        clock_events.send(EffectClockEvent::Tick);
         let cbt_state = state.combat_state.clone();
         let current_room_idx = state.current_room_idx.clone() as usize;
         let max_depth = (&state.max_depth).clone();
@@ -108,23 +124,31 @@ pub fn tick_dungeon(
                 room.init = false;
                 let new_enemy = level.enemies[current_room_idx].clone();
                 enemy.combat_stats = new_enemy.combat_stats;
+                enemy.base_stats = new_enemy.combat_stats;
                 enemy.enemy_id = new_enemy.enemy_id;
                 enemy.enter_combat_text = new_enemy.enter_combat_text;
                 enemy.drop_table = new_enemy.drop_table;
                 enemy.name = new_enemy.name;
+                enemy.portrait = new_enemy.portrait;
+                enemy.encounter += 1;
                 debug!("New Room: {}", room);
                 debug!("Enemy: id: {}, stats: {}", enemy.name, enemy.combat_stats);
                 hero.combat_stats.negative_feedback = 0;
             }
+            // What the messages in this room are about.
+            let mut payload = MessagePayload::default().at_depth(level.depth + 1);
+            if enemy.enemy_id != EnemyId::None {
+                payload = payload.with_enemy(&enemy.name);
+            }
 
             if room.corridor {
                 room.corridor = false;
-                msg_events.send(SimMessageEvent(TextType::Corridor));
+                msg_events.send(SimMessageEvent(TextType::Corridor, payload.clone()));
                 return;
             }
             if room.door {
                 room.door = false;
-                msg_events.send(SimMessageEvent(TextType::Door));
+                msg_events.send(SimMessageEvent(TextType::Door, payload.clone()));
                 return;
             }
             if room.combat {
@@ -132,15 +156,16 @@ pub fn tick_dungeon(
                     // Monster enounter texts now come from a different source
                     // (each monster has a different one)
                     //.send(SimMessageEvent(TextType::EnemyEncounter));
-                    msg_events.send(SimMessageEvent(enemy.enter_combat_text));
+                    msg_events.send(SimMessageEvent(enemy.enter_combat_text, payload.clone()));
                     state.combat_state = CombatState::InProgress;
                     return;
                 } else if cbt_state == CombatState::EnemyDead {
-                    msg_events.send(SimMessageEvent(TextType::CombatEnemyDied));
+                    msg_events.send(SimMessageEvent(TextType::CombatEnemyDied, payload.clone()));
                     state.combat_state = CombatState::Ended;
+                    clock_events.send(EffectClockEvent::CombatEnded);
                     return;
                 } else if cbt_state == CombatState::HeroDead {
-                    msg_events.send(SimMessageEvent(TextType::CombatHeroDied));
+                    msg_events.send(SimMessageEvent(TextType::CombatHeroDied, payload.clone()));
                     state.combat_state = CombatState::Ended;
                     halt_dungeon_sim(state);
                     // HERO IS DEAD, END GAME
@@ -152,10 +177,13 @@ pub fn tick_dungeon(
                 } else if cbt_state == CombatState::InProgress {
                     process_combat(
                         &mut msg_events,
+                        &mut wear_events,
+                        &payload,
                         &mut enemy.combat_stats,
                         &mut hero.combat_stats,
                         &mut state.combat_state,
                     );
+                    clock_events.send(EffectClockEvent::Round);
                     return;
                 } else if cbt_state == CombatState::Ended {
                     room.combat = false;
@@ -165,18 +193,18 @@ pub fn tick_dungeon(
             if room.description {
                 room.description = false;
                 if let Some(flavour) = room.flavour {
-                    msg_events.send(SimMessageEvent(flavour));
+                    msg_events.send(SimMessageEvent(flavour, payload.clone()));
                 } else {
-                    msg_events.send(SimMessageEvent(TextType::EnteredRoom));
+                    msg_events.send(SimMessageEvent(TextType::EnteredRoom, payload.clone()));
                 }
                 return;
             }
 
             if room.search {
                 if enemy.enemy_id == EnemyId::None {
-                    msg_events.send(SimMessageEvent(TextType::SearchingRoom));
+                    msg_events.send(SimMessageEvent(TextType::SearchingRoom, payload.clone()));
                 } else {
-                    msg_events.send(SimMessageEvent(TextType::SearchingBody));
+                    msg_events.send(SimMessageEvent(TextType::SearchingBody, payload.clone()));
                 }
                 room.search = false;
                 room.post_search = true;
@@ -194,27 +222,39 @@ pub fn tick_dungeon(
                     info!("Loot pool combat: {}", &enemy.drop_table.items.len());
                     pick_loot_from_drop_table(&enemy.drop_table)
                 };
+                // Rolled here rather than when the items are spawned, so that the message names
+                // them by their affixes.
+                let mut rng = rand::thread_rng();
+                let loot: Vec<Item> = loot
+                    .into_iter()
+                    .filter_map(|id| items_data.try_get_item(id))
+                    .map(|(_, item)| roll_item(item, &affixes, &mut rng))
+                    .collect();
                 if loot.len() > 0 {
-                    msg_events.send(SimMessageEvent(TextType::FoundLoot));
-                    for i in loot {
-                        loot_events.send(SimLootEvent(i));
+                    msg_events.send(SimMessageEvent(
+                        TextType::FoundLoot,
+                        payload.clone().with_items(&loot),
+                    ));
+                    for item in loot {
+                        loot_events.send(SimLootEvent(item));
                     }
                 } else {
-                    msg_events.send(SimMessageEvent(TextType::FoundNothing));
+                    msg_events.send(SimMessageEvent(TextType::FoundNothing, payload.clone()));
                 }
             }
 
             if room.start {
                 room.start = false;
-                msg_events.send(SimMessageEvent(TextType::RoomStart));
+                msg_events.send(SimMessageEvent(TextType::RoomStart, payload.clone()));
                 return;
             }
             if room.end {
                 room.end = false;
-                msg_events.send(SimMessageEvent(TextType::RoomEnd));
+                msg_events.send(SimMessageEvent(TextType::RoomEnd, payload.clone()));
                 return;
             }
 
+            clock_events.send(EffectClockEvent::RoomLeft);
             if level.rooms.len() - 1 > current_room_idx as usize {
                 state.current_room_idx += 1;
                 state.combat_state = CombatState::Init;
@@ -266,6 +306,8 @@ fn pick_loot_from_drop_table(table: &DropTable) -> Vec<ItemId> {
 
 pub fn manage_continue_prompt(
     state: Res<DungeonState>,
+    actions: Actions,
+    locale: Res<Locale>,
     mut q: Query<&mut Text, With<ContinuePrompt>>,
 ) {
     if state.running {
@@ -274,7 +316,13 @@ pub fn manage_continue_prompt(
         }
     } else if !state.running && state.combat_state != CombatState::HeroDead {
         if let Ok(mut text) = q.get_single_mut() {
-            text.sections[0].value = "Press SPACE to continue exploring.".to_string();
+            text.sections[0].value = locale.ui_with(
+                UiText::ContinuePrompt,
+                &[(
+                    "key",
+                    actions.key_name(InputAction::Continue).to_uppercase(),
+                )],
+            );
         }
     }
 }
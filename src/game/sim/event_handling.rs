@@ -2,41 +2,70 @@ use bevy::prelude::*;
 use rand::Rng;
 
 use crate::audio::sound_event::SoundEvent;
+use crate::config::data_affixes::AffixesData;
 use crate::config::data_items::ItemsData;
 use crate::config::data_layout::LayoutData;
-use crate::config::data_texts::TextsData;
+use crate::config::data_locale::UiText;
+use crate::game::affixes::{roll_item, Affixes};
 use crate::game::dungeon_components::TextType;
 use crate::game::feed::AddFeedItemEvent;
-use crate::game::{find_free_space, FontId, Item, ItemId, SoundId, SpawnItemEvent};
+use crate::game::occupancy::Occupancy;
+use crate::game::{
+    find_free_space, find_stack_with_room, FontId, Item, ItemId, SoundId, SpawnItemEvent, Stack,
+};
+use crate::locale::{fill_placeholders, placeholders, Locale};
 use crate::positioning::{Coords, GridData};
 
-/// Handle a looting session.
-pub struct SimLootEvent(pub ItemId);
+/// Handle a looting session. The item already has its rarity and affixes rolled, so that the
+/// message about it names the same item that ends up in the bag.
+pub struct SimLootEvent(pub Item);
 
 pub fn handle_sim_loot(
     mut events: EventReader<SimLootEvent>,
     grid: Res<GridData>,
     layout: Res<LayoutData>,
     items_data: Res<ItemsData>,
-    items_query: Query<&Coords, With<Item>>,
+    occupancy: Res<Occupancy>,
+    stacks_query: Query<(Entity, &Item, &Coords, &Stack)>,
     mut spawn: EventWriter<SpawnItemEvent>,
 ) {
     let mut same_tick_items: Vec<Coords> = Vec::new();
-    for SimLootEvent(item_id) in events.iter() {
+    let mut same_tick_stacks: Vec<Entity> = Vec::new();
+    for SimLootEvent(item) in events.iter() {
         trace!("Received sim loot event");
-        if let Some((dimens, item)) = items_data.try_get_item(item_id.clone()) {
+        if let Some((dimens, _)) = items_data.try_get_item(item.id.clone()) {
+            let item = item.clone();
             let source = Vec2::new(layout.screen_dimens.x * 0.5, layout.screen_dimens.y + 1.);
-            let free_coords = find_free_space(&grid, dimens, &items_query, &same_tick_items);
-            if let Some(coords) = free_coords {
+            // Top up an existing stack if possible, before taking up a new slot.
+            if let Some((stack, coords)) =
+                find_stack_with_room(&grid, &item, &stacks_query, &same_tick_stacks)
+            {
+                same_tick_stacks.push(stack);
+                spawn.send(SpawnItemEvent::onto_stack(item, stack, coords, source));
+                continue;
+            }
+            let footprint = item.footprint(dimens);
+            let free_coords =
+                find_free_space(&grid, &occupancy, footprint, item.kind(), &same_tick_items);
+            if let Some((coords, rotated)) = free_coords {
                 if same_tick_items.contains(&coords) {
-                    let new_free_coords =
-                        find_free_space(&grid, dimens, &items_query, &same_tick_items);
-                    if let Some(coords) = new_free_coords {
-                        spawn.send(SpawnItemEvent::new(item, coords, source, false));
+                    let new_free_coords = find_free_space(
+                        &grid,
+                        &occupancy,
+                        footprint,
+                        item.kind(),
+                        &same_tick_items,
+                    );
+                    if let Some((coords, rotated)) = new_free_coords {
+                        spawn.send(
+                            SpawnItemEvent::new(item, coords, source, false).with_rotation(rotated),
+                        );
                     }
                 } else {
                     same_tick_items.push(coords);
-                    spawn.send(SpawnItemEvent::new(item, coords, source, false));
+                    spawn.send(
+                        SpawnItemEvent::new(item, coords, source, false).with_rotation(rotated),
+                    );
                 }
             }
         }
@@ -44,17 +73,120 @@ pub fn handle_sim_loot(
 }
 
 /// Cause a message to be printed and maybe a sound to be played.
-pub struct SimMessageEvent(pub TextType);
+pub struct SimMessageEvent(pub TextType, pub MessagePayload);
+
+/// The placeholders that dungeon texts can use.
+pub const PLACEHOLDERS: [&str; 5] = ["hero", "enemy", "damage", "item", "depth"];
+
+/// What a dungeon message is about. The texts can name it with placeholders, see `PLACEHOLDERS`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MessagePayload {
+    /// Defaults to the hero's name in the current language.
+    pub hero: Option<String>,
+    pub enemy: Option<String>,
+    pub damage: Option<i32>,
+    /// The names of the items that were found, along with their affixes, which are named in the
+    /// current language when the message is shown.
+    pub items: Vec<(String, Affixes)>,
+    /// The level of the dungeon, counting from 1.
+    pub depth: Option<i32>,
+}
+
+impl MessagePayload {
+    pub fn with_enemy(mut self, name: &str) -> Self {
+        self.enemy = Some(name.to_string());
+        self
+    }
+
+    pub fn with_damage(mut self, damage: i32) -> Self {
+        self.damage = Some(damage);
+        self
+    }
+
+    pub fn with_items(mut self, items: &[Item]) -> Self {
+        self.items = items
+            .iter()
+            .map(|item| (item.name.clone(), item.affixes))
+            .collect();
+        self
+    }
+
+    pub fn at_depth(mut self, depth: i32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// The values of the placeholders that the payload has.
+    fn values(&self, locale: &Locale) -> Vec<(&'static str, String)> {
+        let mut values = vec![(
+            "hero",
+            self.hero
+                .clone()
+                .unwrap_or_else(|| locale.ui(UiText::HeroName)),
+        )];
+        if let Some(enemy) = &self.enemy {
+            values.push(("enemy", enemy.clone()));
+        }
+        if let Some(damage) = self.damage {
+            values.push(("damage", damage.to_string()));
+        }
+        if !self.items.is_empty() {
+            let names: Vec<String> = self
+                .items
+                .iter()
+                .map(|(name, affixes)| locale.affixed_name(name, affixes))
+                .collect();
+            values.push(("item", names.join(", ")));
+        }
+        if let Some(depth) = self.depth {
+            values.push(("depth", depth.to_string()));
+        }
+        values
+    }
+}
+
+/// Fills in a dungeon text. Lines whose placeholders can all be filled in are preferred; in the
+/// others, the missing values are replaced by vague words such as "the foe".
+pub fn compose_message(
+    lines: &[String],
+    values: &[(&str, String)],
+    fallbacks: &[(&str, String)],
+) -> Option<String> {
+    let complete: Vec<String> = lines
+        .iter()
+        .filter(|line| {
+            placeholders(line)
+                .iter()
+                .all(|name| values.iter().any(|(known, _)| known == name))
+        })
+        .cloned()
+        .collect();
+    let line = pick_random_from_series(&complete).or_else(|| pick_random_from_series(lines))?;
+    Some(fill_placeholders(
+        &fill_placeholders(&line, values),
+        fallbacks,
+    ))
+}
 
 pub fn handle_sim_message(
     mut reader: EventReader<SimMessageEvent>,
     mut write_texts: EventWriter<AddFeedItemEvent>,
     mut write_audio: EventWriter<SoundEvent>,
-    texts: Res<TextsData>,
+    locale: Res<Locale>,
 ) {
-    for SimMessageEvent(text_type) in reader.iter() {
+    let fallbacks = [
+        ("enemy", locale.ui(UiText::FallbackEnemy)),
+        ("damage", locale.ui(UiText::FallbackDamage)),
+        ("item", locale.ui(UiText::FallbackItem)),
+        ("depth", locale.ui(UiText::FallbackDepth)),
+    ];
+    for SimMessageEvent(text_type, payload) in reader.iter() {
         trace!("Received sim message event for TextType::{:?}", text_type);
-        let random = pick_random_from_series(texts.map.get(&text_type).unwrap_or(&Vec::new()));
+        let random = compose_message(
+            locale.texts(*text_type),
+            &payload.values(&locale),
+            &fallbacks,
+        );
         let colour = text_type.colour_hint();
         let font = if colour.is_major() {
             FontId::FiraSansBold
@@ -68,6 +200,7 @@ pub fn handle_sim_message(
                 message,
                 colour,
                 font,
+                category: text_type.category(),
             });
         } else {
             error!("Missing or empty dungeon text: TextType::{:?}", text_type);
@@ -93,7 +226,7 @@ pub fn handle_sim_message(
     }
 }
 
-fn pick_random_from_series(strings: &Vec<String>) -> Option<String> {
+fn pick_random_from_series(strings: &[String]) -> Option<String> {
     if strings.is_empty() {
         None
     } else {
@@ -102,3 +235,39 @@ fn pick_random_from_series(strings: &Vec<String>) -> Option<String> {
         strings.get(idx).cloned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::data_locale::LocaleData;
+
+    #[test]
+    fn prefers_lines_that_can_be_filled_in() {
+        let lines = vec![
+            "The {enemy} hits {hero} for {damage} damage!".to_string(),
+            "{hero} gets hit!".to_string(),
+        ];
+        let values = [("hero", "Sir Hoardalot".to_string())];
+        let fallbacks = [("enemy", "foe".to_string()), ("damage", "some".to_string())];
+        assert_eq!(
+            compose_message(&lines, &values, &fallbacks),
+            Some("Sir Hoardalot gets hit!".to_string())
+        );
+        assert_eq!(
+            compose_message(&lines[..1], &values, &fallbacks),
+            Some("The foe hits Sir Hoardalot for some damage!".to_string())
+        );
+        assert_eq!(compose_message(&[], &values, &fallbacks), None);
+    }
+
+    #[test]
+    fn english_texts_only_use_known_placeholders() {
+        let text = std::fs::read_to_string("assets/locale/en/texts.strings.ron").unwrap();
+        let data: LocaleData = ron::from_str(&text).unwrap();
+        for line in data.texts.values().flatten() {
+            for name in placeholders(line) {
+                assert!(PLACEHOLDERS.contains(&name), "{{{}}} in: {}", name, line);
+            }
+        }
+    }
+}
@@ -1,18 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 use bevy::text::{Text2dBounds, Text2dSize};
+use bevy_egui::{egui, EguiContext};
 
+use crate::config::config_input::InputAction;
 use crate::config::data_layout::LayoutData;
-use crate::game::{AssetStorage, CleanupOnGameplayEnd, FontId};
+use crate::config::data_locale::UiText;
+use crate::game::{AssetStorage, FontId};
+use crate::input::Actions;
+use crate::locale::Locale;
+use crate::mouse::MouseInteractive;
+
+/// The size of the feed's text, before it is scaled down to the layout.
+const FONT_SIZE: f32 = 60.0;
+/// How far a wheel that scrolls by pixels, such as a touchpad, has to move to scroll one line.
+const PIXELS_PER_LINE: f32 = 20.0;
 
 /// Cause a message to be printed.
 pub struct AddFeedItemEvent {
     pub message: String,
     pub font: FontId,
     pub colour: MessageColour,
+    pub category: FeedCategory,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MessageColour {
     Neutral,
     MinorPositive,
@@ -31,6 +45,10 @@ impl MessageColour {
             MessageColour::MajorNegative => Color::rgba(1., 0.5, 0.5, 0.8),
         }
     }
+    fn egui_colour(&self) -> egui::Color32 {
+        let [r, g, b, a] = self.rgba().as_rgba_f32();
+        egui::Rgba::from_rgba_unmultiplied(r, g, b, a).into()
+    }
     pub fn is_major(&self) -> bool {
         matches!(
             self,
@@ -45,69 +63,123 @@ impl MessageColour {
     }
 }
 
+/// What a message in the feed is about, so that the feed can be filtered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FeedCategory {
+    /// Encounters, blows and deaths.
+    Combat,
+    /// Searching rooms and finding items.
+    Loot,
+    /// Walking through the dungeon.
+    Exploration,
+    /// What the player does with the bag: crafting, equipping, discarding.
+    Inventory,
+}
+
+impl FeedCategory {
+    pub const ALL: [FeedCategory; 4] = [
+        FeedCategory::Combat,
+        FeedCategory::Loot,
+        FeedCategory::Exploration,
+        FeedCategory::Inventory,
+    ];
+
+    pub fn ui_text(&self) -> UiText {
+        match self {
+            FeedCategory::Combat => UiText::FeedCombat,
+            FeedCategory::Loot => UiText::FeedLoot,
+            FeedCategory::Exploration => UiText::FeedExploration,
+            FeedCategory::Inventory => UiText::FeedInventory,
+        }
+    }
+}
+
+/// A message that was printed in the feed.
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    pub message: String,
+    pub font: FontId,
+    pub colour: MessageColour,
+    pub category: FeedCategory,
+}
+
 /// The event feed container.
 #[derive(Component)]
 pub struct EventFeedContainer;
 
-/// An event feed item
+/// One of the text lines of the feed. The lines are spawned once, and show whichever messages
+/// are in view, counting up from the bottom.
 #[derive(Component)]
 pub struct EventFeedItem {
-    pub id: i32,
+    pub line: usize,
+    /// Whether a message is shown on this line.
+    pub used: bool,
 }
 
 #[derive(Component)]
 pub struct EventFeedItemBg {
-    pub id: i32,
+    pub line: usize,
 }
 
-/// Resource.
+/// Resource. Every message of the current run, and how the feed shows them.
+#[derive(Default)]
 pub struct EventFeed {
-    /// The next id that should be given out.
-    pub next_id: i32,
+    /// Oldest first.
+    pub history: Vec<FeedEntry>,
+    /// How many of the shown messages are below the bottom of the feed, from scrolling back.
+    pub scroll: usize,
+    /// The categories that are left out of the feed.
+    pub hidden: HashSet<FeedCategory>,
+    /// Only messages that contain this are shown, ignoring case.
+    pub search: String,
+    /// Whether the feed history window is open.
+    pub window_open: bool,
 }
 
 impl EventFeed {
-    pub fn next_id(&mut self) -> i32 {
-        let id = self.next_id;
-        self.next_id += 1;
-        id
+    pub fn shows(&self, entry: &FeedEntry) -> bool {
+        !self.hidden.contains(&entry.category)
+            && (self.search.is_empty()
+                || entry
+                    .message
+                    .to_lowercase()
+                    .contains(&self.search.to_lowercase()))
+    }
+
+    /// The indices in the history of the messages that pass the filters, oldest first.
+    pub fn shown(&self) -> Vec<usize> {
+        (0..self.history.len())
+            .filter(|index| self.shows(&self.history[*index]))
+            .collect()
     }
 }
 
-pub fn handle_add_to_feed(
-    mut commands: Commands,
-    assets: Res<AssetStorage>,
-    mut events: EventReader<AddFeedItemEvent>,
-    mut feed: ResMut<EventFeed>,
-    layout: Res<LayoutData>,
-    query_container: Query<Entity, With<EventFeedContainer>>,
+/// How many lines the feed needs to be filled with one-line messages.
+pub fn feed_line_count(layout: &LayoutData) -> usize {
+    let line_height = FONT_SIZE * 0.9 / layout.text_factor;
+    (layout.c_left.feed_height(layout) / line_height).ceil() as usize
+}
+
+/// Spawns the lines of the feed, which are reused for whichever messages are in view.
+pub fn spawn_feed_lines(
+    commands: &mut Commands,
+    layout: &LayoutData,
+    container: Entity,
+    container_width: f32,
+    container_height: f32,
 ) {
-    for AddFeedItemEvent {
-        message,
-        font,
-        colour,
-    } in events.iter()
-    {
-        let text_style = TextStyle {
-            font: assets.font(font),
-            font_size: 60.0,
-            color: colour.rgba(),
-        };
-        let text_alignment = TextAlignment {
-            vertical: VerticalAlign::Center,
-            horizontal: HorizontalAlign::Left,
-        };
-        let container_width = layout.left_width();
-        let container_height = layout.c_left.feed_height(&layout);
-        let dimens_text = Vec2::new(
-            container_width - 2. * layout.c_left.feed_padding,
-            layout.c_left.feed_item_max_height,
-        );
-        let item_id = feed.next_id();
+    let dimens_text = Vec2::new(
+        container_width - 2. * layout.c_left.feed_padding,
+        layout.c_left.feed_item_max_height,
+    );
+    let text_alignment = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Left,
+    };
+    for line in 0..feed_line_count(layout) {
         let text = commands
-            .spawn()
-            .insert_bundle(Text2dBundle {
-                text: Text::from_section(message, text_style).with_alignment(text_alignment),
+            .spawn_bundle(Text2dBundle {
+                text: Text::from_section("", TextStyle::default()).with_alignment(text_alignment),
                 // The max size that it should fit in:
                 text_2d_bounds: Text2dBounds {
                     size: Vec2::new(
@@ -125,21 +197,15 @@ pub fn handle_add_to_feed(
                     0.9 / layout.text_factor,
                     1.,
                 )),
+                visibility: Visibility { is_visible: false },
                 ..default()
             })
-            .insert(EventFeedItem { id: item_id })
-            .insert(CleanupOnGameplayEnd)
+            .insert(EventFeedItem { line, used: false })
             .id();
-        let bg_color = if item_id.rem_euclid(2) == 0 {
-            Color::rgba(0.1, 0.1, 0.1, 1.)
-        } else {
-            Color::rgba(0.15, 0.15, 0.15, 1.)
-        };
         let text_background = commands
             .spawn_bundle(SpriteBundle {
                 sprite: Sprite {
-                    color: bg_color,
-                    custom_size: Some(Vec2::new(dimens_text.x, dimens_text.y)),
+                    custom_size: Some(dimens_text),
                     ..default()
                 },
                 transform: Transform::from_xyz(
@@ -147,74 +213,289 @@ pub fn handle_add_to_feed(
                     container_height * -0.5 + layout.c_left.feed_padding,
                     10., // Relative to the container.
                 ),
+                visibility: Visibility { is_visible: false },
                 ..default()
             })
-            .insert(EventFeedItemBg { id: item_id })
+            .insert(EventFeedItemBg { line })
             .id();
         commands
-            .entity(query_container.single())
+            .entity(container)
             .push_children(&[text, text_background]);
     }
 }
 
+/// Adds the messages to the history. While the player is scrolled back, the feed stays on the
+/// messages they are reading.
+pub fn handle_add_to_feed(mut events: EventReader<AddFeedItemEvent>, mut feed: ResMut<EventFeed>) {
+    for AddFeedItemEvent {
+        message,
+        font,
+        colour,
+        category,
+    } in events.iter()
+    {
+        let entry = FeedEntry {
+            message: message.clone(),
+            font: *font,
+            colour: *colour,
+            category: *category,
+        };
+        if feed.scroll > 0 && feed.shows(&entry) {
+            feed.scroll += 1;
+        }
+        feed.history.push(entry);
+    }
+}
+
+/// Scrolls the feed back and forth with the mouse wheel while the mouse is over it.
+pub fn scroll_feed(
+    mut wheel: EventReader<MouseWheel>,
+    mut feed: ResMut<EventFeed>,
+    mut pixels: Local<f32>,
+    query_container: Query<&MouseInteractive, With<EventFeedContainer>>,
+) {
+    let hovered = query_container
+        .get_single()
+        .map(|interactive| interactive.hovered)
+        .unwrap_or(false);
+    let mut lines = 0.;
+    for event in wheel.iter() {
+        match event.unit {
+            MouseScrollUnit::Line => lines += event.y,
+            MouseScrollUnit::Pixel => *pixels += event.y,
+        }
+    }
+    lines += (*pixels / PIXELS_PER_LINE).trunc();
+    *pixels %= PIXELS_PER_LINE;
+    if !hovered || lines == 0. {
+        return;
+    }
+    // Scrolling up goes back to older messages.
+    let max_scroll = feed.shown().len().saturating_sub(1);
+    let scroll = (feed.scroll as f32 + lines).clamp(0., max_scroll as f32) as usize;
+    if scroll != feed.scroll {
+        feed.scroll = scroll;
+    }
+}
+
+/// Shows the messages that are in view on the lines of the feed.
+pub fn update_feed_lines(
+    assets: Res<AssetStorage>,
+    feed: Res<EventFeed>,
+    mut query_text: Query<(&mut EventFeedItem, &mut Text)>,
+    mut query_bg: Query<(&EventFeedItemBg, &mut Sprite)>,
+) {
+    if !feed.is_changed() {
+        return;
+    }
+    let shown = feed.shown();
+    let index_on_line = |line: usize| {
+        shown
+            .len()
+            .checked_sub(1 + feed.scroll + line)
+            .map(|position| shown[position])
+    };
+    for (mut item, mut text) in query_text.iter_mut() {
+        let section = &mut text.sections[0];
+        match index_on_line(item.line) {
+            Some(index) => {
+                let entry = &feed.history[index];
+                section.value = entry.message.clone();
+                section.style = TextStyle {
+                    font: assets.font(&entry.font),
+                    font_size: FONT_SIZE,
+                    color: entry.colour.rgba(),
+                };
+                item.used = true;
+            }
+            None => {
+                section.value.clear();
+                item.used = false;
+            }
+        }
+    }
+    for (item, mut sprite) in query_bg.iter_mut() {
+        // The stripes follow the messages, so that they don't flicker while scrolling.
+        if let Some(index) = index_on_line(item.line) {
+            sprite.color = if index.rem_euclid(2) == 0 {
+                Color::rgba(0.1, 0.1, 0.1, 1.)
+            } else {
+                Color::rgba(0.15, 0.15, 0.15, 1.)
+            };
+        }
+    }
+}
+
+/// Stacks the lines of the feed from the bottom up, and hides those that don't fit.
 pub fn position_feed_item(
-    mut commands: Commands,
     layout: Res<LayoutData>,
     mut queries: ParamSet<(
-        Query<(Entity, &EventFeedItem, &mut Transform, &Text2dSize)>,
-        Query<(Entity, &EventFeedItemBg, &mut Transform, &mut Sprite)>,
+        Query<(&EventFeedItem, &mut Transform, &mut Visibility, &Text2dSize)>,
+        Query<(
+            &EventFeedItemBg,
+            &mut Transform,
+            &mut Visibility,
+            &mut Sprite,
+        )>,
     )>,
 ) {
-    let mut map: HashMap<i32, (f32, f32)> = HashMap::new();
-    let highest_id = queries
-        .p0()
-        .iter()
-        .fold(i32::MIN, |highest_id, (_, item, _, size)| {
-            map.insert(item.id, (size.size.y, 0.));
-            highest_id.max(item.id)
-        });
-    let mut running_total = 0.;
-    for i in 0.. {
-        let index = highest_id - i;
-        let temp = map.get(&index);
-        if temp.is_some() {
-            running_total += temp.unwrap().0;
-            map.insert(index, (temp.unwrap().0, running_total));
-        } else {
-            break;
+    let mut heights: Vec<Option<f32>> = vec![];
+    for (item, _, _, size) in queries.p0().iter() {
+        if heights.len() <= item.line {
+            heights.resize(item.line + 1, None);
+        }
+        if item.used {
+            heights[item.line] = Some(size.size.y);
         }
     }
     let container_width = layout.left_width();
     let container_height = layout.c_left.feed_height(&layout);
     let available_height = container_height - layout.c_left.feed_padding * 2.;
-    // Set the text translation and delete those that are out of bounds.
-    for (entity, item, mut transform, size) in queries.p0().iter_mut() {
-        let (height, total_height) = map.get(&item.id).unwrap();
-        if *total_height / layout.text_factor > available_height {
-            trace!("Deleting text with id={:?} and size={:?}", item.id, size);
-            commands.entity(entity).despawn_recursive();
-        } else {
+    // The height of each line, and of the lines below it along with it.
+    let mut map: HashMap<usize, (f32, f32)> = HashMap::new();
+    let mut running_total = 0.;
+    for (line, height) in heights.iter().enumerate() {
+        match height {
+            Some(height) => {
+                running_total += height;
+                if running_total / layout.text_factor > available_height {
+                    break;
+                }
+                map.insert(line, (*height, running_total));
+            }
+            None => break,
+        }
+    }
+    for (item, mut transform, mut visibility, _) in queries.p0().iter_mut() {
+        visibility.is_visible = map.contains_key(&item.line);
+        if let Some((height, total_height)) = map.get(&item.line) {
             transform.translation.x = container_width * -0.5 + layout.c_left.feed_padding * 2.;
             transform.translation.y = container_height * -0.5
                 + layout.c_left.feed_padding
                 + (total_height - height * 0.5) / layout.text_factor;
         }
     }
-    // Set the background translation and delete those that are out of bounds.
-    for (entity, item, mut transform, mut sprite) in queries.p1().iter_mut() {
-        let (height, total_height) = map.get(&item.id).unwrap();
-        if *total_height / layout.text_factor > available_height {
-            trace!("Deleting bg with id={:?}", item.id);
-            commands.entity(entity).despawn_recursive();
-        } else {
+    for (item, mut transform, mut visibility, mut sprite) in queries.p1().iter_mut() {
+        visibility.is_visible = map.contains_key(&item.line);
+        if let Some((height, total_height)) = map.get(&item.line) {
             transform.translation.x = 0.;
             transform.translation.y = container_height * -0.5
                 + (total_height - height * 0.5) / layout.text_factor
                 + layout.c_left.feed_padding;
             sprite.custom_size = Some(Vec2::new(
                 container_width - layout.c_left.feed_padding * 2.,
-                map.get(&item.id).unwrap().0 / layout.text_factor,
+                height / layout.text_factor,
             ));
         }
     }
 }
+
+pub fn toggle_feed_history(actions: Actions, mut feed: ResMut<EventFeed>) {
+    if actions.just_pressed(InputAction::FeedHistory) {
+        feed.window_open = !feed.window_open;
+    }
+}
+
+/// Draws the window with every message of the run, which can be filtered by category and
+/// searched. The filters apply to the feed as well. Only the rows in view are laid out.
+pub fn draw_feed_history(
+    mut egui_context: ResMut<EguiContext>,
+    mut feed: ResMut<EventFeed>,
+    locale: Res<Locale>,
+) {
+    if !feed.window_open {
+        return;
+    }
+    let mut open = true;
+    let mut hidden = feed.hidden.clone();
+    let mut search = feed.search.clone();
+    let shown = feed.shown();
+    egui::Window::new(locale.ui(UiText::FeedHistory))
+        .open(&mut open)
+        .default_size([480., 360.])
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for category in FeedCategory::ALL {
+                    let mut checked = !hidden.contains(&category);
+                    if ui
+                        .checkbox(&mut checked, locale.ui(category.ui_text()))
+                        .changed()
+                    {
+                        if checked {
+                            hidden.remove(&category);
+                        } else {
+                            hidden.insert(category);
+                        }
+                    }
+                }
+            });
+            ui.add(
+                egui::TextEdit::singleline(&mut search).hint_text(locale.ui(UiText::FeedSearch)),
+            );
+            ui.separator();
+            if shown.is_empty() {
+                ui.label(locale.ui(UiText::FeedNoMatches));
+                return;
+            }
+            let row_height = ui.text_style_height(&egui::TextStyle::Body);
+            egui::ScrollArea::both()
+                .auto_shrink([false, false])
+                .stick_to_bottom()
+                .show_rows(ui, row_height, shown.len(), |ui, rows| {
+                    for index in &shown[rows] {
+                        let entry = &feed.history[*index];
+                        ui.add(
+                            egui::Label::new(
+                                egui::RichText::new(&entry.message)
+                                    .color(entry.colour.egui_colour()),
+                            )
+                            .wrap(false),
+                        );
+                    }
+                });
+        });
+    // Changing the filters would otherwise leave the feed scrolled to an unrelated message.
+    if hidden != feed.hidden || search != feed.search {
+        feed.hidden = hidden;
+        feed.search = search;
+        feed.scroll = 0;
+    }
+    if !open {
+        feed.window_open = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str, category: FeedCategory) -> FeedEntry {
+        FeedEntry {
+            message: message.to_string(),
+            font: FontId::FiraSansRegular,
+            colour: MessageColour::Neutral,
+            category,
+        }
+    }
+
+    #[test]
+    fn filters_by_category_and_search() {
+        let mut feed = EventFeed {
+            history: vec![
+                entry("The Rat bites you.", FeedCategory::Combat),
+                entry("You find a Red Herb.", FeedCategory::Loot),
+                entry("You enter a corridor.", FeedCategory::Exploration),
+                entry("The Goblin Brat stabs you.", FeedCategory::Combat),
+            ],
+            ..default()
+        };
+        assert_eq!(feed.shown(), vec![0, 1, 2, 3]);
+        feed.hidden.insert(FeedCategory::Combat);
+        assert_eq!(feed.shown(), vec![1, 2]);
+        feed.hidden.clear();
+        feed.search = "you.".to_string();
+        assert_eq!(feed.shown(), vec![0, 3]);
+        feed.search = "RAT".to_string();
+        assert_eq!(feed.shown(), vec![0, 3]);
+    }
+}
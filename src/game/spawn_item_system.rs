@@ -1,10 +1,13 @@
 use bevy::prelude::*;
 
-use crate::game::items::Item;
-use crate::game::{AssetStorage, CleanupOnGameplayEnd, FallingItem, Silhouette};
+use crate::config::data_layout::LayoutData;
+use crate::game::affixes::spawn_rarity_border;
+use crate::game::items::{item_rotation, CraftItem, Item, ItemKind, Rotated, Stack, StackBadge};
+use crate::game::occupancy::Occupancy;
+use crate::game::{AssetStorage, CleanupOnGameplayEnd, FallingItem, FontId, Silhouette};
 use crate::mouse::MouseInteractive;
 use crate::positioning::{Coords, GridData};
-use crate::positioning::{Depth, Dimens, Pos};
+use crate::positioning::{Depth, Pos};
 
 /// Broadcast this as an event to spawn an item.
 #[derive(Debug)]
@@ -17,6 +20,14 @@ pub struct SpawnItemEvent {
     /// in the inventory without any animations.
     source: Option<Vec2>,
     combine: bool,
+    /// How many copies of the item to spawn on a single stack.
+    count: i32,
+    /// If set, the item is added to this existing stack instead of taking up a new slot.
+    /// The Coords are those of the existing stack.
+    stack_onto: Option<Entity>,
+    /// Whether the item is given a quarter turn. If so, the dimens of the Coords must already
+    /// be swapped.
+    rotated: bool,
 }
 
 impl SpawnItemEvent {
@@ -26,6 +37,22 @@ impl SpawnItemEvent {
             coords,
             source: Some(source),
             combine,
+            count: 1,
+            stack_onto: None,
+            rotated: false,
+        }
+    }
+    /// Use this to add the item to a stack that is already in the inventory, which must have
+    /// room for it.
+    pub fn onto_stack(item: Item, stack: Entity, coords: Coords, source: Vec2) -> Self {
+        SpawnItemEvent {
+            item,
+            coords,
+            source: Some(source),
+            combine: false,
+            count: 1,
+            stack_onto: Some(stack),
+            rotated: false,
         }
     }
     /// Use this for items that already exist in the backpack at the start of the game.
@@ -35,8 +62,21 @@ impl SpawnItemEvent {
             coords,
             source: None,
             combine: false,
+            count: 1,
+            stack_onto: None,
+            rotated: false,
         }
     }
+    /// Spawns a stack of several copies of the item instead of a single one.
+    pub fn with_count(mut self, count: i32) -> Self {
+        self.count = count;
+        self
+    }
+    /// Spawns the item with a quarter turn, see the Rotated component.
+    pub fn with_rotation(mut self, rotated: bool) -> Self {
+        self.rotated = rotated;
+        self
+    }
 }
 
 pub fn spawn_item(
@@ -44,25 +84,48 @@ pub fn spawn_item(
     mut events: EventReader<SpawnItemEvent>,
     assets: Res<AssetStorage>,
     grid: Res<GridData>,
+    mut stacks: Query<&mut Stack>,
 ) {
     for SpawnItemEvent {
         item,
         coords,
         source,
         combine,
+        count,
+        stack_onto,
+        rotated,
     } in events.iter()
     {
         trace!("Received SpawnItemEvent( {:?}, {:?} )", item, coords);
+        // Irregularly shaped items only take up some of the cells in their rectangle.
+        let coords = &if *rotated {
+            Coords {
+                pos: coords.pos,
+                ..item.footprint(coords.dimens.swapped()).turned(true)
+            }
+        } else {
+            Coords {
+                pos: coords.pos,
+                ..item.footprint(coords.dimens)
+            }
+        };
+        // The sprite is drawn at the item's original size, and then turned.
+        let sprite_size = if *rotated {
+            coords.dimens.swapped().as_vec2()
+        } else {
+            coords.dimens.as_vec2()
+        };
         if let Some(source) = source {
             // Spawn the animating item.
             commands
                 .spawn_bundle(SpriteBundle {
                     sprite: Sprite {
-                        custom_size: Some(coords.dimens.as_vec2()),
+                        custom_size: Some(sprite_size),
                         ..default()
                     },
                     texture: assets.texture(&item.texture_id),
-                    transform: Transform::from_xyz(source.x, source.y, Depth::FloatingItem.z()),
+                    transform: Transform::from_xyz(source.x, source.y, Depth::FloatingItem.z())
+                        .with_rotation(item_rotation(*rotated)),
                     ..Default::default()
                 })
                 .insert(Name::new("FallingItem"))
@@ -74,12 +137,20 @@ pub fn spawn_item(
                 ))
                 .insert(CleanupOnGameplayEnd);
         }
+        if let Some(stack_entity) = stack_onto {
+            // The stack is topped up straight away, so that it can't be overfilled while the
+            // item is still falling.
+            if let Ok(mut stack) = stacks.get_mut(*stack_entity) {
+                stack.0 += count;
+            }
+            continue;
+        }
         // Spawn the silhouette.
         let mut builder = commands.spawn();
         builder
             .insert_bundle(SpriteBundle {
                 sprite: Sprite {
-                    custom_size: Some(coords.dimens.as_vec2()),
+                    custom_size: Some(sprite_size),
                     ..default()
                 },
                 texture: assets.texture(&item.texture_id),
@@ -87,17 +158,85 @@ pub fn spawn_item(
                     grid.offset.x + coords.pos.x as f32 + coords.dimens.x as f32 * 0.5,
                     grid.offset.y + coords.pos.y as f32 + coords.dimens.y as f32 * 0.5,
                     Depth::Item.z(),
-                ),
+                )
+                .with_rotation(item_rotation(*rotated)),
                 ..Default::default()
             })
             .insert(Name::new(item.name.clone()))
             .insert(item.clone())
             .insert(*coords)
+            .insert(Stack(*count))
             .insert(MouseInteractive::new(coords.dimens.as_vec2(), true))
             .insert(CleanupOnGameplayEnd);
         if source.is_some() {
             builder.insert(Silhouette);
         }
+        if grid.crafting.encloses(coords) {
+            builder.insert(CraftItem);
+        }
+        if *rotated {
+            builder.insert(Rotated);
+        }
+        if let Some(colour) = item.rarity.colour() {
+            builder.with_children(|parent| spawn_rarity_border(parent, sprite_size, colour));
+        }
+    }
+}
+
+/// Shows the number of items in a stack in the bottom-right corner of its sprite.
+/// Single items don't get a badge.
+pub fn update_stack_badges(
+    mut commands: Commands,
+    assets: Res<AssetStorage>,
+    layout: Res<LayoutData>,
+    query_stacks: Query<
+        (Entity, &Stack, &Coords, &Transform, Option<&Children>),
+        Or<(Changed<Stack>, Changed<Coords>)>,
+    >,
+    query_badges: Query<Entity, With<StackBadge>>,
+) {
+    for (entity, stack, coords, transform, children) in query_stacks.iter() {
+        for child in children.iter().flat_map(|children| children.iter()) {
+            if query_badges.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        if stack.0 <= 1 {
+            continue;
+        }
+        // The badge stays upright and in the bottom-right corner, even if the item is turned.
+        let unrotate = transform.rotation.inverse();
+        let offset = Vec3::new(
+            coords.dimens.x as f32 * 0.5 - 0.05,
+            coords.dimens.y as f32 * -0.5,
+            1.,
+        );
+        commands.entity(entity).with_children(|parent| {
+            parent
+                .spawn_bundle(Text2dBundle {
+                    text: Text::from_section(
+                        stack.0.to_string(),
+                        TextStyle {
+                            font: assets.font(&FontId::FiraSansBold),
+                            font_size: 60.0,
+                            color: Color::ANTIQUE_WHITE,
+                        },
+                    )
+                    .with_alignment(TextAlignment {
+                        horizontal: HorizontalAlign::Right,
+                        vertical: VerticalAlign::Bottom,
+                    }),
+                    transform: Transform::from_translation(unrotate * offset)
+                        .with_rotation(unrotate)
+                        .with_scale(Vec3::new(
+                            1. / layout.text_factor,
+                            1. / layout.text_factor,
+                            1.,
+                        )),
+                    ..default()
+                })
+                .insert(StackBadge);
+        });
     }
 }
 
@@ -130,24 +269,72 @@ pub fn animate_falling_item(
     }
 }
 
+/// Finds a stack of the same item in the bag that still has room for one more.
+/// Loot and crafting results should top up existing stacks before taking up new slots.
+/// Stacks that will already be topped up this tick should be passed in `same_tick_stacks`, once
+/// for every item that is added to them.
+pub fn find_stack_with_room(
+    grid: &GridData,
+    item: &Item,
+    stacks_query: &Query<(Entity, &Item, &Coords, &Stack)>,
+    same_tick_stacks: &[Entity],
+) -> Option<(Entity, Coords)> {
+    stacks_query
+        .iter()
+        .find(|(entity, stack_item, coords, stack)| {
+            let pending = same_tick_stacks.iter().filter(|e| *e == entity).count() as i32;
+            stack_item.id == item.id && grid.in_storage(coords) && stack.room(stack_item) > pending
+        })
+        .map(|(entity, _, coords, _)| (entity, *coords))
+}
+
+/// Finds a free spot in the bag for an item of the given kind with the given footprint, see
+/// `Item::footprint`. Pockets that are meant for the item are tried first, then the inventory, then
+/// any other pockets. If the item doesn't fit as it is, it is given a quarter turn.
+/// Returns where the item fits, and whether it had to be turned.
 pub fn find_free_space(
     grid: &GridData,
-    dimens: Dimens,
-    items_query: &Query<&Coords, With<Item>>, // is there any way to call this function without this query? it forces you to have the exact same query in whichever query you're calling this function from. - Jacques
-    same_tick_items: &[Coords],               // Pass this an emtpy vec if not multiple spawn
-) -> Option<Coords> {
-    for y in 0..grid.inventory.dimens.y {
-        for x in 0..grid.inventory.dimens.x {
-            let coords = Coords {
-                pos: Pos::new(x, y),
-                dimens,
-            };
+    occupancy: &Occupancy,
+    footprint: Coords,
+    kind: ItemKind,
+    same_tick_items: &[Coords], // Pass this an emtpy vec if not multiple spawn
+) -> Option<(Coords, bool)> {
+    find_free_space_in(
+        &grid.storage_for(kind),
+        occupancy,
+        footprint,
+        same_tick_items,
+    )
+}
+
+/// Like `find_free_space`, but looks in the given areas, in order, rather than in the bag.
+pub fn find_free_space_in(
+    areas: &[Coords],
+    occupancy: &Occupancy,
+    footprint: Coords,
+    same_tick_items: &[Coords],
+) -> Option<(Coords, bool)> {
+    let mut orientations = vec![(footprint, false)];
+    let turned = footprint.turned(true);
+    if turned != footprint {
+        orientations.push((turned, true));
+    }
+    for area in areas {
+        for (footprint, rotated) in &orientations {
+            for y in area.pos.y..area.pos.y + area.dimens.y {
+                for x in area.pos.x..area.pos.x + area.dimens.x {
+                    let coords = Coords {
+                        pos: Pos::new(x, y),
+                        ..*footprint
+                    };
 
-            let overlap_conflict = items_query.iter().any(|item| coords.overlaps(item))
-                || same_tick_items.iter().any(|item| coords.overlaps(item));
-            let bound_conflict = !grid.inventory.encloses(&coords);
-            if !overlap_conflict && !bound_conflict {
-                return Some(coords);
+                    let overlap_conflict = !occupancy.is_free(&coords, &[])
+                        || same_tick_items.iter().any(|item| coords.overlaps(item));
+                    let bound_conflict = !area.encloses(&coords);
+                    if !overlap_conflict && !bound_conflict {
+                        return Some((coords, *rotated));
+                    }
+                }
             }
         }
     }
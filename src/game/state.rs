@@ -1,23 +1,49 @@
-use std::time::Duration;
-
 use bevy::prelude::*;
 use bevy_ninepatch::{NinePatchBuilder, NinePatchBundle};
 use iyes_loopless::prelude::*;
 
 use crate::audio::record_player::animate;
 use crate::audio::sound_event::SoundEvent;
+use crate::game::auto_sort::auto_sort_inventory;
+use crate::game::bag_upgrades::{draw_bag_grids, use_bag_upgrades};
+use crate::game::context_menu::{open_context_menu, use_context_menu};
+use crate::game::crafting_preview::update_crafting_preview;
 use crate::game::dungeon_sim::{init_dungeon, manage_continue_prompt, tick_dungeon};
+use crate::game::durability::{wear_gear, WearGearEvent};
+use crate::game::encounter::{
+    slide_encounter_panel, update_encounter_panel, use_items_on_enemy, UseOnEnemyEvent,
+};
+use crate::game::equipment::{equip_items, update_equipment_slots, EquipEvent};
 use crate::game::event_handling::{
     handle_sim_loot, handle_sim_message, SimLootEvent, SimMessageEvent,
 };
-use crate::game::feed::{handle_add_to_feed, position_feed_item, AddFeedItemEvent};
+use crate::game::feed::{
+    draw_feed_history, handle_add_to_feed, position_feed_item, scroll_feed, toggle_feed_history,
+    update_feed_lines, AddFeedItemEvent, EventFeed,
+};
+use crate::game::grid_cursor::{
+    draw_grid_cursor, grid_cursor_actions, move_grid_cursor, GridCursor,
+};
+use crate::game::hero_stats::{show_stat_breakdown, update_hero_stats, HeroStatSources};
 use crate::game::item_info_system::*;
-use crate::game::timed_effect::{test_apply_modifier, tick_temporary_modifiers, TimedEffectTicker};
+use crate::game::occupancy::{remove_despawned_items, update_occupancy, Occupancy};
+use crate::game::recipe_book::{
+    draw_recipe_book, learn_recipes, toggle_recipe_book, LearnRecipeEvent, RecipeBook,
+};
+use crate::game::status_tray::{show_status_tooltip, tick_status_icons, update_status_trays};
+use crate::game::timed_effect::{
+    apply_effects, test_apply_modifier, tick_temporary_modifiers, update_enemy_stats,
+    ApplyEffectEvent, EffectClockEvent,
+};
+use crate::game::trash::{
+    discard_items, empty_trash, undo_discard, update_trash_zone, DiscardEvent, Trash,
+};
 use crate::game::{
     animate_falling_item, apply_silhouette, check_drag_begin, check_drag_end,
-    check_ghost_placement_validity, combine_items_system, process_drag_event, set_ghost_position,
-    spawn_item, AlbumId, AssetStorage, CleanupOnGameplayEnd, DragEvent, ItemId, Player,
-    SpawnItemEvent, TextureId,
+    check_equipment_drag_begin, check_ghost_placement_validity, combine_items_system,
+    process_drag_event, rotate_drag_ghost, set_ghost_position, spawn_item, update_stack_badges,
+    AlbumId, AssetStorage, CleanupOnGameplayEnd, DragEvent, ItemId, Player, SpawnItemEvent,
+    TextureId,
 };
 //use crate::hud::gold::gold_update_system;
 use crate::config::data_items::ItemsData;
@@ -39,21 +65,28 @@ impl Plugin for GamePlugin {
             .add_event::<AddFeedItemEvent>()
             .add_event::<SimMessageEvent>()
             .add_event::<SimLootEvent>()
+            .add_event::<LearnRecipeEvent>()
+            .add_event::<EquipEvent>()
+            .add_event::<WearGearEvent>()
+            .add_event::<UseOnEnemyEvent>()
+            .add_event::<ApplyEffectEvent>()
+            .add_event::<EffectClockEvent>()
+            .add_event::<DiscardEvent>()
             .add_plugin(bevy_ninepatch::NinePatchPlugin::<()>::default())
             .init_resource::<Player>()
-            .insert_resource(TimedEffectTicker {
-                timer: Timer::new(Duration::from_secs(1), true),
-            })
-            .insert_resource(Hero {
-                combat_stats: Combatant {
-                    health: 20,
-                    max_health: 20,
-                    proficiency: 1,
-                    damage_res: 0,
-                    damage_bonus: 0,
-                    negative_feedback: 0,
-                },
-            })
+            .init_resource::<RecipeBook>()
+            .init_resource::<Occupancy>()
+            .init_resource::<GridCursor>()
+            .init_resource::<Trash>()
+            .insert_resource(Hero::new(Combatant {
+                health: 20,
+                max_health: 20,
+                proficiency: 1,
+                damage_res: 0,
+                damage_bonus: 0,
+                negative_feedback: 0,
+            }))
+            .init_resource::<HeroStatSources>()
             .init_resource::<Enemy>()
             .add_enter_system_set(
                 AppState::InGame,
@@ -70,7 +103,9 @@ impl Plugin for GamePlugin {
                     .run_in_state(AppState::InGame)
                     .with_system(spawn_item)
                     .with_system(check_drag_begin)
+                    .with_system(check_equipment_drag_begin)
                     .with_system(set_ghost_position)
+                    .with_system(rotate_drag_ghost)
                     .with_system(apply_silhouette)
                     .with_system(check_ghost_placement_validity)
                     .with_system(check_drag_end)
@@ -79,20 +114,92 @@ impl Plugin for GamePlugin {
                     .with_system(animate)
                     .with_system(tick_dungeon)
                     .with_system(tick_temporary_modifiers)
+                    .with_system(apply_effects)
+                    .with_system(update_enemy_stats)
                     .with_system(test_apply_modifier)
                     .with_system(handle_sim_message)
                     .with_system(handle_add_to_feed)
+                    .with_system(scroll_feed)
+                    .with_system(update_feed_lines)
                     .with_system(manage_continue_prompt)
                     .with_system(handle_sim_loot)
                     .with_system(update_health_bar)
+                    .with_system(update_hero_stats)
                     .with_system(update_hero_stats_display)
+                    .with_system(show_stat_breakdown)
+                    .with_system(update_status_trays)
+                    .with_system(tick_status_icons)
+                    .with_system(show_status_tooltip)
                     .with_system(eye_tracking_system)
                     .with_system(update_mouse_over_item_info_system)
                     .with_system(update_mouse_over_item_info_style_position_system)
                     .with_system(position_feed_item)
+                    .with_system(toggle_feed_history)
                     .with_system(consume_item)
+                    .with_system(equip_items)
+                    .with_system(update_equipment_slots)
+                    .with_system(wear_gear)
+                    .with_system(slide_encounter_panel)
+                    .with_system(update_encounter_panel)
+                    .with_system(use_items_on_enemy)
                     .with_system(delete_item_system)
                     .with_system(animate_falling_item)
+                    .with_system(update_stack_badges)
+                    .with_system(auto_sort_inventory)
+                    .with_system(use_bag_upgrades)
+                    .with_system(draw_bag_grids)
+                    .with_system(learn_recipes)
+                    .with_system(toggle_recipe_book)
+                    .with_system(draw_recipe_book)
+                    .with_system(update_crafting_preview)
+                    .with_system(draw_grid_cursor)
+                    .with_system(discard_items)
+                    .with_system(undo_discard)
+                    .with_system(empty_trash)
+                    .with_system(update_trash_zone)
+                    .into(),
+            )
+            // Also drawn on the game over screen, see GameEndedPlugin.
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(AppState::InGame)
+                    .run_if_resource_exists::<EventFeed>()
+                    .with_system(draw_feed_history)
+                    .into(),
+            )
+            // The grid cursor moves the mouse, so it runs between the mouse stages.
+            .add_system_set_to_stage(
+                "mouse_1",
+                ConditionSet::new()
+                    .run_in_state(AppState::InGame)
+                    .after("calc_mouse_pos")
+                    .with_system(move_grid_cursor)
+                    .into(),
+            )
+            .add_system_set_to_stage(
+                "mouse_2",
+                ConditionSet::new()
+                    .run_in_state(AppState::InGame)
+                    .after("track_mouse_hover")
+                    .with_system(grid_cursor_actions)
+                    .with_system(open_context_menu)
+                    .with_system(use_context_menu)
+                    .into(),
+            )
+            // Runs before everything else, so that the placement checks see last frame's changes.
+            .add_system_set_to_stage(
+                CoreStage::PreUpdate,
+                ConditionSet::new()
+                    .run_in_state(AppState::InGame)
+                    .with_system(update_occupancy)
+                    .into(),
+            )
+            // Runs after everything else, while the despawned items are still known.
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                ConditionSet::new()
+                    .run_in_state(AppState::InGame)
+                    .with_system(remove_despawned_items)
                     .into(),
             )
             .add_exit_system_set(
@@ -127,15 +234,25 @@ pub fn despawn_gameplay_entities(
     audio.send(SoundEvent::KillAllMusic);
 }
 
-fn clear_gameplay_data(mut hero: ResMut<Hero>) {
-    hero.combat_stats = Combatant {
+fn clear_gameplay_data(
+    mut hero: ResMut<Hero>,
+    mut book: ResMut<RecipeBook>,
+    mut occupancy: ResMut<Occupancy>,
+    mut cursor: ResMut<GridCursor>,
+    mut trash: ResMut<Trash>,
+) {
+    *book = RecipeBook::default();
+    *cursor = GridCursor::default();
+    *trash = Trash::default();
+    occupancy.clear();
+    *hero = Hero::new(Combatant {
         health: 20,
         max_health: 20,
         proficiency: 1,
         damage_res: 0,
         damage_bonus: 0,
         negative_feedback: 0,
-    };
+    });
 }
 
 pub fn eye_tracking_system(
@@ -168,16 +285,9 @@ pub fn create_debug_items(mut spawn: EventWriter<SpawnItemEvent>, items_db: Res<
     ));
 
     item = items_db.try_get_item(ItemId::Vial).unwrap();
-    spawn.send(SpawnItemEvent::without_anim(
-        item.1,
-        Coords::new(Pos::new(0, 0), item.0),
-    ));
-
-    item = items_db.try_get_item(ItemId::Vial).unwrap();
-    spawn.send(SpawnItemEvent::without_anim(
-        item.1,
-        Coords::new(Pos::new(1, 0), item.0),
-    ));
+    spawn.send(
+        SpawnItemEvent::without_anim(item.1, Coords::new(Pos::new(0, 0), item.0)).with_count(2),
+    );
 
     item = items_db.try_get_item(ItemId::HerbRed).unwrap();
     spawn.send(SpawnItemEvent::without_anim(
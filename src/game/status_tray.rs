@@ -0,0 +1,242 @@
+use bevy::prelude::*;
+
+use crate::config::data_layout::LayoutData;
+use crate::config::data_locale::UiText;
+use crate::game::hero_stats::{spawn_text_tooltip, stat_change_line, HeroStat};
+use crate::game::timed_effect::TemporaryModifier;
+use crate::game::{AssetStorage, FontId};
+use crate::locale::Locale;
+use crate::mouse::{Mouse, MouseInteractive};
+
+/// A row of icons for the effects on the hero or on the enemy. The icons are laid out to the right
+/// of the tray's position.
+#[derive(Component)]
+pub struct StatusTray {
+    pub on_hero: bool,
+    pub icon_size: f32,
+}
+
+/// The icon of a single effect in a status tray.
+#[derive(Component)]
+pub struct StatusIcon {
+    /// The entity with the TemporaryModifier.
+    pub modifier: Entity,
+    pub on_hero: bool,
+}
+
+/// Marker component for the countdown on a status icon.
+#[derive(Component)]
+pub struct StatusIconCountdown;
+
+/// Marker component for the tooltip of a status icon.
+#[derive(Component)]
+pub struct StatusTooltip;
+
+/// The lines of the tooltip of an effect: where it comes from, how it changes the stats and how
+/// long it lasts.
+pub fn modifier_lines(modifier: &TemporaryModifier, locale: &Locale) -> Vec<String> {
+    let bonus = modifier.stat_bonus();
+    let mut lines = vec![modifier
+        .source
+        .clone()
+        .unwrap_or_else(|| locale.ui(UiText::TemporaryEffect))];
+    lines.extend(
+        HeroStat::ALL
+            .iter()
+            .map(|stat| (stat, stat.of_bonus(&bonus)))
+            .filter(|(_, value)| *value != 0)
+            .map(|(stat, value)| stat_change_line(locale.ui(stat.ui_text()), value, locale)),
+    );
+    lines.push(locale.ui_with(
+        UiText::EffectLasts,
+        &[("duration", modifier.duration.describe(locale))],
+    ));
+    lines
+}
+
+/// Keeps one icon in each tray for every effect on its side. The icons are rebuilt whenever an
+/// effect is added or runs out.
+pub fn update_status_trays(
+    mut commands: Commands,
+    assets: Res<AssetStorage>,
+    layout: Res<LayoutData>,
+    query_trays: Query<(Entity, &StatusTray, Option<&Children>)>,
+    query_icons: Query<&StatusIcon>,
+    query_modifiers: Query<(Entity, &TemporaryModifier)>,
+) {
+    for (tray_entity, tray, children) in query_trays.iter() {
+        let mut modifiers: Vec<(Entity, &TemporaryModifier)> = query_modifiers
+            .iter()
+            .filter(|(_, modifier)| modifier.on_hero == tray.on_hero)
+            .collect();
+        modifiers.sort_by_key(|(entity, _)| *entity);
+        let icons: Vec<(Entity, Entity)> = children
+            .map(|children| {
+                children
+                    .iter()
+                    .filter_map(|child| {
+                        query_icons
+                            .get(*child)
+                            .ok()
+                            .map(|icon| (*child, icon.modifier))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if icons.len() == modifiers.len()
+            && icons
+                .iter()
+                .zip(modifiers.iter())
+                .all(|((_, shown), (wanted, _))| shown == wanted)
+        {
+            continue;
+        }
+        for (icon, _) in icons {
+            commands.entity(icon).despawn_recursive();
+        }
+        let size = Vec2::splat(tray.icon_size);
+        commands.entity(tray_entity).with_children(|parent| {
+            for (i, (entity, modifier)) in modifiers.iter().enumerate() {
+                let x = (i as f32 + 0.5) * tray.icon_size * 1.15;
+                let mut icon = parent.spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(size),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(x, 0., 1.),
+                    ..default()
+                });
+                match modifier.icon {
+                    Some(texture) => {
+                        icon.insert(assets.texture(&texture));
+                    }
+                    None => {
+                        icon.insert(Sprite {
+                            color: Color::rgb(0.5, 0.5, 0.6),
+                            custom_size: Some(size),
+                            ..default()
+                        });
+                    }
+                }
+                icon.insert(Name::new("StatusIcon"))
+                    .insert(StatusIcon {
+                        modifier: *entity,
+                        on_hero: tray.on_hero,
+                    })
+                    .insert(MouseInteractive::new(size, false))
+                    .with_children(|icon_parent| {
+                        icon_parent
+                            .spawn_bundle(Text2dBundle {
+                                text: Text::from_section(
+                                    "",
+                                    TextStyle {
+                                        font: assets.font(&FontId::FiraSansBold),
+                                        font_size: 40.0,
+                                        color: Color::ANTIQUE_WHITE,
+                                    },
+                                )
+                                .with_alignment(TextAlignment::CENTER),
+                                transform: Transform::from_xyz(size.x * 0.25, size.y * -0.3, 1.)
+                                    .with_scale(Vec3::new(
+                                        1. / layout.text_factor,
+                                        1. / layout.text_factor,
+                                        1.,
+                                    )),
+                                ..default()
+                            })
+                            .insert(StatusIconCountdown);
+                    });
+            }
+        });
+    }
+}
+
+/// Counts down what is left of each effect on its icon, and flashes the icons of effects that
+/// are about to run out.
+pub fn tick_status_icons(
+    time: Res<Time>,
+    query_modifiers: Query<&TemporaryModifier>,
+    mut query_icons: Query<(&StatusIcon, &mut Sprite)>,
+    mut query_countdowns: Query<(&Parent, &mut Text), With<StatusIconCountdown>>,
+) {
+    for (icon, mut sprite) in query_icons.iter_mut() {
+        let alpha = match query_modifiers.get(icon.modifier) {
+            Ok(modifier) if modifier.about_to_end() => {
+                0.4 + 0.6 * (time.seconds_since_startup() as f32 * 8.).cos().abs()
+            }
+            _ => 1.,
+        };
+        if sprite.color.a() != alpha {
+            sprite.color.set_a(alpha);
+        }
+    }
+    for (parent, mut text) in query_countdowns.iter_mut() {
+        let modifier = query_icons
+            .get(parent.get())
+            .ok()
+            .and_then(|(icon, _)| query_modifiers.get(icon.modifier).ok());
+        if let Some(modifier) = modifier {
+            let countdown = modifier.duration.remaining().to_string();
+            if text.sections[0].value != countdown {
+                text.sections[0].value = countdown;
+            }
+        }
+    }
+}
+
+/// Shows what an effect does while its icon is hovered over.
+pub fn show_status_tooltip(
+    mut commands: Commands,
+    mut shown: Local<Option<Vec<String>>>,
+    mouse: Res<Mouse>,
+    assets: Res<AssetStorage>,
+    layout: Res<LayoutData>,
+    query_modifiers: Query<&TemporaryModifier>,
+    query_icons: Query<(&StatusIcon, &MouseInteractive)>,
+    query_tooltip: Query<Entity, With<StatusTooltip>>,
+    locale: Res<Locale>,
+) {
+    let lines = query_icons
+        .iter()
+        .find(|(_, interactive)| interactive.hovered && !mouse.is_dragging)
+        .and_then(|(icon, _)| query_modifiers.get(icon.modifier).ok())
+        .map(|modifier| modifier_lines(modifier, &locale));
+    if *shown == lines {
+        return;
+    }
+    for entity in query_tooltip.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *shown = lines.clone();
+    if let Some(lines) = lines {
+        let entity = spawn_text_tooltip(&mut commands, &assets, &layout, mouse.position, &lines);
+        commands
+            .entity(entity)
+            .insert(Name::new("StatusTooltip"))
+            .insert(StatusTooltip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tooltip_lists_only_the_stats_that_change() {
+        let modifier = TemporaryModifier {
+            combat_prof_mod: -3,
+            damage_mod: 2,
+            source: Some("Poison Flask".to_string()),
+            ..default()
+        };
+        assert_eq!(
+            modifier_lines(&modifier, &Locale::english()),
+            vec![
+                "Poison Flask",
+                "Combat Proficiency: -3",
+                "Damage Bonus: +2",
+                "Lasts 1 more step"
+            ]
+        );
+    }
+}
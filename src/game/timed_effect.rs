@@ -1,151 +1,387 @@
-﻿use crate::game::combat::{Enemy, Hero};
-use crate::{default, Entity, KeyCode, Query, Res};
-use bevy::input::Input;
-use bevy::prelude::{Commands, Component, ResMut};
-use bevy::time::{Time, Timer};
+use std::collections::HashSet;
+
+use crate::config::config_input::InputAction;
+use crate::config::data_locale::UiText;
+use crate::game::combat::{Enemy, Hero};
+use crate::game::hero_stats::{effective_stats, StatSource};
+use crate::game::{ItemId, StatBonus, TextureId};
+use crate::input::Actions;
+use crate::locale::Locale;
+use crate::{default, Entity, Query, Res};
+use bevy::prelude::{Commands, Component, EventReader, EventWriter, ResMut};
 use serde::{Deserialize, Serialize};
 
-pub struct TimedEffectTicker {
-    pub timer: Timer,
+/// How much longer an effect lasts. Time is measured by the dungeon, so effects don't run out
+/// while the sim is waiting for the player.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectDuration {
+    /// Steps of the dungeon sim, i.e. messages in the feed.
+    Ticks(u32),
+    /// Rounds of combat. Doesn't count down outside of fights.
+    Rounds(u32),
+    /// Rooms that are left behind. Rooms(1) lasts until the current room is left.
+    Rooms(u32),
+}
+
+impl EffectDuration {
+    pub fn remaining(&self) -> u32 {
+        match self {
+            EffectDuration::Ticks(n) | EffectDuration::Rounds(n) | EffectDuration::Rooms(n) => *n,
+        }
+    }
+
+    /// E.g. "3 more rounds".
+    pub fn describe(&self, locale: &Locale) -> String {
+        let n = self.remaining();
+        let id = match (self, n == 1) {
+            (EffectDuration::Ticks(_), true) => UiText::DurationStep,
+            (EffectDuration::Ticks(_), false) => UiText::DurationSteps,
+            (EffectDuration::Rounds(_), true) => UiText::DurationRound,
+            (EffectDuration::Rounds(_), false) => UiText::DurationRounds,
+            (EffectDuration::Rooms(_), true) => UiText::DurationRoom,
+            (EffectDuration::Rooms(_), false) => UiText::DurationRooms,
+        };
+        locale.ui_with(id, &[("n", n.to_string())])
+    }
+}
+
+/// Something that happens in the dungeon that can end an effect early.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectEnd {
+    CombatEnd,
+    RoomEnd,
+}
+
+/// What happens when an effect is applied while an effect with the same key is still active on
+/// the same side.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StackPolicy {
+    /// The new effect takes the place of the old one, which starts the duration over.
+    #[default]
+    Refresh,
+    /// Both effects count.
+    Stack,
+    /// The new effect takes the place of the old one, unless the old one is stronger.
+    ReplaceIfStronger,
 }
 
 #[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct TemporaryModifier {
-    pub time: f32,
+    pub duration: EffectDuration,
     pub max_health_mod: i32,
     pub combat_prof_mod: i32,
     pub damage_mod: i32,
     pub damage_res_mod: i32,
     pub on_hero: bool,
-    pub applied: bool,
-    pub expired: bool,
+    /// Effects with the same key are the same effect, and don't add up unless the stacking policy
+    /// says so. Defaults to the item that caused the effect.
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub stacking: StackPolicy,
+    /// Ends the effect early. Effects on the enemy always end with the fight.
+    #[serde(default)]
+    pub ends_on: Option<EffectEnd>,
+    /// The name of whatever caused the effect, such as the potion that was drunk.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// The icon of the effect in the status trays, usually the texture of its source.
+    #[serde(default)]
+    pub icon: Option<TextureId>,
+    /// The item that caused the effect. Unlike the source, it doesn't change with the language.
+    #[serde(skip)]
+    pub item: Option<ItemId>,
+}
+
+impl TemporaryModifier {
+    /// The effect on the stats. See `update_hero_stats` and `update_enemy_stats`.
+    pub fn stat_bonus(&self) -> StatBonus {
+        StatBonus {
+            health: 0,
+            max_health: self.max_health_mod,
+            proficiency: self.combat_prof_mod,
+            damage_bonus: self.damage_mod,
+            damage_res: self.damage_res_mod,
+        }
+    }
+
+    pub fn effective_key(&self) -> Option<String> {
+        self.key
+            .clone()
+            .or_else(|| self.item.as_ref().map(ItemId::to_string))
+    }
+
+    /// How much the effect changes the stats, for comparing effects with the same key.
+    pub fn strength(&self) -> i32 {
+        self.max_health_mod.abs()
+            + self.combat_prof_mod.abs()
+            + self.damage_mod.abs()
+            + self.damage_res_mod.abs()
+    }
+
+    /// Whether the effect will run out with the next step of its clock.
+    pub fn about_to_end(&self) -> bool {
+        self.duration.remaining() <= 1
+    }
+
+    /// Moves the clock of the effect along. Returns true iff the effect is over.
+    pub fn advance(&mut self, clock: EffectClockEvent) -> bool {
+        let ends_on = match clock {
+            EffectClockEvent::CombatEnded => Some(EffectEnd::CombatEnd),
+            EffectClockEvent::RoomLeft => Some(EffectEnd::RoomEnd),
+            _ => None,
+        };
+        if ends_on.is_some() && (ends_on == self.ends_on || !self.on_hero) {
+            return true;
+        }
+        match (&mut self.duration, clock) {
+            (EffectDuration::Ticks(n), EffectClockEvent::Tick)
+            | (EffectDuration::Rounds(n), EffectClockEvent::Round)
+            | (EffectDuration::Rooms(n), EffectClockEvent::RoomLeft) => {
+                *n = n.saturating_sub(1);
+                *n == 0
+            }
+            _ => false,
+        }
+    }
 }
 
 impl Default for TemporaryModifier {
     fn default() -> Self {
         TemporaryModifier {
-            time: 1.0,
+            duration: EffectDuration::Ticks(1),
             max_health_mod: 0,
             combat_prof_mod: 0,
             damage_mod: 0,
             damage_res_mod: 0,
             on_hero: false,
-            applied: false,
-            expired: false,
+            key: None,
+            stacking: StackPolicy::Refresh,
+            ends_on: None,
+            source: None,
+            icon: None,
+            item: None,
         }
     }
 }
 
-#[derive(Component)]
-pub struct DamageOverTime {
-    pub ticks: f32,
-    pub damage: f32,
-    pub on_hero: bool,
+/// Broadcast this event to put an effect on the hero or the enemy.
+pub struct ApplyEffectEvent(pub TemporaryModifier);
+
+/// Broadcast by the dungeon sim to move the clocks of the effects along.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EffectClockEvent {
+    /// The sim took a step.
+    Tick,
+    /// A round of combat was fought.
+    Round,
+    CombatEnded,
+    RoomLeft,
 }
 
-#[derive(Component)]
-pub struct ModifierExpired;
+/// What to do with a new effect, given the effects that are already active.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Stacking {
+    Add,
+    /// Add the new effect, and remove these.
+    Replace(Vec<Entity>),
+    Discard,
+}
 
-pub fn tick_temporary_modifiers(
-    mut q: Query<(Entity, &mut TemporaryModifier)>,
-    mut hero: ResMut<Hero>,
-    mut enemy: ResMut<Enemy>,
-    mut ticker: ResMut<TimedEffectTicker>,
-    time: Res<Time>,
+pub fn stack_effect<'a>(
+    new: &TemporaryModifier,
+    active: impl Iterator<Item = (Entity, &'a TemporaryModifier)>,
+) -> Stacking {
+    let key = match new.effective_key() {
+        Some(key) => key,
+        None => return Stacking::Add,
+    };
+    let same: Vec<(Entity, &TemporaryModifier)> = active
+        .filter(|(_, old)| old.on_hero == new.on_hero && old.effective_key().as_ref() == Some(&key))
+        .collect();
+    if same.is_empty() {
+        return Stacking::Add;
+    }
+    let replaced = same.iter().map(|(entity, _)| *entity).collect();
+    match new.stacking {
+        StackPolicy::Stack => Stacking::Add,
+        StackPolicy::Refresh => Stacking::Replace(replaced),
+        StackPolicy::ReplaceIfStronger => {
+            if same.iter().all(|(_, old)| new.strength() >= old.strength()) {
+                Stacking::Replace(replaced)
+            } else {
+                Stacking::Discard
+            }
+        }
+    }
+}
+
+/// Puts new effects on the hero or the enemy, taking the stacking policies into account.
+pub fn apply_effects(
     mut cmd: Commands,
+    mut events: EventReader<ApplyEffectEvent>,
+    query: Query<(Entity, &TemporaryModifier)>,
 ) {
-    if ticker.timer.tick(time.delta()).just_finished() {
-        for (e, mut modifier) in q.iter_mut() {
-            // actually apply modifier to stats if not yet applied
-            if !modifier.applied {
-                modifier.applied = true;
-                if modifier.on_hero {
-                    hero.combat_stats.damage_bonus += modifier.damage_mod;
-                    hero.combat_stats.max_health += modifier.max_health_mod;
-                    hero.combat_stats.damage_res += modifier.damage_res_mod;
-                    hero.combat_stats.proficiency += modifier.combat_prof_mod;
-                    if hero.combat_stats.health > hero.combat_stats.max_health {
-                        hero.combat_stats.health = hero.combat_stats.max_health;
-                    }
-                } else {
-                    enemy.combat_stats.damage_bonus += modifier.damage_mod;
-                    enemy.combat_stats.max_health += modifier.max_health_mod;
-                    enemy.combat_stats.damage_res += modifier.damage_res_mod;
-                    enemy.combat_stats.proficiency += modifier.combat_prof_mod;
-                    if enemy.combat_stats.health > enemy.combat_stats.max_health {
-                        enemy.combat_stats.health = enemy.combat_stats.max_health;
-                    }
+    for ApplyEffectEvent(modifier) in events.iter() {
+        match stack_effect(modifier, query.iter()) {
+            Stacking::Add => {}
+            Stacking::Replace(old) => {
+                for entity in old {
+                    cmd.entity(entity).despawn();
                 }
             }
+            Stacking::Discard => continue,
+        }
+        cmd.spawn().insert(modifier.clone());
+    }
+}
 
-            // despawn modifier and return early if combatant is dead
-            if hero.combat_stats.health < 1 && modifier.on_hero {
-                hero.combat_stats.damage_bonus -= modifier.damage_mod;
-                hero.combat_stats.max_health -= modifier.max_health_mod;
-                hero.combat_stats.damage_res -= modifier.damage_res_mod;
-                hero.combat_stats.proficiency -= modifier.combat_prof_mod;
-                cmd.entity(e).despawn();
-                continue;
-            }
-
-            if enemy.combat_stats.health < 1 && !modifier.on_hero {
-                enemy.combat_stats.damage_bonus -= modifier.damage_mod;
-                enemy.combat_stats.max_health -= modifier.max_health_mod;
-                enemy.combat_stats.damage_res -= modifier.damage_res_mod;
-                enemy.combat_stats.proficiency -= modifier.combat_prof_mod;
-                cmd.entity(e).despawn();
-                continue;
-            }
-
-            // tick timer
-            modifier.time -= (ticker.timer.duration().as_millis() / 1000.0 as u128) as f32;
-            // if expired, remove effect and despawn entity
-            if modifier.time <= 0.0 {
-                if modifier.on_hero {
-                    hero.combat_stats.damage_bonus -= modifier.damage_mod;
-                    hero.combat_stats.max_health -= modifier.max_health_mod;
-                    hero.combat_stats.damage_res -= modifier.damage_res_mod;
-                    hero.combat_stats.proficiency -= modifier.combat_prof_mod;
-                } else {
-                    enemy.combat_stats.damage_bonus -= modifier.damage_mod;
-                    enemy.combat_stats.max_health -= modifier.max_health_mod;
-                    enemy.combat_stats.damage_res -= modifier.damage_res_mod;
-                    enemy.combat_stats.proficiency -= modifier.combat_prof_mod;
-                }
-                cmd.entity(e).despawn();
+/// Counts effects down as the dungeon moves along, and removes the ones that are over.
+/// Effects on a dead hero are over as well.
+pub fn tick_temporary_modifiers(
+    mut cmd: Commands,
+    mut events: EventReader<EffectClockEvent>,
+    hero: Res<Hero>,
+    mut query: Query<(Entity, &mut TemporaryModifier)>,
+) {
+    let mut ended = HashSet::new();
+    for (entity, modifier) in query.iter() {
+        if modifier.on_hero && hero.combat_stats.health < 1 {
+            ended.insert(entity);
+        }
+    }
+    for clock in events.iter() {
+        for (entity, mut modifier) in query.iter_mut() {
+            if !ended.contains(&entity) && modifier.advance(*clock) {
+                ended.insert(entity);
             }
         }
     }
+    for entity in ended {
+        cmd.entity(entity).despawn();
+    }
 }
 
-pub fn apply_timed_modifier(modifier: TemporaryModifier, cmd: &mut Commands) {
-    cmd.spawn().insert(modifier);
+/// Derives the enemy's stats from its base stats and the effects on it, the same way as for the
+/// hero. This way, nothing carries over from one enemy to the next.
+pub fn update_enemy_stats(mut enemy: ResMut<Enemy>, query: Query<&TemporaryModifier>) {
+    let sources: Vec<StatSource> = query
+        .iter()
+        .filter(|modifier| !modifier.on_hero)
+        .map(|modifier| StatSource {
+            name: modifier.source.clone().unwrap_or_default(),
+            bonus: modifier.stat_bonus(),
+        })
+        .collect();
+    let stats = effective_stats(&enemy.base_stats, &enemy.combat_stats, &sources);
+    if stats != enemy.combat_stats {
+        enemy.combat_stats = stats;
+    }
 }
 
-pub fn test_apply_modifier(input: Res<Input<KeyCode>>, mut cmd: Commands) {
-    if input.just_pressed(KeyCode::M) {
-        apply_timed_modifier(
-            TemporaryModifier {
-                time: 10.0,
-                damage_mod: 4,
-                on_hero: true,
-                ..default()
-            },
-            &mut cmd,
-        );
+pub fn test_apply_modifier(actions: Actions, mut events: EventWriter<ApplyEffectEvent>) {
+    if actions.just_pressed(InputAction::DebugBuffHero) {
+        events.send(ApplyEffectEvent(TemporaryModifier {
+            duration: EffectDuration::Ticks(5),
+            damage_mod: 4,
+            on_hero: true,
+            ..default()
+        }));
     }
 
-    if input.just_pressed(KeyCode::N) {
-        apply_timed_modifier(
-            TemporaryModifier {
-                time: 10.0,
-                combat_prof_mod: -3,
-                on_hero: false,
-                ..default()
-            },
-            &mut cmd,
-        );
+    if actions.just_pressed(InputAction::DebugDebuffEnemy) {
+        events.send(ApplyEffectEvent(TemporaryModifier {
+            duration: EffectDuration::Rounds(5),
+            combat_prof_mod: -3,
+            on_hero: false,
+            ..default()
+        }));
     }
 }
 
-pub fn tick_damage_over_time() {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strength_potion(name: &str, damage_mod: i32, stacking: StackPolicy) -> TemporaryModifier {
+        TemporaryModifier {
+            duration: EffectDuration::Rounds(5),
+            damage_mod,
+            on_hero: true,
+            key: Some("Strength".to_string()),
+            stacking,
+            source: Some(name.to_string()),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn effects_with_the_same_key_follow_the_stacking_policy() {
+        let weak = strength_potion("Flask of Strength", 2, StackPolicy::ReplaceIfStronger);
+        let strong = strength_potion(
+            "Greater Flask of Strength",
+            4,
+            StackPolicy::ReplaceIfStronger,
+        );
+        let old = Entity::from_raw(1);
+        assert_eq!(
+            stack_effect(&strong, [(old, &weak)].into_iter()),
+            Stacking::Replace(vec![old])
+        );
+        assert_eq!(
+            stack_effect(&weak, [(old, &strong)].into_iter()),
+            Stacking::Discard
+        );
+        let refresh = strength_potion("Flask of Strength", 2, StackPolicy::Refresh);
+        assert_eq!(
+            stack_effect(&refresh, [(old, &weak)].into_iter()),
+            Stacking::Replace(vec![old])
+        );
+        let stack = strength_potion("Flask of Strength", 2, StackPolicy::Stack);
+        assert_eq!(
+            stack_effect(&stack, [(old, &weak)].into_iter()),
+            Stacking::Add
+        );
+        // Effects on the enemy are kept apart from effects on the hero.
+        let on_enemy = TemporaryModifier {
+            on_hero: false,
+            ..weak.clone()
+        };
+        assert_eq!(
+            stack_effect(&refresh, [(old, &on_enemy)].into_iter()),
+            Stacking::Add
+        );
+        // Without a key, the item is the key, whatever its name is in the current language.
+        let keyless = |name: &str| TemporaryModifier {
+            key: None,
+            item: Some(ItemId::FlaskStrength),
+            ..strength_potion(name, 2, StackPolicy::Refresh)
+        };
+        assert_eq!(
+            stack_effect(
+                &keyless("Flask of Strength"),
+                [(old, &keyless("Stärketrank"))].into_iter()
+            ),
+            Stacking::Replace(vec![old])
+        );
+    }
+
+    #[test]
+    fn effects_count_down_on_their_own_clock() {
+        let mut potion = strength_potion("Flask of Strength", 2, StackPolicy::Refresh);
+        potion.duration = EffectDuration::Rounds(2);
+        assert!(!potion.advance(EffectClockEvent::Tick));
+        assert!(!potion.advance(EffectClockEvent::Round));
+        assert!(!potion.advance(EffectClockEvent::CombatEnded));
+        assert!(potion.advance(EffectClockEvent::Round));
+
+        let mut poison = TemporaryModifier {
+            duration: EffectDuration::Rounds(5),
+            on_hero: false,
+            ..default()
+        };
+        // Effects on the enemy don't outlast it.
+        assert!(poison.advance(EffectClockEvent::CombatEnded));
+        assert!(poison.advance(EffectClockEvent::RoomLeft));
+    }
+}
@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+
+use crate::config::config_input::InputAction;
+use crate::config::data_locale::UiText;
+use crate::game::affixes::Rarity;
+use crate::game::feed::{AddFeedItemEvent, FeedCategory, MessageColour};
+use crate::game::item_info_system::TooltipBg;
+use crate::game::items::{Item, Rotated, Stack};
+use crate::game::occupancy::Occupancy;
+use crate::game::timed_effect::EffectClockEvent;
+use crate::game::{find_free_space, FontId, Player, SpawnItemEvent};
+use crate::input::Actions;
+use crate::locale::Locale;
+use crate::mouse::MouseInteractive;
+use crate::positioning::{Coords, GridData};
+
+/// Broadcast this event to put items in the trash. Discarding all of a stack removes it from
+/// the bag.
+pub struct DiscardEvent {
+    pub entity: Entity,
+    /// How many items of the stack are discarded.
+    pub count: i32,
+}
+
+/// Resource. The items that were thrown away in the current room. They can be taken back out
+/// until the hero moves on, at which point they are salvaged for a little gold.
+#[derive(Default)]
+pub struct Trash {
+    pub items: Vec<Discarded>,
+}
+
+/// Everything that is needed to put a discarded item back where it was.
+#[derive(Debug, Clone)]
+pub struct Discarded {
+    pub item: Item,
+    pub count: i32,
+    pub coords: Coords,
+    pub rotated: bool,
+}
+
+/// The area at the left end of the toasts bar that items are dragged onto to throw them away.
+/// Clicking it takes the last item back out.
+#[derive(Component)]
+pub struct TrashZone;
+
+/// Marker component for the text on the trash zone.
+#[derive(Component)]
+pub struct TrashZoneText;
+
+/// How much gold a single discarded item is salvaged for.
+pub fn salvage_value(item: &Item) -> i32 {
+    match item.rarity {
+        Rarity::Common => 1,
+        Rarity::Uncommon => 3,
+        Rarity::Rare => 6,
+    }
+}
+
+/// Takes discarded items off the grid and puts them in the trash.
+pub fn discard_items(
+    mut commands: Commands,
+    mut events: EventReader<DiscardEvent>,
+    mut trash: ResMut<Trash>,
+    mut occupancy: ResMut<Occupancy>,
+    mut feed: EventWriter<AddFeedItemEvent>,
+    actions: Actions,
+    locale: Res<Locale>,
+    mut query_items: Query<(&Item, &Coords, &mut Stack, Option<&Rotated>)>,
+    tooltips: Query<Entity, With<TooltipBg>>,
+) {
+    for DiscardEvent { entity, count } in events.iter() {
+        if let Ok((item, coords, mut stack, rotated)) = query_items.get_mut(*entity) {
+            let count = (*count).min(stack.0);
+            if count <= 0 {
+                continue;
+            }
+            stack.0 -= count;
+            if stack.0 <= 0 {
+                commands.entity(*entity).despawn_recursive();
+                occupancy.remove(*entity);
+                for tooltip in tooltips.iter() {
+                    commands.entity(tooltip).despawn_recursive();
+                }
+            }
+            trash.items.push(Discarded {
+                item: item.clone(),
+                count,
+                coords: *coords,
+                rotated: rotated.is_some(),
+            });
+            feed.send(AddFeedItemEvent {
+                message: locale.ui_with(
+                    UiText::TrashThrown,
+                    &[
+                        ("item", item.display_name(&locale)),
+                        ("key", actions.key_name(InputAction::Undo)),
+                    ],
+                ),
+                font: FontId::FiraSansItalic,
+                colour: MessageColour::Neutral,
+                category: FeedCategory::Inventory,
+            });
+        }
+    }
+}
+
+/// Takes the last discarded item back out of the trash, when the Undo action is triggered or the
+/// trash zone is clicked. The item goes back where it was if that spot is still free. Otherwise
+/// it is added to a stack of the same item, or put in the first free spot in the bag.
+pub fn undo_discard(
+    actions: Actions,
+    grid: Res<GridData>,
+    occupancy: Res<Occupancy>,
+    mut trash: ResMut<Trash>,
+    mut spawn: EventWriter<SpawnItemEvent>,
+    mut feed: EventWriter<AddFeedItemEvent>,
+    locale: Res<Locale>,
+    query_zone: Query<(&MouseInteractive, &GlobalTransform), With<TrashZone>>,
+    query_stacks: Query<(Entity, &Item, &Coords, &Stack)>,
+) {
+    let clicked = query_zone
+        .iter()
+        .any(|(interactive, _)| interactive.clicked);
+    if !clicked && !actions.just_pressed(InputAction::Undo) {
+        return;
+    }
+    let discarded = match trash.items.pop() {
+        Some(discarded) => discarded,
+        None => return,
+    };
+    // The item flies back out of the trash.
+    let source = query_zone
+        .iter()
+        .next()
+        .map(|(_, transform)| transform.translation().truncate())
+        .unwrap_or(Vec2::ZERO);
+    let Discarded {
+        item,
+        count,
+        coords,
+        rotated,
+    } = discarded.clone();
+    let fits_where_it_was = occupancy.is_free(&coords, &[])
+        && (grid.can_store(&coords, item.kind()) || grid.crafting.encloses(&coords));
+    if fits_where_it_was {
+        spawn.send(
+            SpawnItemEvent::new(item, coords, source, false)
+                .with_count(count)
+                .with_rotation(rotated),
+        );
+        return;
+    }
+    let stack = query_stacks
+        .iter()
+        .find(|(_, stack_item, stack_coords, stack)| {
+            stack_item.id == item.id
+                && grid.in_storage(stack_coords)
+                && stack.room(stack_item) >= count
+        });
+    if let Some((entity, _, stack_coords, _)) = stack {
+        spawn.send(
+            SpawnItemEvent::onto_stack(item, entity, *stack_coords, source).with_count(count),
+        );
+        return;
+    }
+    let footprint = if rotated {
+        coords.turned(false)
+    } else {
+        coords
+    };
+    match find_free_space(&grid, &occupancy, footprint, item.kind(), &[]) {
+        Some((coords, rotated)) => {
+            spawn.send(
+                SpawnItemEvent::new(item, coords, source, false)
+                    .with_count(count)
+                    .with_rotation(rotated),
+            );
+        }
+        None => {
+            feed.send(AddFeedItemEvent {
+                message: locale
+                    .ui_with(UiText::TrashNoRoom, &[("item", item.display_name(&locale))]),
+                font: FontId::FiraSansItalic,
+                colour: MessageColour::MinorNegative,
+                category: FeedCategory::Inventory,
+            });
+            trash.items.push(discarded);
+        }
+    }
+}
+
+/// Empties the trash when the hero leaves the room. Whatever is in it is salvaged for gold.
+pub fn empty_trash(
+    mut events: EventReader<EffectClockEvent>,
+    mut trash: ResMut<Trash>,
+    mut player: ResMut<Player>,
+    mut feed: EventWriter<AddFeedItemEvent>,
+    locale: Res<Locale>,
+) {
+    let left_room = events
+        .iter()
+        .filter(|event| matches!(event, EffectClockEvent::RoomLeft))
+        .count()
+        > 0;
+    if !left_room || trash.items.is_empty() {
+        return;
+    }
+    let gold: i32 = trash
+        .items
+        .drain(..)
+        .map(|discarded| salvage_value(&discarded.item) * discarded.count)
+        .sum();
+    player.gold.add(gold);
+    feed.send(AddFeedItemEvent {
+        message: locale.ui_with(UiText::TrashSalvaged, &[("gold", gold.to_string())]),
+        font: FontId::FiraSansItalic,
+        colour: MessageColour::MinorPositive,
+        category: FeedCategory::Inventory,
+    });
+}
+
+/// Shows how many things are in the trash.
+pub fn update_trash_zone(
+    trash: Res<Trash>,
+    locale: Res<Locale>,
+    mut query_text: Query<&mut Text, With<TrashZoneText>>,
+) {
+    if !trash.is_changed() && !locale.is_changed() {
+        return;
+    }
+    let label = match trash.items.len() {
+        0 => locale.ui(UiText::Trash),
+        n => format!("{}\n{}", locale.ui(UiText::Trash), n),
+    };
+    for mut text in query_text.iter_mut() {
+        text.sections[0].value = label.clone();
+    }
+}
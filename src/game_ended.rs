@@ -1,6 +1,9 @@
 use iyes_loopless::prelude::{ConditionSet, NextState};
 
+use crate::config::data_locale::UiText;
+use crate::game::feed::{draw_feed_history, EventFeed};
 use crate::game::GameResult;
+use crate::locale::Locale;
 use crate::*;
 
 pub struct GameEndedPlugin;
@@ -12,6 +15,14 @@ impl Plugin for GameEndedPlugin {
                 .run_in_state(AppState::GameEnded)
                 .with_system(draw_game_over_screen)
                 .into(),
+        )
+        // The feed history stays open on the game over screen, to look back at what went wrong.
+        .add_system_set(
+            ConditionSet::new()
+                .run_in_state(AppState::GameEnded)
+                .run_if_resource_exists::<EventFeed>()
+                .with_system(draw_feed_history)
+                .into(),
         );
     }
 }
@@ -22,6 +33,8 @@ fn draw_game_over_screen(
     mut egui_context: ResMut<EguiContext>,
     windows: ResMut<Windows>,
     result: Res<State<GameResult>>,
+    locale: Res<Locale>,
+    mut feed: Option<ResMut<EventFeed>>,
 ) {
     let win_fill = egui_context.ctx_mut().style().visuals.window_fill();
     let text_col = egui_context.ctx_mut().style().visuals.text_color();
@@ -47,10 +60,10 @@ fn draw_game_over_screen(
                 // title text
                 rect.center_top() + vec2(0.0, height / 2.0),
                 Align2::CENTER_CENTER,
-                match *result.current() {
-                    GameResult::Won => "The Ogre Necromancer is dead! You win!",
-                    GameResult::Lost => "You lost! Keep Sir Hoardalot alive!",
-                },
+                locale.ui(match *result.current() {
+                    GameResult::Won => UiText::GameWon,
+                    GameResult::Lost => UiText::GameLost,
+                }),
                 FontId::proportional(46.0),
                 text_col,
             );
@@ -65,10 +78,19 @@ fn draw_game_over_screen(
 
             let quit_btn = ui.put(
                 Rect::from_center_size(pos2(win_wi / 2., win_ht / 2. + 132.), vec2(280., 66.)),
-                egui::Button::new("Back to menu"),
+                egui::Button::new(locale.ui(UiText::BackToMenu)),
             );
             if quit_btn.clicked() {
                 commands.insert_resource(NextState(AppState::MainMenu));
             }
+            if let Some(feed) = feed.as_mut() {
+                let history_btn = ui.put(
+                    Rect::from_center_size(pos2(win_wi / 2., win_ht / 2. + 48.), vec2(280., 66.)),
+                    egui::Button::new(locale.ui(UiText::FeedHistory)),
+                );
+                if history_btn.clicked() {
+                    feed.window_open = !feed.window_open;
+                }
+            }
         });
 }
@@ -0,0 +1,98 @@
+use std::marker::PhantomData;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+
+use crate::config::config_input::{Binding, InputAction, InputButton, InputConfig};
+use crate::settings::SettingsPage;
+
+/// Whether an egui widget, like the search box of the feed history, has keyboard focus.
+/// Keys typed into it shouldn't trigger any actions.
+#[derive(Default)]
+pub struct KeyboardFocus(pub bool);
+
+pub fn track_keyboard_focus(mut egui: ResMut<EguiContext>, mut focus: ResMut<KeyboardFocus>) {
+    let wants_keyboard = egui.ctx_mut().wants_keyboard_input();
+    if focus.0 != wants_keyboard {
+        focus.0 = wants_keyboard;
+    }
+}
+
+/// Nothing is triggered while the settings page is waiting for a new key to bind, and keys don't
+/// trigger anything while egui has keyboard focus. Mouse buttons still work then.
+fn is_enabled(settings: &SettingsPage, focus: &KeyboardFocus, binding: &Binding) -> bool {
+    !settings.is_rebinding() && (!focus.0 || matches!(binding.button, InputButton::Mouse(_)))
+}
+
+/// System param to check for actions, instead of for raw keys and mouse buttons.
+#[derive(SystemParam)]
+pub struct Actions<'w, 's> {
+    config: Res<'w, InputConfig>,
+    keys: Res<'w, Input<KeyCode>>,
+    buttons: Res<'w, Input<MouseButton>>,
+    settings: Res<'w, SettingsPage>,
+    focus: Res<'w, KeyboardFocus>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> Actions<'w, 's> {
+    fn enabled_bindings(&self, action: InputAction) -> impl Iterator<Item = Binding> + '_ {
+        self.config
+            .bindings(action)
+            .into_iter()
+            .filter(|binding| is_enabled(&self.settings, &self.focus, binding))
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.enabled_bindings(action)
+            .any(|binding| binding.just_pressed(&self.keys, &self.buttons))
+    }
+
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.enabled_bindings(action)
+            .any(|binding| binding.pressed(&self.keys, &self.buttons))
+    }
+
+    pub fn just_released(&self, action: InputAction) -> bool {
+        self.config.just_released(action, &self.keys, &self.buttons)
+    }
+
+    /// The name of the first key that triggers the action, for showing in hints.
+    pub fn key_name(&self, action: InputAction) -> String {
+        self.config
+            .bindings(action)
+            .first()
+            .map(|binding| binding.name())
+            .unwrap_or_else(|| "(unbound)".to_string())
+    }
+}
+
+/// Like `Actions`, but an action that is seen as just pressed is consumed: the button that
+/// triggered it is cleared, so that the same press doesn't also trigger other systems.
+/// Meant for global actions like Back, that are handled regardless of the state.
+#[derive(SystemParam)]
+pub struct ConsumingActions<'w, 's> {
+    config: Res<'w, InputConfig>,
+    keys: ResMut<'w, Input<KeyCode>>,
+    buttons: ResMut<'w, Input<MouseButton>>,
+    settings: Res<'w, SettingsPage>,
+    focus: Res<'w, KeyboardFocus>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> ConsumingActions<'w, 's> {
+    pub fn just_pressed(&mut self, action: InputAction) -> bool {
+        let binding = self.config.bindings(action).into_iter().find(|binding| {
+            is_enabled(&self.settings, &self.focus, binding)
+                && binding.just_pressed(&self.keys, &self.buttons)
+        });
+        match binding.map(|binding| binding.button) {
+            Some(InputButton::Key(key)) => self.keys.clear_just_pressed(key),
+            Some(InputButton::Mouse(button)) => self.buttons.clear_just_pressed(button),
+            None => false,
+        }
+    }
+}
@@ -1,4 +1,4 @@
 pub mod atlas_prefab;
 mod loading_instructions;
 pub mod state;
-mod systems;
+pub mod systems;
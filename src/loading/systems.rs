@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use bevy::asset::LoadState;
@@ -6,27 +7,60 @@ use iyes_loopless::prelude::NextState;
 
 use crate::config::config_audio::AudioConfig;
 use crate::config::config_debug::DebugConfig;
+use crate::config::config_input::InputConfig;
+use crate::config::config_locale::{Language, LocaleConfig};
 use crate::config::config_sim::SimConfig;
+use crate::config::data_affixes::AffixesData;
 use crate::config::data_blueprint::BlueprintData;
 use crate::config::data_enemies::EnemiesData;
 use crate::config::data_items::ItemsData;
 use crate::config::data_layout::LayoutData;
+use crate::config::data_locale::LocaleData;
 use crate::config::data_recipes::RecipesData;
-use crate::config::data_texts::TextsData;
 use crate::game::{AlbumId, AssetStorage, TextureId};
 use crate::loading::loading_instructions::prepare_loading_config;
+use crate::locale::{log_missing_keys, Locale};
 use crate::AppState;
 
+/// The path of a config file in the override directory.
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+pub fn override_path(file: &str) -> PathBuf {
+    bevy::asset::FileAssetIo::get_base_path()
+        .join("assets/config/override")
+        .join(file)
+}
+
+/// The path of a config file, relative to the assets directory. A file in the override directory
+/// takes the place of the default one. See the README.
+fn config_path(file: &str) -> String {
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    if override_path(file).exists() {
+        return format!("config/override/{}", file);
+    }
+    format!("config/default/{}", file)
+}
+
 pub fn load_configs(server: Res<AssetServer>, mut assets: ResMut<AssetStorage>) {
-    assets.audio = server.load("config/default/config.audio.ron");
-    assets.debug = server.load("config/default/config.debug.ron");
-    assets.sim = server.load("config/default/config.sim.ron");
-    assets.blueprint = server.load("config/default/data.blueprint.ron");
-    assets.enemies = server.load("config/default/data.enemies.ron");
-    assets.items = server.load("config/default/data.items.ron");
-    assets.layout = server.load("config/default/data.layout.ron");
-    assets.recipes = server.load("config/default/data.recipes.ron");
-    assets.texts = server.load("config/default/data.texts.ron");
+    assets.audio = server.load(config_path("config.audio.ron").as_str());
+    assets.debug = server.load(config_path("config.debug.ron").as_str());
+    assets.input = server.load(config_path("config.input.ron").as_str());
+    assets.sim = server.load(config_path("config.sim.ron").as_str());
+    assets.affixes = server.load(config_path("data.affixes.ron").as_str());
+    assets.blueprint = server.load(config_path("data.blueprint.ron").as_str());
+    assets.enemies = server.load(config_path("data.enemies.ron").as_str());
+    assets.items = server.load(config_path("data.items.ron").as_str());
+    assets.layout = server.load(config_path("data.layout.ron").as_str());
+    assets.recipes = server.load(config_path("data.recipes.ron").as_str());
+    assets.locale = server.load(config_path("config.locale.ron").as_str());
+    assets.strings = Language::ALL
+        .iter()
+        .flat_map(|language| {
+            ["texts", "items", "enemies", "ui"].map(|file| {
+                let path = format!("locale/{}/{}.strings.ron", language.code(), file);
+                (*language, server.load(path.as_str()))
+            })
+        })
+        .collect();
 }
 
 pub fn load_assets(
@@ -105,13 +139,16 @@ pub fn add_configs(
     assets: Res<AssetStorage>,
     audio: Res<Assets<AudioConfig>>,
     debug: Res<Assets<DebugConfig>>,
+    input: Res<Assets<InputConfig>>,
     sim: Res<Assets<SimConfig>>,
+    affixes: Res<Assets<AffixesData>>,
     blueprint: Res<Assets<BlueprintData>>,
     enemies: Res<Assets<EnemiesData>>,
     items: Res<Assets<ItemsData>>,
     layout: Res<Assets<LayoutData>>,
     recipes: Res<Assets<RecipesData>>,
-    texts: Res<Assets<TextsData>>,
+    locale: Res<Assets<LocaleConfig>>,
+    strings: Res<Assets<LocaleData>>,
 ) {
     commands.insert_resource(
         audio
@@ -125,29 +162,56 @@ pub fn add_configs(
             .cloned()
             .expect("debug.ron wasn't loaded (yet)!"),
     );
+    commands.insert_resource(
+        input
+            .get(&assets.input)
+            .cloned()
+            .expect("input.ron wasn't loaded (yet)!"),
+    );
     commands.insert_resource(
         sim.get(&assets.sim)
             .cloned()
             .expect("sim.ron wasn't loaded (yet)!"),
     );
+    let affixes = affixes
+        .get(&assets.affixes)
+        .cloned()
+        .expect("affixes.ron wasn't loaded (yet)!");
     commands.insert_resource(
         blueprint
             .get(&assets.blueprint)
             .cloned()
             .expect("blueprint.ron wasn't loaded (yet)!"),
     );
-    commands.insert_resource(
-        enemies
-            .get(&assets.enemies)
-            .cloned()
-            .expect("enemies.ron wasn't loaded (yet)!"),
-    );
-    commands.insert_resource(
-        items
-            .get(&assets.items)
-            .cloned()
-            .expect("items.ron wasn't loaded (yet)!"),
-    );
+    let mut bundles: HashMap<Language, LocaleData> = HashMap::new();
+    for (language, handle) in assets.strings.iter() {
+        if let Some(data) = strings.get(handle) {
+            bundles.entry(*language).or_default().merge(data.clone());
+        }
+    }
+    let mut locale = Locale {
+        language: locale
+            .get(&assets.locale)
+            .expect("locale.ron wasn't loaded (yet)!")
+            .language,
+        bundles,
+        complete: vec![],
+    };
+    let mut enemies = enemies
+        .get(&assets.enemies)
+        .cloned()
+        .expect("enemies.ron wasn't loaded (yet)!");
+    let mut items = items
+        .get(&assets.items)
+        .cloned()
+        .expect("items.ron wasn't loaded (yet)!");
+    locale.complete = log_missing_keys(&locale, &items, &affixes, &enemies);
+    commands.insert_resource(affixes);
+    // Names and descriptions are only in the strings of each language.
+    locale.localize_enemies(&mut enemies);
+    locale.localize_items(&mut items);
+    commands.insert_resource(enemies);
+    commands.insert_resource(items);
     commands.insert_resource(
         layout
             .get(&assets.layout)
@@ -160,10 +224,5 @@ pub fn add_configs(
             .cloned()
             .expect("recipes.ron wasn't loaded (yet)!"),
     );
-    commands.insert_resource(
-        texts
-            .get(&assets.texts)
-            .cloned()
-            .expect("texts.ron wasn't loaded (yet)!"),
-    );
+    commands.insert_resource(locale);
 }
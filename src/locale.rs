@@ -0,0 +1,353 @@
+use std::collections::{BTreeSet, HashMap};
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::config::config_locale::Language;
+use crate::config::data_affixes::AffixesData;
+use crate::config::data_enemies::EnemiesData;
+use crate::config::data_items::ItemsData;
+use crate::config::data_locale::{ItemText, LocaleData, UiText};
+use crate::game::affixes::{AffixId, Affixes};
+use crate::game::combat::{Enemy, EnemyId};
+use crate::game::dungeon_sim::DungeonState;
+use crate::game::sim::dungeon_components::TextType;
+use crate::game::trash::Trash;
+use crate::game::{EquippedItem, Item, ItemId};
+
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_if_resource_exists::<Locale>()
+                .with_system(apply_language)
+                .into(),
+        );
+    }
+}
+
+/// Resource. The strings of every language, and the language that the game is shown in.
+pub struct Locale {
+    pub language: Language,
+    pub bundles: HashMap<Language, LocaleData>,
+    /// The translations that have every string, see `missing_keys`.
+    pub complete: Vec<Language>,
+}
+
+impl Locale {
+    /// Whether the language can be picked on the settings page. Only complete translations are
+    /// offered, so that the game isn't shown in a mix of languages. An incomplete one can still
+    /// be tried out by setting it in config.locale.ron.
+    pub fn offers(&self, language: Language) -> bool {
+        language == Language::English
+            || language == self.language
+            || self.complete.contains(&language)
+    }
+
+    /// Looks a string up in the current language, or in English if it is missing there.
+    fn lookup<'a, T>(&'a self, get: impl Fn(&'a LocaleData) -> Option<&'a T>) -> Option<&'a T> {
+        [self.language, Language::English]
+            .iter()
+            .filter_map(|language| self.bundles.get(language))
+            .find_map(get)
+    }
+
+    pub fn ui(&self, id: UiText) -> String {
+        self.lookup(|bundle| bundle.ui.get(&id))
+            .cloned()
+            .unwrap_or_else(|| format!("{:?}", id))
+    }
+
+    /// A string with its placeholders filled in, see `fill_placeholders`.
+    pub fn ui_with(&self, id: UiText, values: &[(&str, String)]) -> String {
+        fill_placeholders(&self.ui(id), values)
+    }
+
+    /// The lines that one is picked from for a message in the feed.
+    pub fn texts(&self, text_type: TextType) -> &[String] {
+        self.lookup(|bundle| {
+            bundle
+                .texts
+                .get(&text_type)
+                .filter(|lines| !lines.is_empty())
+        })
+        .map(Vec::as_slice)
+        .unwrap_or(&[])
+    }
+
+    pub fn item(&self, id: &ItemId) -> Option<&ItemText> {
+        self.lookup(|bundle| bundle.items.get(id))
+    }
+
+    pub fn enemy(&self, id: &EnemyId) -> Option<&String> {
+        self.lookup(|bundle| bundle.enemies.get(id))
+    }
+
+    pub fn affix(&self, id: AffixId) -> String {
+        self.lookup(|bundle| bundle.affixes.get(&id))
+            .cloned()
+            .unwrap_or_else(|| format!("{:?}", id))
+    }
+
+    /// Puts the names of the affixes around the name of an item, e.g. "Keen Sword of Might".
+    pub fn affixed_name(&self, name: &str, affixes: &Affixes) -> String {
+        let mut name = name.to_string();
+        for (affix, template) in [
+            (affixes.prefix, UiText::AffixPrefix),
+            (affixes.suffix, UiText::AffixSuffix),
+        ] {
+            if let Some(affix) = affix {
+                name = self.ui_with(template, &[("item", name), ("affix", self.affix(affix))]);
+            }
+        }
+        name
+    }
+
+    /// Names and describes the items in the current language.
+    pub fn localize_items(&self, data: &mut ItemsData) {
+        for (_, item) in data.items.iter_mut() {
+            if let Some(text) = self.item(&item.id) {
+                item.name = text.name.clone();
+                item.description = text.description.clone();
+            }
+        }
+    }
+
+    /// Names the enemies in the current language.
+    pub fn localize_enemies(&self, data: &mut EnemiesData) {
+        for enemy in data.enemies.iter_mut() {
+            if let Some(name) = self.enemy(&enemy.enemy_id) {
+                enemy.name = name.clone();
+            }
+        }
+    }
+
+    /// The keys that have no string in the given language, such as "items.HerbRed". A complete
+    /// translation has a string for every item, affix and enemy in the game data, every part of
+    /// the user interface, and every kind of message in English.
+    pub fn missing_keys(
+        &self,
+        language: Language,
+        items: &ItemsData,
+        affixes: &AffixesData,
+        enemies: &EnemiesData,
+    ) -> Vec<String> {
+        let empty = LocaleData::default();
+        let english = self.bundles.get(&Language::English).unwrap_or(&empty);
+        let mut required = keys(english);
+        required.retain(|key| {
+            !key.starts_with("items.")
+                && !key.starts_with("affixes.")
+                && !key.starts_with("enemies.")
+        });
+        required.extend(UiText::ALL.iter().map(|id| format!("ui.{:?}", id)));
+        required.extend(
+            items
+                .items
+                .iter()
+                .map(|(_, item)| format!("items.{}", item.id)),
+        );
+        required.extend(
+            affixes
+                .prefixes
+                .iter()
+                .chain(affixes.suffixes.iter())
+                .map(|affix| format!("affixes.{:?}", affix.id)),
+        );
+        required.extend(
+            enemies
+                .enemies
+                .iter()
+                .map(|enemy| format!("enemies.{:?}", enemy.enemy_id)),
+        );
+        let present = keys(self.bundles.get(&language).unwrap_or(&empty));
+        required.difference(&present).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+impl Locale {
+    /// The English strings, read straight from the assets, for tests of the text that is shown.
+    pub fn english() -> Self {
+        Locale {
+            language: Language::English,
+            bundles: HashMap::from([(Language::English, read_bundle(Language::English))]),
+            complete: vec![Language::English],
+        }
+    }
+}
+
+/// Reads and merges the files of a language in assets/locale.
+#[cfg(test)]
+fn read_bundle(language: Language) -> LocaleData {
+    let mut bundle = LocaleData::default();
+    for file in ["texts", "items", "enemies", "ui"] {
+        let path = format!("assets/locale/{}/{}.strings.ron", language.code(), file);
+        let text = std::fs::read_to_string(&path).unwrap();
+        bundle.merge(ron::from_str(&text).unwrap_or_else(|e| panic!("{}: {}", path, e)));
+    }
+    bundle
+}
+
+/// The keys of all strings in the bundle.
+fn keys(bundle: &LocaleData) -> BTreeSet<String> {
+    let texts = bundle
+        .texts
+        .iter()
+        .filter(|(_, lines)| !lines.is_empty())
+        .map(|(id, _)| format!("texts.{:?}", id));
+    let items = bundle.items.keys().map(|id| format!("items.{}", id));
+    let affixes = bundle.affixes.keys().map(|id| format!("affixes.{:?}", id));
+    let enemies = bundle.enemies.keys().map(|id| format!("enemies.{:?}", id));
+    let ui = bundle.ui.keys().map(|id| format!("ui.{:?}", id));
+    texts
+        .chain(items)
+        .chain(affixes)
+        .chain(enemies)
+        .chain(ui)
+        .collect()
+}
+
+/// Fills in the placeholders in braces, such as "{value}". Placeholders that have no value are
+/// left as they are.
+pub fn fill_placeholders(template: &str, values: &[(&str, String)]) -> String {
+    let mut filled = template.to_string();
+    for (name, value) in values {
+        filled = filled.replace(&format!("{{{}}}", name), value);
+    }
+    filled
+}
+
+/// The names of the placeholders in the string, such as "value" for "{value}".
+pub fn placeholders(template: &str) -> Vec<&str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}'))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Logs the strings that each language is missing, and returns the languages that are complete.
+pub fn log_missing_keys(
+    locale: &Locale,
+    items: &ItemsData,
+    affixes: &AffixesData,
+    enemies: &EnemiesData,
+) -> Vec<Language> {
+    let mut complete = vec![];
+    for language in Language::ALL {
+        let missing = locale.missing_keys(language, items, affixes, enemies);
+        if missing.is_empty() {
+            complete.push(language);
+            continue;
+        }
+        if language == Language::English {
+            error!("Missing English strings: {}", missing.join(", "));
+        } else {
+            warn!(
+                "{} strings are missing in {}, which are shown in English instead: {}",
+                missing.len(),
+                language.name(),
+                missing.join(", ")
+            );
+        }
+    }
+    complete
+}
+
+/// Puts the strings of the chosen language in the game data when the language is changed, and
+/// renames whatever was already made from it: the items in the bag, the trash and the hero's
+/// slots, and the enemies of the current level. The names of affixes are looked up whenever an
+/// item is shown, so they follow along by themselves.
+pub fn apply_language(
+    locale: Res<Locale>,
+    mut items_data: ResMut<ItemsData>,
+    mut enemies_data: ResMut<EnemiesData>,
+    mut enemy: ResMut<Enemy>,
+    mut trash: ResMut<Trash>,
+    dungeon: Option<ResMut<DungeonState>>,
+    mut query_items: Query<&mut Item>,
+    mut query_equipped: Query<&mut EquippedItem>,
+) {
+    // The game data was already localized when it was loaded.
+    if !locale.is_changed() || locale.is_added() {
+        return;
+    }
+    locale.localize_items(&mut items_data);
+    locale.localize_enemies(&mut enemies_data);
+    let rename_item = |item: &mut Item| {
+        if let Some(text) = locale.item(&item.id) {
+            item.name = text.name.clone();
+            item.description = text.description.clone();
+        }
+    };
+    for mut item in query_items.iter_mut() {
+        rename_item(&mut item);
+    }
+    for mut equipped in query_equipped.iter_mut() {
+        rename_item(&mut equipped.item);
+    }
+    for discarded in trash.items.iter_mut() {
+        rename_item(&mut discarded.item);
+    }
+    let rename_enemy = |enemy: &mut Enemy| {
+        if let Some(name) = locale.enemy(&enemy.enemy_id) {
+            enemy.name = name.clone();
+        }
+    };
+    rename_enemy(&mut enemy);
+    if let Some(level) = dungeon.and_then(|dungeon| dungeon.into_inner().current_level.as_mut()) {
+        level.enemies.iter_mut().for_each(rename_enemy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read<T: serde::de::DeserializeOwned>(path: &str) -> T {
+        let text = std::fs::read_to_string(path).unwrap();
+        ron::from_str(&text).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    /// Lists the strings that each translation is still missing. English must be complete.
+    #[test]
+    fn missing_locale_keys() {
+        let locale = Locale {
+            language: Language::English,
+            bundles: Language::ALL
+                .into_iter()
+                .map(|language| (language, read_bundle(language)))
+                .collect(),
+            complete: vec![],
+        };
+        let items: ItemsData = read("assets/config/default/data.items.ron");
+        let affixes: AffixesData = read("assets/config/default/data.affixes.ron");
+        let enemies: EnemiesData = read("assets/config/default/data.enemies.ron");
+        assert_eq!(
+            locale.missing_keys(Language::English, &items, &affixes, &enemies),
+            Vec::<String>::new()
+        );
+        for language in Language::ALL {
+            let missing = locale.missing_keys(language, &items, &affixes, &enemies);
+            println!("{}: {} missing", language.name(), missing.len());
+            for key in missing {
+                println!("    {}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn fills_known_placeholders_only() {
+        assert_eq!(
+            fill_placeholders(
+                "Press {key} to {verb}.",
+                &[("key", "SPACE".to_string()), ("unused", "x".to_string())]
+            ),
+            "Press SPACE to {verb}."
+        );
+    }
+}
@@ -14,19 +14,25 @@ use iyes_loopless::prelude::AppLooplessStateExt;
 use crate::audio::plugin::MyAudioPlugin;
 use crate::config::config_audio::{AudioConfig, AudioConfigLoader};
 use crate::config::config_debug::{DebugConfig, DebugConfigLoader};
+use crate::config::config_input::{InputConfig, InputConfigLoader};
+use crate::config::config_locale::{LocaleConfig, LocaleConfigLoader};
 use crate::config::config_sim::{SimConfig, SimConfigLoader};
+use crate::config::data_affixes::{AffixesData, AffixesDataLoader};
 use crate::config::data_blueprint::{BlueprintData, BlueprintDataLoader};
 use crate::config::data_enemies::{EnemiesData, EnemiesDataLoader};
 use crate::config::data_items::{ItemsData, ItemsDataLoader};
 use crate::config::data_layout::{LayoutData, LayoutDataLoader};
+use crate::config::data_locale::{LocaleData, LocaleDataLoader};
 use crate::config::data_recipes::{RecipesData, RecipesDataLoader};
-use crate::config::data_texts::{TextsData, TextsDataLoader};
 use crate::game::camera::set_cam_scale;
 use crate::game::GamePlugin;
 use crate::game_ended::GameEndedPlugin;
+use crate::input::{track_keyboard_focus, KeyboardFocus};
 use crate::loading::state::LoadingPlugin;
+use crate::locale::LocalePlugin;
 use crate::main_menu::MainMenuPlugin;
 use crate::mouse::{Mouse, MousePlugin};
+use crate::settings::SettingsPlugin;
 use crate::states::{handle_escape, log_state_changes, AppState};
 use crate::transition_state::TransitionPlugin;
 use crate::window_event_handler::handle_window;
@@ -37,10 +43,13 @@ mod config;
 pub mod game;
 mod game_ended;
 mod hud;
+mod input;
 mod loading;
+mod locale;
 mod main_menu;
 mod mouse;
 mod positioning;
+mod settings;
 mod states;
 mod transition_state;
 mod window_event_handler;
@@ -74,12 +83,23 @@ fn main() {
         .add_plugin(TransitionPlugin)
         .add_plugin(GamePlugin)
         .add_plugin(GameEndedPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(LocalePlugin)
+        // Replaced by the loaded config once loading is done:
+        .init_resource::<InputConfig>()
+        .init_resource::<KeyboardFocus>()
         .add_asset::<AudioConfig>()
         .init_asset_loader::<AudioConfigLoader>()
         .add_asset::<DebugConfig>()
         .init_asset_loader::<DebugConfigLoader>()
+        .add_asset::<InputConfig>()
+        .init_asset_loader::<InputConfigLoader>()
+        .add_asset::<LocaleConfig>()
+        .init_asset_loader::<LocaleConfigLoader>()
         .add_asset::<SimConfig>()
         .init_asset_loader::<SimConfigLoader>()
+        .add_asset::<AffixesData>()
+        .init_asset_loader::<AffixesDataLoader>()
         .add_asset::<BlueprintData>()
         .init_asset_loader::<BlueprintDataLoader>()
         .add_asset::<EnemiesData>()
@@ -90,8 +110,9 @@ fn main() {
         .init_asset_loader::<LayoutDataLoader>()
         .add_asset::<RecipesData>()
         .init_asset_loader::<RecipesDataLoader>()
-        .add_asset::<TextsData>()
-        .init_asset_loader::<TextsDataLoader>()
+        .add_asset::<LocaleData>()
+        .init_asset_loader::<LocaleDataLoader>()
+        .add_system_to_stage(CoreStage::PreUpdate, track_keyboard_focus)
         .add_system(handle_window)
         .add_system(log_state_changes)
         .add_system(handle_escape)
@@ -6,11 +6,16 @@ use iyes_loopless::state::NextState;
 use crate::audio::sound_event::SoundEvent;
 use crate::config::data_layout::LayoutData;
 use crate::game::create_backpack::create_layout_background;
+use crate::game::create_widget_encounter::create_layout_encounter;
 use crate::game::create_widget_feed::create_layout_feed;
-use crate::game::create_widget_grids::{create_layout_combine_button, create_layout_grids};
+use crate::game::create_widget_grids::{
+    create_layout_combine_button, create_layout_grids, create_layout_sort_button,
+    create_layout_trash_zone,
+};
 use crate::game::create_widget_hero::create_layout_hero;
 use crate::game::create_widget_music::create_layout_music;
 use crate::game::create_widget_toasts::create_layout_toasts;
+use crate::game::grid_cursor::create_layout_grid_cursor;
 use crate::game::{create_camera, AlbumId, AssetStorage, FontId, MENU_ZOOM};
 use crate::mouse::MouseInteractive;
 use crate::positioning::Depth;
@@ -35,7 +40,11 @@ impl Plugin for MainMenuPlugin {
                     .with_system(create_layout_grids)
                     .with_system(create_layout_toasts)
                     .with_system(create_layout_combine_button)
+                    .with_system(create_layout_sort_button)
+                    .with_system(create_layout_trash_zone)
                     .with_system(create_layout_hero)
+                    .with_system(create_layout_encounter)
+                    .with_system(create_layout_grid_cursor)
                     .with_system(init_menu)
                     .with_system(play_menu_music.run_if(should_play_music_right_away))
                     .into(),
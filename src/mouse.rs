@@ -1,9 +1,20 @@
 use bevy::prelude::*;
+use bevy_egui::EguiContext;
 use iyes_loopless::prelude::ConditionSet;
 
+use crate::config::config_input::InputAction;
 use crate::game::camera::GameCamera;
+use crate::input::Actions;
+use crate::positioning::{Coords, GridData, Pos};
 use crate::Update;
 
+/// How far the mouse has to move while the Select button is held down on something before it
+/// starts being dragged, in grid cells.
+const DRAG_DISTANCE: f32 = 0.3;
+/// How long the Select button has to be held down on something before it starts being dragged,
+/// even if the mouse doesn't move, in seconds.
+const DRAG_DELAY: f64 = 0.25;
+
 #[derive(Default)]
 pub struct Mouse {
     /// Position in world coordinates.
@@ -15,13 +26,31 @@ pub struct Mouse {
     /// Whether or not the mouse is currently in a dragging operation.
     ///
     /// It is handy to store this separately, rather than relying on whether or not the LMB is
-    /// held down, because this way we could add an accessibility mode that
-    /// allows click-to-start-dragging, click-to-stop-dragging.
+    /// held down, because this way dragging can also be started and stopped with the Grab
+    /// action. See `click_to_drag`.
     pub is_dragging: bool,
+    /// Whether the current drag was started with the Grab action (Enter by default), rather than
+    /// by holding down the Select button. Such a drag ends when Grab is triggered again, instead
+    /// of when the Select button is let go of.
+    pub click_to_drag: bool,
+    /// The Select button was pressed down on this entity, but it's too early to tell whether
+    /// this is a click or the start of a drag.
+    pub press: Option<MousePress>,
+    /// Where the mouse was when the current drag was started: where the Select button was
+    /// pressed, or where Grab was triggered.
+    pub drag_from: Vec2,
     pub disabled: bool,
     pub out_of_bounds: bool,
 }
 
+#[derive(Debug, Copy, Clone)]
+pub struct MousePress {
+    pub entity: Entity,
+    pub position: Vec2,
+    /// When the button was pressed, in seconds since startup.
+    pub time: f64,
+}
+
 /// Any entity that that be interacted with by the mouse.
 #[derive(Component)]
 pub struct MouseInteractive {
@@ -33,10 +62,16 @@ pub struct MouseInteractive {
     /// Whether the mouse is currently hovering over this entity.
     pub hovered: bool,
     /// Whether the mouse just clicked this entity.
-    /// (Set to true upon signal going up, aka the LMB going down.)
+    /// (Set to true upon signal going up, aka the Select or Grab button going down.)
     pub clicked: bool,
+    /// Whether the Use action was just triggered on this entity (LShift + Click by default).
     pub shift_clicked: bool,
+    /// Whether the Delete action was just triggered on this entity (LCtrl + LAlt + Click by
+    /// default).
     pub ctrl_alt_clicked: bool,
+    /// Whether this entity should start being dragged: the Select button was pressed on it and
+    /// then held down past the drag threshold, or Grab was triggered on it.
+    pub drag_started: bool,
 }
 
 impl MouseInteractive {
@@ -48,6 +83,7 @@ impl MouseInteractive {
             clicked: false,
             shift_clicked: false,
             ctrl_alt_clicked: false,
+            drag_started: false,
         }
     }
 }
@@ -61,11 +97,17 @@ impl Plugin for MousePlugin {
             .add_stage_before("mouse_2", "mouse_1", SystemStage::parallel())
             .add_system_set_to_stage(
                 "mouse_1",
-                ConditionSet::new().with_system(calc_mouse_pos).into(),
+                ConditionSet::new()
+                    .label("calc_mouse_pos")
+                    .with_system(calc_mouse_pos)
+                    .into(),
             )
             .add_system_set_to_stage(
                 "mouse_2",
-                ConditionSet::new().with_system(track_mouse_hover).into(),
+                ConditionSet::new()
+                    .label("track_mouse_hover")
+                    .with_system(track_mouse_hover)
+                    .into(),
             )
             .add_system_set_to_stage(
                 Update,
@@ -113,29 +155,58 @@ pub fn calc_mouse_pos(
 }
 
 /// Runs on a separate stage after cal_mouse_pos but before everything else.
+/// Entities on the grid with an irregular shape are only hovered over their occupied cells.
+/// Also tells clicks apart from drags: see `MouseInteractive::drag_started`.
 pub fn track_mouse_hover(
-    input: Res<Input<MouseButton>>,
-    keys: Res<Input<KeyCode>>,
-    mouse: Res<Mouse>,
-    mut query: Query<(&mut MouseInteractive, &GlobalTransform, &Visibility)>,
+    time: Res<Time>,
+    actions: Actions,
+    mut egui_context: Option<ResMut<EguiContext>>,
+    mut mouse: ResMut<Mouse>,
+    grid: Option<Res<GridData>>,
+    mut query: Query<(
+        Entity,
+        &mut MouseInteractive,
+        &GlobalTransform,
+        &Visibility,
+        Option<&Coords>,
+    )>,
 ) {
+    // Nothing in the game is hovered while the cursor is over a window such as the settings page.
+    let over_ui = egui_context
+        .as_mut()
+        .map(|context| context.ctx_mut().is_pointer_over_area())
+        .unwrap_or(false);
+    // Grab only picks things up here. Dropping them again is up to check_drag_end.
+    let grabbed = !mouse.is_dragging && actions.just_pressed(InputAction::Grab);
+    if grabbed {
+        mouse.click_to_drag = true;
+    } else if !mouse.is_dragging && actions.just_pressed(InputAction::Select) {
+        mouse.click_to_drag = false;
+    }
     let (highest_z, nr_items) = query.iter_mut().fold(
         (-1000. as f32, 0),
-        |(highest_z, nr_items), (mut interactive, transform, visibility)| {
+        |(highest_z, nr_items), (_, mut interactive, transform, visibility, coords)| {
+            let over_shape = match (coords, &grid) {
+                (Some(coords), Some(grid)) if coords.mask.is_some() => {
+                    coords.occupies(&Pos::from(mouse.position - grid.offset))
+                }
+                _ => true,
+            };
             interactive.hovered = !mouse.disabled
+                && !over_ui
                 && visibility.is_visible
+                && over_shape
                 && mouse.position.x > transform.translation().x - interactive.size.x * 0.5
                 && mouse.position.x < transform.translation().x + interactive.size.x * 0.5
                 && mouse.position.y > transform.translation().y - interactive.size.y * 0.5
                 && mouse.position.y < transform.translation().y + interactive.size.y * 0.5;
-            interactive.clicked = interactive.hovered && input.just_pressed(MouseButton::Left);
-            interactive.shift_clicked = interactive.hovered
-                && input.just_pressed(MouseButton::Left)
-                && keys.pressed(KeyCode::LShift);
-            interactive.ctrl_alt_clicked = interactive.hovered
-                && input.just_pressed(MouseButton::Left)
-                && keys.pressed(KeyCode::LControl)
-                && keys.pressed(KeyCode::LAlt);
+            interactive.clicked =
+                interactive.hovered && (grabbed || actions.just_pressed(InputAction::Select));
+            interactive.shift_clicked =
+                interactive.hovered && actions.just_pressed(InputAction::Use);
+            interactive.ctrl_alt_clicked =
+                interactive.hovered && actions.just_pressed(InputAction::Delete);
+            interactive.drag_started = false;
             if interactive.hovered {
                 (highest_z.max(transform.translation().z), nr_items + 1)
             } else {
@@ -151,15 +222,59 @@ pub fn track_mouse_hover(
         );
         query
             .iter_mut()
-            .filter(|(interactive, transform, _)| {
+            .filter(|(_, interactive, transform, _, _)| {
                 interactive.hovered && (transform.translation().z - highest_z).abs() > f32::EPSILON
             })
-            .for_each(|(mut interactive, transform, _)| {
+            .for_each(|(_, mut interactive, transform, _, _)| {
                 trace!("Culling {:?}", transform.translation().z);
                 interactive.hovered = false;
                 interactive.clicked = false;
             });
     }
+    if mouse.is_dragging {
+        mouse.press = None;
+        return;
+    }
+    let now = time.seconds_since_startup();
+    let pressed = query
+        .iter()
+        .find(|(_, interactive, ..)| {
+            interactive.clicked && !interactive.shift_clicked && !interactive.ctrl_alt_clicked
+        })
+        .map(|(entity, ..)| entity);
+    let drag_started = match (pressed, mouse.press) {
+        // Grab doesn't need to be held down, so there is nothing to wait for.
+        (Some(entity), _) if grabbed => Some(entity),
+        (Some(entity), _) => {
+            mouse.press = Some(MousePress {
+                entity,
+                position: mouse.position,
+                time: now,
+            });
+            None
+        }
+        (None, Some(_)) if !actions.pressed(InputAction::Select) => {
+            // Let go of before the threshold, so it was just a click.
+            mouse.press = None;
+            None
+        }
+        (None, Some(press))
+            if mouse.position.distance(press.position) > DRAG_DISTANCE
+                || now - press.time > DRAG_DELAY =>
+        {
+            Some(press.entity)
+        }
+        _ => None,
+    };
+    if let Some(entity) = drag_started {
+        mouse.drag_from = match mouse.press.take() {
+            Some(press) => press.position,
+            None => mouse.position,
+        };
+        if let Ok((_, mut interactive, ..)) = query.get_mut(entity) {
+            interactive.drag_started = true;
+        }
+    }
 }
 
 pub fn set_cursor_appearance(
@@ -20,19 +20,109 @@ pub struct Coords {
     pub pos: Pos,
     /// Width and height of the entity.
     pub dimens: Dimens,
+    /// Which cells within the rectangle are actually occupied, for entities that aren't a filled
+    /// rectangle. Bit `y * MASK_WIDTH + x` stands for the cell at (x, y), relative to the
+    /// bottom-left corner. None means that every cell is occupied.
+    #[serde(default)]
+    pub mask: Option<u64>,
 }
 
+/// The widest (and tallest) shape that fits in a Coords mask.
+pub const MASK_WIDTH: i32 = 8;
+
 impl Coords {
     pub fn new(pos: Pos, dimens: Dimens) -> Self {
-        Coords { pos, dimens }
+        Coords {
+            pos,
+            dimens,
+            mask: None,
+        }
+    }
+
+    /// Only the given cells, relative to the bottom-left corner, are occupied.
+    /// Cells outside of the dimens are ignored.
+    ///
+    /// If the dimens are wider or taller than MASK_WIDTH, the cells wouldn't fit in the mask, so
+    /// the whole rectangle stays occupied instead.
+    #[must_use]
+    pub fn with_cells(mut self, cells: &[Pos]) -> Self {
+        if !Self::fits_mask(self.dimens) {
+            return self;
+        }
+        let mut mask: u64 = 0;
+        for cell in cells {
+            if cell.x >= 0 && cell.x < self.dimens.x && cell.y >= 0 && cell.y < self.dimens.y {
+                mask |= 1 << (cell.y * MASK_WIDTH + cell.x);
+            }
+        }
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Return true iff a shape with the given dimens fits in a mask.
+    pub fn fits_mask(dimens: Dimens) -> bool {
+        dimens.x <= MASK_WIDTH && dimens.y <= MASK_WIDTH
+    }
+
+    /// Return true iff the given absolute position is one of the occupied cells.
+    pub fn occupies(&self, pos: &Pos) -> bool {
+        let x = pos.x - self.pos.x;
+        let y = pos.y - self.pos.y;
+        if x < 0 || x >= self.dimens.x || y < 0 || y >= self.dimens.y {
+            return false;
+        }
+        match self.mask {
+            Some(mask) => mask & (1 << (y * MASK_WIDTH + x)) != 0,
+            None => true,
+        }
+    }
+
+    /// All occupied cells, in absolute positions.
+    pub fn cells(&self) -> impl Iterator<Item = Pos> + '_ {
+        (0..self.dimens.y)
+            .flat_map(move |y| (0..self.dimens.x).map(move |x| self.pos.plus_xy(x, y)))
+            .filter(|pos| self.occupies(pos))
+    }
+
+    /// Returns the same shape after a quarter turn, clockwise or counter-clockwise. The
+    /// bottom-left corner stays where it is.
+    #[must_use]
+    pub fn turned(&self, clockwise: bool) -> Self {
+        let mut turned = Coords::new(self.pos, self.dimens.swapped());
+        if self.mask.is_some() {
+            let cells: Vec<Pos> = self
+                .cells()
+                .map(|pos| {
+                    let x = pos.x - self.pos.x;
+                    let y = pos.y - self.pos.y;
+                    if clockwise {
+                        Pos::new(y, self.dimens.x - 1 - x)
+                    } else {
+                        Pos::new(self.dimens.y - 1 - y, x)
+                    }
+                })
+                .collect();
+            turned = turned.with_cells(&cells);
+        }
+        turned
     }
 
     /// Return true iff the two Coords rectangles overlap at all.
+    /// If either has a mask, they only overlap if they share an occupied cell.
     pub fn overlaps(&self, other: &Coords) -> bool {
-        self.pos.x < other.pos.x + other.dimens.x
+        let rects_overlap = self.pos.x < other.pos.x + other.dimens.x
             && self.pos.x + self.dimens.x > other.pos.x
             && self.pos.y < other.pos.y + other.dimens.y
-            && self.pos.y + self.dimens.y > other.pos.y
+            && self.pos.y + self.dimens.y > other.pos.y;
+        if !rects_overlap || (self.mask.is_none() && other.mask.is_none()) {
+            return rects_overlap;
+        }
+        self.cells().any(|pos| other.occupies(&pos))
+    }
+
+    /// The occupied cells that the two Coords have in common, in absolute positions.
+    pub fn overlapping_cells(&self, other: &Coords) -> Vec<Pos> {
+        self.cells().filter(|pos| other.occupies(pos)).collect()
     }
     pub fn overlaps_pos(&self, other: &Pos) -> bool {
         let other = Coords::new(*other, Dimens::new(1, 1));
@@ -44,7 +134,11 @@ impl Coords {
     }
     /// Return true iff the given 'other' Coords is completely enclosed by this Coords.
     /// Two Coords that are equal will enclose each other.
+    /// If either has a mask, every occupied cell of 'other' must be an occupied cell of this one.
     pub fn encloses(&self, other: &Coords) -> bool {
+        if self.mask.is_some() || other.mask.is_some() {
+            return other.cells().all(|pos| self.occupies(&pos));
+        }
         self.pos.x <= other.pos.x
             && self.pos.x + self.dimens.x >= other.pos.x + other.dimens.x
             && self.pos.y <= other.pos.y
@@ -74,3 +168,94 @@ impl Coords {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An L-shape in a 2 by 2 square, with the top-right cell left empty.
+    fn l_shape(pos: Pos) -> Coords {
+        Coords::new(pos, Dimens::new(2, 2)).with_cells(&[
+            Pos::new(0, 0),
+            Pos::new(1, 0),
+            Pos::new(0, 1),
+        ])
+    }
+
+    #[test]
+    fn masked_shapes_only_overlap_on_occupied_cells() {
+        let l = l_shape(Pos::new(0, 0));
+        assert!(!l.overlaps(&Coords::new(Pos::new(1, 1), Dimens::unit())));
+        assert!(l.overlaps(&Coords::new(Pos::new(1, 0), Dimens::unit())));
+        // Two L-shapes can interlock when one is turned upside down.
+        let upside_down = l_shape(Pos::new(1, 1)).turned(true).turned(true);
+        assert!(!l.overlaps(&upside_down));
+        assert!(l.overlaps(&l_shape(Pos::new(1, 0))));
+    }
+
+    #[test]
+    fn enclosing_ignores_empty_cells() {
+        let grid = Coords::new(Pos::new(0, 0), Dimens::new(2, 2));
+        assert!(grid.encloses(&l_shape(Pos::new(0, 0))));
+        // The empty top-right cell may hang outside of the grid.
+        let hanging = Coords::new(Pos::new(0, 0), Dimens::new(3, 2)).with_cells(&[
+            Pos::new(0, 0),
+            Pos::new(1, 0),
+            Pos::new(0, 1),
+        ]);
+        assert!(grid.encloses(&hanging));
+        assert!(!grid.encloses(&l_shape(Pos::new(1, 0))));
+    }
+
+    #[test]
+    fn masks_hold_shapes_up_to_the_mask_width() {
+        let corners = [
+            Pos::new(0, 0),
+            Pos::new(MASK_WIDTH - 1, 0),
+            Pos::new(0, MASK_WIDTH - 1),
+            Pos::new(MASK_WIDTH - 1, MASK_WIDTH - 1),
+        ];
+        let shape =
+            Coords::new(Pos::new(0, 0), Dimens::new(MASK_WIDTH, MASK_WIDTH)).with_cells(&corners);
+        let mut cells: Vec<Pos> = shape.cells().collect();
+        cells.sort();
+        let mut expected = corners.to_vec();
+        expected.sort();
+        assert_eq!(cells, expected);
+        // The cell after the end of the bottom row must not alias the start of the next one.
+        assert!(!shape.occupies(&Pos::new(1, 1)));
+        assert!(!shape.occupies(&Pos::new(MASK_WIDTH, 0)));
+    }
+
+    #[test]
+    fn shapes_wider_than_the_mask_fill_their_rectangle() {
+        let shape = Coords::new(Pos::new(0, 0), Dimens::new(MASK_WIDTH + 1, 1))
+            .with_cells(&[Pos::new(MASK_WIDTH, 0)]);
+        assert_eq!(shape.mask, None);
+        assert!(shape.occupies(&Pos::new(0, 0)));
+    }
+
+    #[test]
+    fn turning_swaps_dimens_and_moves_cells_clockwise() {
+        // A vertical bar with a notch to the right at the top, shaped like a 'Γ'.
+        let shape = Coords::new(Pos::new(0, 0), Dimens::new(2, 3)).with_cells(&[
+            Pos::new(0, 0),
+            Pos::new(0, 1),
+            Pos::new(0, 2),
+            Pos::new(1, 2),
+        ]);
+        let turned = shape.turned(true);
+        assert_eq!(turned.dimens, Dimens::new(3, 2));
+        let mut cells: Vec<Pos> = turned.cells().collect();
+        cells.sort();
+        let mut expected = vec![
+            Pos::new(0, 1),
+            Pos::new(1, 1),
+            Pos::new(2, 1),
+            Pos::new(2, 0),
+        ];
+        expected.sort();
+        assert_eq!(cells, expected);
+        assert_eq!(turned.turned(false), shape);
+    }
+}
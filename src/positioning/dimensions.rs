@@ -37,6 +37,12 @@ impl Dimens {
     pub fn plus_xy(self, x: i32, y: i32) -> Self {
         Dimens::new(self.x + x, self.y + y)
     }
+
+    /// The dimensions after a quarter turn: width and height are swapped.
+    #[must_use]
+    pub fn swapped(self) -> Self {
+        Dimens::new(self.y, self.x)
+    }
 }
 
 impl Deref for Dimens {
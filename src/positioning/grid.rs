@@ -1,13 +1,37 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::positioning::Coords;
+use crate::game::ItemKind;
+use crate::positioning::{Coords, Pos};
 
+#[derive(Clone)]
 pub struct GridData {
     /// The translation offset of the grid.
     /// Add this to the Coords of items on the grid to get their translation.
     pub offset: Vec2,
     pub inventory: Coords,
     pub crafting: Coords,
+    /// Separate grids that bag upgrades have attached to the inventory.
+    pub pockets: Vec<Pocket>,
+}
+
+/// A separate grid that belongs to the bag. Items are stored in it just like in the inventory,
+/// but it may only accept a certain kind of item.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pocket {
+    pub coords: Coords,
+    /// If present, only items of this kind fit in the pocket.
+    #[serde(default)]
+    pub only: Option<ItemKind>,
+}
+
+impl Pocket {
+    pub fn accepts(&self, kind: ItemKind) -> bool {
+        match self.only {
+            Some(only) => only == kind,
+            None => true,
+        }
+    }
 }
 
 impl GridData {
@@ -23,4 +47,44 @@ impl GridData {
     pub fn center_crafting(&self) -> Vec2 {
         Vec2::new(self.calc_x(&self.crafting), self.calc_y(&self.crafting))
     }
+    /// The areas where an item of the given kind can be stored, in order of preference: pockets
+    /// that are meant for this kind of item, then the inventory, then any other pockets.
+    pub fn storage_for(&self, kind: ItemKind) -> Vec<Coords> {
+        let dedicated = self
+            .pockets
+            .iter()
+            .filter(|pocket| pocket.only == Some(kind));
+        let general = self.pockets.iter().filter(|pocket| pocket.only.is_none());
+        dedicated
+            .map(|pocket| pocket.coords)
+            .chain(std::iter::once(self.inventory))
+            .chain(general.map(|pocket| pocket.coords))
+            .collect()
+    }
+    /// Returns true iff an item of the given kind can be stored at the given Coords, which must
+    /// lie entirely within the inventory or a single pocket that accepts it.
+    pub fn can_store(&self, coords: &Coords, kind: ItemKind) -> bool {
+        self.storage_for(kind)
+            .iter()
+            .any(|area| area.encloses(coords))
+    }
+    /// Returns true iff the given Coords lie entirely within the inventory or a single pocket,
+    /// regardless of what is allowed in the pocket.
+    pub fn in_storage(&self, coords: &Coords) -> bool {
+        self.inventory.encloses(coords)
+            || self
+                .pockets
+                .iter()
+                .any(|pocket| pocket.coords.encloses(coords))
+    }
+    /// Returns true iff an item of the given kind may occupy the given cell, either for storage
+    /// or for crafting.
+    pub fn accepts_cell(&self, pos: &Pos, kind: ItemKind) -> bool {
+        self.crafting.occupies(pos)
+            || self.inventory.occupies(pos)
+            || self
+                .pockets
+                .iter()
+                .any(|pocket| pocket.accepts(kind) && pocket.coords.occupies(pos))
+    }
 }
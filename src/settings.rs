@@ -0,0 +1,292 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::config::config_input::{Binding, InputAction, InputButton, InputConfig};
+use crate::config::config_locale::{Language, LocaleConfig};
+use crate::config::data_locale::UiText;
+use crate::input::KeyboardFocus;
+use crate::locale::Locale;
+
+/// Keys that can be held down while pressing another key or mouse button.
+const MODIFIERS: [KeyCode; 8] = [
+    KeyCode::LShift,
+    KeyCode::RShift,
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::LAlt,
+    KeyCode::RAlt,
+    KeyCode::LWin,
+    KeyCode::RWin,
+];
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsPage>()
+            .add_system(toggle_settings)
+            .add_system(draw_settings_page);
+    }
+}
+
+/// Resource. The state of the settings page, where the language is picked and the controls can be
+/// rebound.
+#[derive(Default)]
+pub struct SettingsPage {
+    pub open: bool,
+    /// The action that the next key or mouse button is bound to.
+    pub rebinding: Option<Rebinding>,
+    /// Set for a frame after binding, so that the new key doesn't also trigger its action.
+    just_bound: bool,
+    message: Option<String>,
+}
+
+impl SettingsPage {
+    /// While rebinding, keys and mouse buttons don't trigger any actions.
+    pub fn is_rebinding(&self) -> bool {
+        self.rebinding.is_some() || self.just_bound
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rebinding {
+    pub action: InputAction,
+    /// Replace the existing bindings, rather than adding to them.
+    pub replace: bool,
+}
+
+pub fn toggle_settings(
+    config: Res<InputConfig>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    focus: Res<KeyboardFocus>,
+    mut page: ResMut<SettingsPage>,
+) {
+    if !page.is_rebinding()
+        && !focus.0
+        && config.just_pressed(InputAction::OpenSettings, &keys, &buttons)
+    {
+        page.open = !page.open;
+        page.message = None;
+    }
+}
+
+/// The binding that the player just pressed, if any: a key or mouse button along with the
+/// modifiers that are held down. A modifier on its own is bound when it is let go of.
+pub fn captured_binding(
+    keys: &Input<KeyCode>,
+    buttons: &Input<MouseButton>,
+    allow_mouse: bool,
+) -> Option<Binding> {
+    let button = keys
+        .get_just_pressed()
+        .find(|key| !MODIFIERS.contains(key))
+        .map(|key| InputButton::Key(*key))
+        .or_else(|| {
+            buttons
+                .get_just_pressed()
+                .next()
+                .filter(|_| allow_mouse)
+                .map(|button| InputButton::Mouse(*button))
+        });
+    match button {
+        Some(button) => {
+            let held = MODIFIERS
+                .iter()
+                .filter(|key| keys.pressed(**key))
+                .copied()
+                .collect();
+            Some(Binding::held(held, button))
+        }
+        None => keys
+            .get_just_released()
+            .find(|key| MODIFIERS.contains(key))
+            .map(|key| Binding::new(InputButton::Key(*key))),
+    }
+}
+
+/// Draws the settings page, and binds the next key or mouse button while rebinding. Clicks on the
+/// page itself are never bound, so that the buttons on it keep working.
+pub fn draw_settings_page(
+    mut egui_context: ResMut<EguiContext>,
+    mut page: ResMut<SettingsPage>,
+    mut config: ResMut<InputConfig>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    mut locale: Option<ResMut<Locale>>,
+) {
+    if page.just_bound {
+        page.just_bound = false;
+    }
+    if !page.open {
+        return;
+    }
+    let ctx = egui_context.ctx_mut();
+    if let Some(rebinding) = page.rebinding {
+        if let Some(binding) = captured_binding(&keys, &buttons, !ctx.is_pointer_over_area()) {
+            let mut bindings = if rebinding.replace {
+                vec![]
+            } else {
+                config.bindings(rebinding.action)
+            };
+            if !bindings.contains(&binding) {
+                bindings.push(binding);
+            }
+            config.bindings.insert(rebinding.action, bindings);
+            page.rebinding = None;
+            page.just_bound = true;
+        }
+    }
+    let page = &mut *page;
+    // The page can be opened while the strings are still loading.
+    let text_with = |id: UiText, values: &[(&str, String)]| match &locale {
+        Some(locale) => locale.ui_with(id, values),
+        None => format!("{:?}", id),
+    };
+    let text = |id: UiText| text_with(id, &[]);
+    let current_language = locale
+        .as_ref()
+        .map(|locale| locale.language)
+        .unwrap_or_default();
+    let mut language = current_language;
+    egui::Window::new(text(UiText::Settings))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+        .show(ctx, |ui| {
+            ui.heading(text(UiText::SettingsLanguage));
+            egui::ComboBox::from_id_source("language")
+                .selected_text(language.name())
+                .show_ui(ui, |ui| {
+                    let offered = Language::ALL.into_iter().filter(|option| match &locale {
+                        Some(locale) => locale.offers(*option),
+                        None => *option == Language::English,
+                    });
+                    for option in offered {
+                        ui.selectable_value(&mut language, option, option.name());
+                    }
+                });
+            ui.separator();
+            ui.heading(text(UiText::SettingsControls));
+            egui::Grid::new("bindings")
+                .striped(true)
+                .num_columns(5)
+                .show(ui, |ui| {
+                    for action in InputAction::ALL {
+                        ui.label(text(action.ui_text()));
+                        match page.rebinding {
+                            Some(rebinding) if rebinding.action == action => {
+                                ui.label(text(UiText::SettingsPressAKey));
+                            }
+                            _ => {
+                                let names: Vec<String> =
+                                    config.bindings(action).iter().map(Binding::name).collect();
+                                ui.label(names.join(", "));
+                            }
+                        }
+                        if ui.button(text(UiText::SettingsRebind)).clicked() {
+                            page.rebinding = Some(Rebinding {
+                                action,
+                                replace: true,
+                            });
+                        }
+                        if ui.button(text(UiText::SettingsAdd)).clicked() {
+                            page.rebinding = Some(Rebinding {
+                                action,
+                                replace: false,
+                            });
+                        }
+                        if ui.button(text(UiText::SettingsReset)).clicked() {
+                            config.bindings.remove(&action);
+                        }
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+            ui.horizontal(|ui| {
+                if page.rebinding.is_some() && ui.button(text(UiText::SettingsCancel)).clicked() {
+                    page.rebinding = None;
+                }
+                if ui.button(text(UiText::SettingsSave)).clicked() {
+                    let saved = save_config(&*config, "config.input.ron").and_then(|_| {
+                        save_config(
+                            &LocaleConfig {
+                                language: current_language,
+                            },
+                            "config.locale.ron",
+                        )
+                    });
+                    page.message = Some(match saved {
+                        Ok(directory) => text_with(UiText::SettingsSaved, &[("path", directory)]),
+                        Err(Some(error)) => {
+                            text_with(UiText::SettingsSaveFailed, &[("error", error)])
+                        }
+                        Err(None) => text(UiText::SettingsCantSave),
+                    });
+                }
+                if ui.button(text(UiText::SettingsResetAll)).clicked() {
+                    config.bindings.clear();
+                }
+                if ui.button(text(UiText::SettingsClose)).clicked() {
+                    page.open = false;
+                    page.rebinding = None;
+                    page.message = None;
+                }
+            });
+            if let Some(message) = &page.message {
+                ui.label(message);
+            }
+        });
+    if language != current_language {
+        if let Some(locale) = locale.as_mut() {
+            locale.language = language;
+        }
+    }
+}
+
+/// Writes a config file to the override directory, from where it is loaded next time. Returns
+/// the directory, or what went wrong. There is no error if the settings can't be saved at all.
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+fn save_config<T: serde::Serialize>(config: &T, file: &str) -> Result<String, Option<String>> {
+    let path = crate::loading::systems::override_path(file);
+    let ron = ron::ser::to_string_pretty(config, ron::ser::PrettyConfig::default())
+        .map_err(|e| Some(e.to_string()))?;
+    std::fs::write(&path, ron).map_err(|e| Some(e.to_string()))?;
+    Ok(path.parent().unwrap_or(&path).display().to_string())
+}
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+fn save_config<T: serde::Serialize>(_config: &T, _file: &str) -> Result<String, Option<String>> {
+    Err(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binds_keys_along_with_held_modifiers() {
+        let mut keys = Input::<KeyCode>::default();
+        let mut buttons = Input::<MouseButton>::default();
+        keys.press(KeyCode::LShift);
+        // Modifiers aren't bound while they are held down...
+        assert_eq!(captured_binding(&keys, &buttons, true), None);
+        keys.clear();
+        buttons.press(MouseButton::Right);
+        assert_eq!(
+            captured_binding(&keys, &buttons, true),
+            Some(Binding::held(
+                vec![KeyCode::LShift],
+                InputButton::Mouse(MouseButton::Right)
+            ))
+        );
+        assert_eq!(captured_binding(&keys, &buttons, false), None);
+        // ...but on their own when let go of.
+        buttons.clear();
+        keys.release(KeyCode::LShift);
+        assert_eq!(
+            captured_binding(&keys, &buttons, true),
+            Some(Binding::new(InputButton::Key(KeyCode::LShift)))
+        );
+    }
+}
@@ -3,6 +3,8 @@ use bevy::prelude::*;
 use iyes_loopless::prelude::CurrentState;
 use iyes_loopless::state::NextState;
 
+use crate::config::config_input::InputAction;
+use crate::input::ConsumingActions;
 use crate::main_menu::MenuBackpack;
 use crate::transition_state::MenuTransition;
 
@@ -40,9 +42,9 @@ pub fn handle_escape(
     mut query: Query<&mut MenuBackpack>,
     mut exit: EventWriter<AppExit>,
     state: Res<CurrentState<AppState>>,
-    mut keys: ResMut<Input<KeyCode>>,
+    mut actions: ConsumingActions,
 ) {
-    if keys.clear_just_pressed(KeyCode::Escape) {
+    if actions.just_pressed(InputAction::Back) {
         match state.0 {
             AppState::MainMenu => {
                 exit.send(AppExit);
@@ -1,11 +1,14 @@
 use bevy::prelude::*;
 use bevy::window::WindowMode;
 
+use crate::config::config_input::InputAction;
+use crate::input::ConsumingActions;
+
 /// Handle some general behaviour related to the window that should be executed in any State.
-pub fn handle_window(mut keys: ResMut<Input<KeyCode>>, mut windows: ResMut<Windows>) {
+pub fn handle_window(mut actions: ConsumingActions, mut windows: ResMut<Windows>) {
     let primary = windows.primary_mut();
     // Toggle fullscreen:
-    if keys.clear_just_pressed(KeyCode::F11) {
+    if actions.just_pressed(InputAction::ToggleFullscreen) {
         primary.set_mode(if primary.mode() != WindowMode::Windowed {
             WindowMode::Windowed
         } else {